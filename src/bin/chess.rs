@@ -0,0 +1,269 @@
+// Batch jobs on FEN and PGN without writing a program for each:
+//
+//     chess validate-fen              FENs from stdin, one per line
+//     chess perft FEN DEPTH           node counts per move and in total
+//     chess pgn-to-fen [FILE...]      every position of every game
+//     chess pgn-stats [FILE...]       games, results and lengths
+//     chess convert san|uci|coord [--fen FEN] [MOVE...]
+//
+// PGN comes from stdin when no file is given, and so do the moves for
+// `convert`. Moves may be in any of the three notations.
+extern crate chess;
+extern crate nom;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::process;
+
+use chess::castle;
+use chess::color::Color;
+use chess::kind::PAWN;
+use chess::pgn::{Game, parse_games, result_str};
+use chess::position::{Position, parse_position};
+use chess::retro_check::retro_check;
+use chess::rules::Rules;
+
+const USAGE: &'static str = "\
+usage: chess validate-fen
+       chess perft FEN DEPTH
+       chess pgn-to-fen [FILE...]
+       chess pgn-stats [FILE...]
+       chess convert san|uci|coord [--fen FEN] [MOVE...]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("validate-fen") => validate_fen(),
+        Some("perft") => perft(&args[1..]),
+        Some("pgn-to-fen") => pgn_to_fen(&args[1..]),
+        Some("pgn-stats") => pgn_stats(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    }
+}
+
+// Exit codes: 0 when all is fine, 1 when some input is bad,
+// 2 (the error) when the job could not be done at all
+type Exit = Result<i32, String>;
+
+fn parse_fen(fen: &str) -> Result<Position, String> {
+    if fen.trim().is_empty() {
+        return Err("empty FEN".to_string());
+    }
+    match parse_position(fen.trim().as_bytes()) {
+        nom::IResult::Done(_, p) => Ok(p),
+        _ => Err(format!("can not parse '{}'", fen.trim())),
+    }
+}
+
+fn validate_fen() -> Exit {
+    let stdin = io::stdin();
+    let mut code = 0;
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let fen = line.trim();
+        if fen.is_empty() || fen.starts_with('#') {
+            continue;
+        }
+        let report = match parse_fen(fen) {
+            Err(e) => e,
+            Ok(p) => {
+                // the retrograde checks know standard chess only
+                let problems = if p.rules == Rules::Standard {
+                    retro_check(&p).iter().map(|r| format!("{:?}", r)).collect()
+                } else if p.validate().is_empty() {
+                    vec![]
+                } else {
+                    vec![format!("{:?}", p.validate())]
+                };
+                if problems.is_empty() {
+                    "ok".to_string()
+                } else {
+                    problems.join("; ")
+                }
+            }
+        };
+        if report != "ok" {
+            code = 1;
+        }
+        println!("{}: {}", fen, report);
+    }
+    Ok(code)
+}
+
+fn perft(args: &[String]) -> Exit {
+    if args.len() != 2 {
+        return Err(USAGE.to_string());
+    }
+    let p = parse_fen(&args[0])?;
+    if !p.validate().is_empty() {
+        return Err(format!("invalid position: {:?}", p.validate()));
+    }
+    let depth: u32 = args[1].parse().map_err(|_| format!("bad depth '{}'", args[1]))?;
+    if depth == 0 {
+        println!("nodes 1");
+        return Ok(0);
+    }
+    let mut divide: Vec<(String, u64)> = p.legal_moves()
+        .into_iter()
+        .map(|mv| (p.uci(mv), p.make_move(mv).perft(depth - 1)))
+        .collect();
+    divide.sort();
+    for &(ref mv, nodes) in &divide {
+        println!("{} {}", mv, nodes);
+    }
+    println!("nodes {}", divide.iter().map(|d| d.1).sum::<u64>());
+    Ok(0)
+}
+
+// The text of the files, or stdin
+fn read_input(files: &[String]) -> Result<Vec<(String, String)>, String> {
+    if files.is_empty() {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+        return Ok(vec![("stdin".to_string(), text)]);
+    }
+    files.iter()
+        .map(|name| {
+            let mut text = String::new();
+            File::open(name)
+                .and_then(|mut f| f.read_to_string(&mut text))
+                .map_err(|e| format!("{}: {}", name, e))?;
+            Ok((name.clone(), text))
+        })
+        .collect()
+}
+
+// Calls back with every game, reports the broken ones on stderr
+fn each_game<F: FnMut(&Game)>(files: &[String], mut f: F) -> Exit {
+    let mut code = 0;
+    for (name, text) in read_input(files)? {
+        for (i, game) in parse_games(&text).enumerate() {
+            match game {
+                Ok(game) => f(&game),
+                Err(e) => {
+                    eprintln!("{}: game {}: {:?}", name, i + 1, e);
+                    code = 1;
+                }
+            }
+        }
+    }
+    Ok(code)
+}
+
+fn pgn_to_fen(files: &[String]) -> Exit {
+    each_game(files, |game| {
+        for fen in fens(game) {
+            println!("{}", fen);
+        }
+        println!();
+    })
+}
+
+// Every position of the game, the clocks counted from its start
+fn fens(game: &Game) -> Vec<String> {
+    let (mut halfmove, mut fullmove) = (0, 1);
+    let mut p = game.start;
+    let mut result = vec![p.fen(halfmove, fullmove)];
+    for &mv in &game.moves {
+        let pawn = !mv.is_drop() && mv.castle == castle::NONE &&
+                   p.board.get_piece(mv.from.mask()).kind() == PAWN;
+        halfmove = if pawn || p.is_capture(mv) { 0 } else { halfmove + 1 };
+        if p.active == Color::Black {
+            fullmove += 1;
+        }
+        p = p.make_move(mv);
+        result.push(p.fen(halfmove, fullmove));
+    }
+    result
+}
+
+fn pgn_stats(files: &[String]) -> Exit {
+    let mut games = 0;
+    let mut plies = vec![];
+    let mut results = [0; 4];
+    let labels = ["1-0", "0-1", "1/2-1/2", "*"];
+    let code = each_game(files, |game| {
+        games += 1;
+        plies.push(game.moves.len());
+        let result = result_str(game.result);
+        results[labels.iter().position(|&l| l == result).unwrap()] += 1;
+    })?;
+    println!("games {}", games);
+    for (label, count) in labels.iter().zip(results.iter()) {
+        println!("{:<8} {}", label, count);
+    }
+    if !plies.is_empty() {
+        println!("plies min {}, max {}, average {:.1}",
+                 plies.iter().min().unwrap(),
+                 plies.iter().max().unwrap(),
+                 plies.iter().sum::<usize>() as f64 / plies.len() as f64);
+    }
+    Ok(code)
+}
+
+fn convert(args: &[String]) -> Exit {
+    let to = match args.first().map(|s| s.as_str()) {
+        Some(to @ "san") | Some(to @ "uci") | Some(to @ "coord") => to,
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut p = Position::start(Rules::Standard);
+    let mut moves = vec![];
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--fen" {
+            p = parse_fen(rest.next().ok_or("--fen needs a FEN")?)?;
+        } else {
+            moves.push(arg.clone());
+        }
+    }
+    if moves.is_empty() {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+        moves = text.split_whitespace().map(|s| s.to_string()).collect();
+    }
+    let mut converted = vec![];
+    for input in &moves {
+        let mv = match p.parse_san(input).ok().or_else(|| p.parse_uci(input)) {
+            Some(mv) => mv,
+            None => {
+                println!("{}", converted.join(" "));
+                eprintln!("no legal move '{}' in {}", input, p);
+                return Ok(1);
+            }
+        };
+        converted.push(match to {
+            "san" => p.san(mv),
+            "uci" => p.uci(mv),
+            _ => format!("{}", mv),
+        });
+        p = p.make_move(mv);
+    }
+    println!("{}", converted.join(" "));
+    Ok(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clocks() {
+        let game = Game::parse("1. e4 Nf6 2. Nc3 Nc6 3. Nb1 Nxe4 *").unwrap();
+        assert_eq!(fens(&game)[1..],
+                   ["rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                    "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2",
+                    "rnbqkb1r/pppppppp/5n2/8/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 2",
+                    "r1bqkb1r/pppppppp/2n2n2/8/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 3 3",
+                    "r1bqkb1r/pppppppp/2n2n2/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 4 3",
+                    "r1bqkb1r/pppppppp/2n5/8/4n3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 4"]);
+    }
+}
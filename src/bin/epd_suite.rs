@@ -0,0 +1,312 @@
+// Runs the positions of an EPD file through the search and checks
+// `bm`, `am` and `dm`:
+//
+//     epd_suite [--depth N] [--time MS] [--json] FILE
+//
+// The depth is in plies, the time is per position; without either the
+// search goes 6 plies deep. A mate other than the `bm` one counts as
+// long as it is just as short.
+extern crate chess;
+extern crate nom;
+
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+use std::time::{Duration, Instant};
+
+use chess::moves::Move;
+use chess::position::{Epd, Position};
+use chess::position::epd::{Operation, parse_epd};
+use chess::search::{Limits, Score, Search};
+
+const USAGE: &'static str = "\
+usage: epd_suite [--depth N] [--time MS] [--json] FILE
+Searches every position N plies deep and/or for MS milliseconds
+(6 plies when neither is given).";
+
+const DEPTH: u32 = 6;
+
+struct Options {
+    depth: Option<u32>,
+    time: Option<Duration>,
+    json: bool,
+    path: String,
+}
+
+#[derive(PartialEq)]
+enum Verdict {
+    Solved,
+    Failed,
+    // nothing to check
+    Skipped,
+    Invalid,
+}
+
+struct Report {
+    line: usize,
+    id: String,
+    verdict: Verdict,
+    // the move played and its score
+    found: Option<(String, Score)>,
+    note: String,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+    let file = File::open(&options.path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", options.path, e);
+        process::exit(2);
+    });
+    let search = Search::default();
+    let mut reports = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("{}: {}", options.path, e);
+            process::exit(2);
+        });
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        reports.push(run(i + 1, line, &search, &options));
+    }
+    if options.json {
+        print_json(&reports);
+    } else {
+        print_text(&reports);
+    }
+    if reports.iter().any(|r| r.verdict == Verdict::Failed || r.verdict == Verdict::Invalid) {
+        process::exit(1);
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        depth: None,
+        time: None,
+        json: false,
+        path: String::new(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => options.depth = Some(number(args.next(), "--depth")?),
+            "--time" => options.time = Some(Duration::from_millis(number(args.next(), "--time")?)),
+            "--json" => options.json = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => options.path = arg,
+        }
+    }
+    if options.path.is_empty() {
+        return Err("no EPD file given".to_string());
+    }
+    if options.depth.is_none() && options.time.is_none() {
+        options.depth = Some(DEPTH);
+    }
+    Ok(options)
+}
+
+fn number<T: std::str::FromStr>(arg: Option<String>, option: &str) -> Result<T, String> {
+    arg.and_then(|a| a.parse().ok()).ok_or_else(|| format!("{} needs a number", option))
+}
+
+fn run(line: usize, input: &str, search: &Search, options: &Options) -> Report {
+    let epd = match parse_epd(input.as_bytes()) {
+        nom::IResult::Done(_, epd) => epd,
+        _ => {
+            return Report {
+                line: line,
+                id: String::new(),
+                verdict: Verdict::Invalid,
+                found: None,
+                note: "can not parse".to_string(),
+            }
+        }
+    };
+    let solution = solve(&epd, search, options);
+    let found = solution.map(|(mv, score)| (epd.position.san(mv), score));
+    let (verdict, note) = judge(&epd, solution);
+    Report {
+        line: line,
+        id: epd.id().unwrap_or("").to_string(),
+        verdict: verdict,
+        found: found,
+        note: note,
+    }
+}
+
+// The move the search settles on within the limits
+fn solve(epd: &Epd, search: &Search, options: &Options) -> Option<(Move, Score)> {
+    let limits = Limits {
+        depth: options.depth,
+        deadline: options.time.map(|t| Instant::now() + t),
+        stop: None,
+    };
+    // every position on its own
+    search.clear();
+    search.run(&epd.position, &limits, |_| {}).map(|report| (report.pv[0], report.score))
+}
+
+fn judge(epd: &Epd, solution: Option<(Move, Score)>) -> (Verdict, String) {
+    let mate = epd.operations.iter().filter_map(|op| match *op {
+        Operation::DirectMate(n) => Some(n),
+        _ => None,
+    }).next();
+    let (best, avoid) = (epd.best_moves(), epd.avoid_moves());
+    if mate.is_none() && best.is_empty() && avoid.is_empty() {
+        return (Verdict::Skipped, "no bm, am or dm".to_string());
+    }
+    let (mv, score) = match solution {
+        Some(s) => s,
+        None => return (Verdict::Failed, "no move".to_string()),
+    };
+    let length = match score {
+        Score::Mate(n) if n > 0 => Some(n as u32),
+        _ => None,
+    };
+    if !best.is_empty() && !best.contains(&mv) && !mates_as_fast(&epd.position, mv, best, length) {
+        return (Verdict::Failed, "not a best move".to_string());
+    }
+    if avoid.contains(&mv) {
+        return (Verdict::Failed, "a move to avoid".to_string());
+    }
+    match (mate, length) {
+        (Some(_), None) => (Verdict::Failed, "no mate found".to_string()),
+        (Some(n), Some(length)) if length != n => {
+            (Verdict::Failed, format!("mate in {}, expected {}", length, n))
+        }
+        _ => (Verdict::Solved, String::new()),
+    }
+}
+
+// `mv` mates no later than the best moves do
+fn mates_as_fast(position: &Position, mv: Move, best: &[Move], length: Option<u32>) -> bool {
+    let length = match length {
+        Some(n) => n,
+        None => return false,
+    };
+    (1..length + 1)
+        .map(|n| position.solve_mate_cooks(n))
+        .find(|cooks| best.iter().any(|m| cooks.contains(m)))
+        .map_or(false, |cooks| cooks.contains(&mv))
+}
+
+fn verdict(v: &Verdict) -> &'static str {
+    match *v {
+        Verdict::Solved => "solved",
+        Verdict::Failed => "failed",
+        Verdict::Skipped => "skipped",
+        Verdict::Invalid => "invalid",
+    }
+}
+
+fn count(reports: &[Report], v: Verdict) -> usize {
+    reports.iter().filter(|r| r.verdict == v).count()
+}
+
+fn print_text(reports: &[Report]) {
+    for r in reports {
+        let found = r.found.as_ref().map_or(String::new(), |&(ref san, score)| {
+            format!(" {} ({})", san, score)
+        });
+        println!("{:>5} {:<16} {:<8}{} {}",
+                 r.line,
+                 r.id,
+                 verdict(&r.verdict),
+                 found,
+                 r.note);
+    }
+    println!("solved {}, failed {}, skipped {}, invalid {}",
+             count(reports, Verdict::Solved),
+             count(reports, Verdict::Failed),
+             count(reports, Verdict::Skipped),
+             count(reports, Verdict::Invalid));
+}
+
+fn print_json(reports: &[Report]) {
+    println!("{{");
+    println!("  \"solved\": {},", count(reports, Verdict::Solved));
+    println!("  \"failed\": {},", count(reports, Verdict::Failed));
+    println!("  \"skipped\": {},", count(reports, Verdict::Skipped));
+    println!("  \"invalid\": {},", count(reports, Verdict::Invalid));
+    println!("  \"positions\": [");
+    for (i, r) in reports.iter().enumerate() {
+        let (mv, score) = match r.found {
+            Some((ref san, score)) => (json_string(san), json_string(&score.to_string())),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        println!("    {{\"line\": {}, \"id\": {}, \"verdict\": \"{}\", \"move\": {}, \
+                  \"score\": {}, \"note\": {}}}{}",
+                 r.line,
+                 json_string(&r.id),
+                 verdict(&r.verdict),
+                 mv,
+                 score,
+                 json_string(&r.note),
+                 if i + 1 < reports.len() { "," } else { "" });
+    }
+    println!("  ]");
+    println!("}}");
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn verdict_of(line: &str, depth: u32) -> (Verdict, String) {
+        let epd = Epd::parse(line);
+        let options = Options {
+            depth: Some(depth),
+            time: None,
+            json: false,
+            path: String::new(),
+        };
+        judge(&epd, solve(&epd, &Search::default(), &options))
+    }
+
+    #[test]
+    fn best_move() {
+        // the queen hangs
+        let line = "4k3/8/8/3q4/8/8/8/3RK3 w - - bm Rxd5; id \"q\";";
+        assert!(verdict_of(line, 3) == (Verdict::Solved, String::new()));
+        let line = "4k3/8/8/3q4/8/8/8/3RK3 w - - am Rxd5; id \"q\";";
+        assert!(verdict_of(line, 3) == (Verdict::Failed, "a move to avoid".to_string()));
+    }
+
+    #[test]
+    fn any_mate_as_short() {
+        // Ra8# and Rb8# both mate, only one is listed
+        for &bm in &["Ra8#", "Rb8#"] {
+            let line = format!("6k1/5ppp/8/8/8/8/8/RR4K1 w - - bm {}; dm 1;", bm);
+            assert!(verdict_of(&line, 3) == (Verdict::Solved, String::new()));
+        }
+        // a longer mate is not as good
+        let epd = Epd::parse("6k1/5ppp/8/8/8/8/8/RR4K1 w - - bm Ra8#;");
+        assert!(!mates_as_fast(&epd.position, Move::parse("a1a7"), epd.best_moves(), Some(2)));
+    }
+
+    #[test]
+    fn wrong_mate_length() {
+        let line = "6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 2;";
+        assert!(verdict_of(line, 3) == (Verdict::Failed, "mate in 1, expected 2".to_string()));
+    }
+}
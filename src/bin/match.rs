@@ -0,0 +1,531 @@
+// Plays two UCI engines against each other:
+//
+//     match --engine1 CMD --engine2 CMD [--option1 NAME=VALUE]...
+//           [--option2 NAME=VALUE]... [--games N] [--openings FILE]
+//           [--tc SECONDS+INCREMENT] [--margin MS] [--max-plies N]
+//           [--pgn FILE]
+//
+// Every opening (EPD lines or PGN games, the standard start without a
+// file) is played twice with the colors swapped. A game is lost on an
+// illegal move, on time or when the engine goes away, and drawn after
+// `--max-plies`. The score is from the first engine's point of view.
+// Variant openings need engines offering them through UCI_Variant.
+// Engine commands are split at whitespace, there is no quoting.
+extern crate chess;
+extern crate nom;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use chess::color::Color;
+use chess::pgn::{Game, parse_games, result_str};
+use chess::position::{Outcome, Position};
+use chess::position::epd::parse_epd;
+use chess::rules::Rules;
+use chess::uci_client::{Engine, Limits, UciError};
+
+const USAGE: &'static str = "\
+usage: match --engine1 CMD --engine2 CMD [--option1 NAME=VALUE]...
+             [--option2 NAME=VALUE]... [--games N] [--openings FILE]
+             [--tc SECONDS+INCREMENT] [--margin MS] [--max-plies N] [--pgn FILE]";
+
+struct Options {
+    commands: [String; 2],
+    options: [Vec<(String, String)>; 2],
+    games: usize,
+    openings: Option<String>,
+    base: Duration,
+    increment: Duration,
+    // what an engine may overstep its clock by
+    margin: Duration,
+    max_plies: usize,
+    pgn: Option<String>,
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+    let openings = load_openings(&options).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let mut engines = vec![start_engine(&options.commands[0], &options.options[0]),
+                           start_engine(&options.commands[1], &options.options[1])];
+    let names: Vec<String> = engines.iter()
+        .zip(options.commands.iter())
+        .map(|(e, command)| e.name.clone().unwrap_or_else(|| command.clone()))
+        .collect();
+    for opening in &openings {
+        for (engine, name) in engines.iter().zip(&names) {
+            if !plays(engine, opening.start.rules) {
+                eprintln!("{} does not play {}", name, opening.start.rules.name());
+                process::exit(2);
+            }
+        }
+    }
+    let mut pgn = options.pgn.as_ref().map(|path| {
+        File::create(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(2);
+        })
+    });
+
+    // wins, draws, losses of the first engine
+    let mut score = [0; 3];
+    for round in 0..options.games {
+        let opening = &openings[round / 2 % openings.len()];
+        let (game, termination) = play_round(round, opening, &mut engines, &names, &options);
+        match game.result {
+            Some(Outcome::Win(c)) if c == first_color(round) => score[0] += 1,
+            Some(Outcome::Win(_)) => score[2] += 1,
+            _ => score[1] += 1,
+        }
+        println!("game {}: {} - {} {} ({}), +{} ={} -{}",
+                 round + 1,
+                 game.tag("White").unwrap_or(""),
+                 game.tag("Black").unwrap_or(""),
+                 result_str(game.result),
+                 termination,
+                 score[0],
+                 score[1],
+                 score[2]);
+        if let Some(ref mut file) = pgn {
+            if let Err(e) = write!(file, "{}\n", game) {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
+    for engine in engines {
+        engine.quit();
+    }
+    println!("{} vs {}: +{} ={} -{}",
+             names[0],
+             names[1],
+             score[0],
+             score[1],
+             score[2]);
+    println!("{}", elo_report(score[0], score[1], score[2]));
+}
+
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options {
+        commands: [String::new(), String::new()],
+        options: [vec![], vec![]],
+        games: 2,
+        openings: None,
+        base: Duration::from_secs(10),
+        increment: Duration::from_millis(100),
+        margin: Duration::from_millis(100),
+        max_plies: 400,
+        pgn: None,
+    };
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--engine1" => options.commands[0] = value()?,
+            "--engine2" => options.commands[1] = value()?,
+            "--option1" | "--option2" => {
+                let v = value()?;
+                let i = v.find('=').ok_or_else(|| format!("{} is not NAME=VALUE", v))?;
+                let engine = if arg == "--option1" { 0 } else { 1 };
+                options.options[engine].push((v[..i].to_string(), v[i + 1..].to_string()));
+            }
+            "--games" => options.games = number(&value()?)?,
+            "--openings" => options.openings = Some(value()?),
+            "--tc" => {
+                let v = value()?;
+                let mut parts = v.splitn(2, '+');
+                options.base = duration(parts.next().unwrap_or(""))?;
+                options.increment = duration(parts.next().unwrap_or("0"))?;
+            }
+            "--margin" => options.margin = Duration::from_millis(number(&value()?)?),
+            "--max-plies" => options.max_plies = number(&value()?)?,
+            "--pgn" => options.pgn = Some(value()?),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    if options.commands[0].is_empty() || options.commands[1].is_empty() {
+        return Err("two engines are needed".to_string());
+    }
+    Ok(options)
+}
+
+fn number<T: std::str::FromStr>(input: &str) -> Result<T, String> {
+    input.parse().map_err(|_| format!("{} is not a number", input))
+}
+
+fn duration(seconds: &str) -> Result<Duration, String> {
+    let s: f64 = number(seconds)?;
+    Ok(Duration::from_millis((s * 1000.0) as u64))
+}
+
+fn seconds(d: Duration) -> String {
+    let s = d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9;
+    format!("{}", s)
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1000000
+}
+
+// Start positions with the moves leading up to them
+fn load_openings(options: &Options) -> Result<Vec<Game>, String> {
+    let path = match options.openings {
+        Some(ref path) => path,
+        None => return Ok(vec![Game::new(Position::start(Rules::Standard))]),
+    };
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let mut result = vec![];
+    if path.ends_with(".pgn") {
+        for (i, game) in parse_games(&text).enumerate() {
+            let game = game.map_err(|e| format!("{}: game {}: {:?}", path, i + 1, e))?;
+            result.push(Game { tags: vec![], result: None, ..game });
+        }
+    } else {
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_epd(line.trim().as_bytes()) {
+                nom::IResult::Done(_, epd) => result.push(Game::new(epd.position)),
+                _ => return Err(format!("{}:{}: can not parse", path, i + 1)),
+            }
+        }
+    }
+    if result.is_empty() {
+        return Err(format!("{}: no openings", path));
+    }
+    Ok(result)
+}
+
+fn start_engine(command: &str, options: &[(String, String)]) -> Engine {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let started = Engine::spawn(words.first().cloned().unwrap_or(""), &words[1.min(words.len())..])
+        .and_then(|mut engine| {
+            for &(ref name, ref value) in options {
+                engine.set_option(name, Some(value))?;
+            }
+            engine.is_ready()?;
+            Ok(engine)
+        });
+    started.unwrap_or_else(|e| {
+        eprintln!("{}: {:?}", command, e);
+        process::exit(2);
+    })
+}
+
+// The UCI_Variant value of the rules, house rules have none
+fn uci_variant(rules: Rules) -> Option<&'static str> {
+    match rules {
+        Rules::Standard => Some("chess"),
+        Rules::Crazyhouse => Some("crazyhouse"),
+        Rules::Atomic => Some("atomic"),
+        Rules::Antichess => Some("antichess"),
+        Rules::ThreeCheck => Some("3check"),
+        Rules::KingOfTheHill => Some("kingofthehill"),
+        Rules::Horde => Some("horde"),
+        Rules::RacingKings => Some("racingkings"),
+        Rules::Custom(_) => None,
+    }
+}
+
+fn has_variants(engine: &Engine) -> bool {
+    engine.options.iter().any(|o| o.name == "UCI_Variant")
+}
+
+fn plays(engine: &Engine, rules: Rules) -> bool {
+    let variant = match uci_variant(rules) {
+        Some(variant) => variant,
+        None => return false,
+    };
+    rules == Rules::Standard ||
+    engine.options.iter().any(|o| o.name == "UCI_Variant" && o.vars.iter().any(|v| v == variant))
+}
+
+// the first engine has white in even rounds
+fn first_color(round: usize) -> Color {
+    if round % 2 == 0 { Color::White } else { Color::Black }
+}
+
+// The game of a round with its tags, and why it ended
+fn play_round(round: usize,
+              opening: &Game,
+              engines: &mut [Engine],
+              names: &[String],
+              options: &Options)
+              -> (Game, &'static str) {
+    let first_white = first_color(round) == Color::White;
+    let (white, black) = if first_white { (0, 1) } else { (1, 0) };
+    let (mut game, termination) = {
+        let (first, second) = engines.split_at_mut(1);
+        let (first, second) = (&mut first[0], &mut second[0]);
+        if first_white {
+            play(opening, [second, first], options)
+        } else {
+            play(opening, [first, second], options)
+        }
+    };
+    game.set_tag("Event", "match");
+    game.set_tag("Round", &(round + 1).to_string());
+    game.set_tag("White", &names[white]);
+    game.set_tag("Black", &names[black]);
+    game.set_tag("TimeControl",
+                 &format!("{}+{}", seconds(options.base), seconds(options.increment)));
+    game.set_tag("Termination", termination);
+    (game, termination)
+}
+
+// The game and why it ended, engines indexed by color
+fn play(opening: &Game, engines: [&mut Engine; 2], options: &Options) -> (Game, &'static str) {
+    let mut engines = engines;
+    let mut game = opening.clone();
+    let mut p = game.positions().pop().unwrap();
+    let mut clocks = [options.base, options.base];
+    for (side, engine) in engines.iter_mut().enumerate() {
+        let rules = game.start.rules;
+        // back to standard chess after a variant game too
+        let set_up = match uci_variant(rules) {
+            Some(variant) if rules != Rules::Standard || has_variants(engine) => {
+                engine.set_option("UCI_Variant", Some(variant))
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = set_up.and_then(|_| engine.new_game()) {
+            let loser = if side == Color::White as usize { Color::White } else { Color::Black };
+            game.result = Some(Outcome::Win(loser.invert()));
+            return (game, termination(&e));
+        }
+    }
+    loop {
+        if let Some(outcome) = p.outcome() {
+            game.result = Some(outcome);
+            return (game, "normal");
+        }
+        if game.moves.len() >= options.max_plies {
+            game.result = Some(Outcome::Draw);
+            return (game, "adjudication");
+        }
+        let side = p.active as usize;
+        let limits = Limits {
+            wtime: Some(millis(clocks[Color::White as usize])),
+            btime: Some(millis(clocks[Color::Black as usize])),
+            winc: Some(millis(options.increment)),
+            binc: Some(millis(options.increment)),
+            ..Limits::default()
+        };
+        let engine = &mut engines[side];
+        let started = Instant::now();
+        let search = engine.set_position(&game.start, &game.moves)
+            .and_then(|_| engine.go(&limits, Some(clocks[side] + options.margin)));
+        let used = started.elapsed();
+        let termination = match search {
+            Ok(_) if used > clocks[side] + options.margin => "time forfeit",
+            Ok(search) => {
+                clocks[side] = clocks[side] - used.min(clocks[side]) + options.increment;
+                game.moves.push(search.best);
+                p = p.make_move(search.best);
+                continue;
+            }
+            Err(e) => termination(&e),
+        };
+        game.result = Some(Outcome::Win(p.active.invert()));
+        return (game, termination);
+    }
+}
+
+// The loss an engine error counts as
+fn termination(e: &UciError) -> &'static str {
+    match *e {
+        UciError::Timeout => "time forfeit",
+        UciError::IllegalMove(_) => "illegal move",
+        UciError::Protocol(_) => "protocol error",
+        UciError::Exited |
+        UciError::Io(_) => "engine exited",
+    }
+}
+
+// Elo difference with a 95% interval, from the first engine's side
+fn elo_report(wins: usize, draws: usize, losses: usize) -> String {
+    let n = (wins + draws + losses) as f64;
+    if n == 0.0 {
+        return "no games".to_string();
+    }
+    let score = (wins as f64 + draws as f64 / 2.0) / n;
+    let variance = (wins as f64 * (1.0 - score).powi(2) + draws as f64 * (0.5 - score).powi(2) +
+                    losses as f64 * score.powi(2)) / n;
+    let margin = 1.96 * (variance / n).sqrt();
+    // plus zero turns -0 into 0
+    let elo = |s: f64| -400.0 * (1.0 / s - 1.0).log10() + 0.0;
+    let low = elo((score - margin).max(0.0));
+    let high = elo((score + margin).min(1.0));
+    if !elo(score).is_finite() {
+        return format!("score {:.1}%, Elo difference out of range", score * 100.0);
+    }
+    let error = if low.is_finite() && high.is_finite() {
+        format!("{:.1}", (high - low) / 2.0)
+    } else {
+        "inf".to_string()
+    };
+    format!("score {:.1}%, Elo difference {:+.1} +/- {}",
+            score * 100.0,
+            elo(score),
+            error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    // Plays the fool's mate from either side, with `$0` as `illegal`
+    // always an illegal move and as `wait` nothing until `stop`
+    static FAKE_ENGINE: &'static str = "
+        while read command rest; do
+            case \"$command\" in
+                uci) echo 'option name UCI_Variant type combo default chess var chess var atomic'
+                     echo uciok ;;
+                isready) echo readyok ;;
+                position) moves=$rest ;;
+                go) case \"$0:$moves\" in
+                        wait:*) ;;
+                        illegal:*) echo 'bestmove e2e5' ;;
+                        *f2f3) echo 'bestmove e7e5' ;;
+                        *e7e5) echo 'bestmove g2g4' ;;
+                        *g2g4) echo 'bestmove d8h4' ;;
+                        *) echo 'bestmove f2f3' ;;
+                    esac ;;
+                stop) echo 'bestmove a2a3' ;;
+                quit) exit 0 ;;
+            esac
+        done";
+
+    fn fake(mode: &str) -> Engine {
+        Engine::spawn("sh", &["-c", FAKE_ENGINE, mode]).unwrap()
+    }
+
+    fn args(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    fn options(extra: &[&str]) -> Options {
+        let mut all = vec!["--engine1", "a", "--engine2", "b"];
+        all.extend_from_slice(extra);
+        args(&all).unwrap()
+    }
+
+    fn start() -> Game {
+        Game::new(Position::start(Rules::Standard))
+    }
+
+    #[test]
+    fn arguments() {
+        let o = args(&["--engine1", "sf -q", "--engine2", "other", "--option1", "Hash=64",
+                       "--option2", "Skill Level=3", "--games", "4", "--tc", "5+0.5",
+                       "--margin", "20", "--max-plies", "10", "--pgn", "out.pgn"])
+            .unwrap();
+        assert_eq!(o.commands, ["sf -q".to_string(), "other".to_string()]);
+        assert_eq!(o.options[0], vec![("Hash".to_string(), "64".to_string())]);
+        assert_eq!(o.options[1], vec![("Skill Level".to_string(), "3".to_string())]);
+        assert_eq!((o.games, o.max_plies), (4, 10));
+        assert_eq!((o.base, o.increment), (Duration::from_secs(5), Duration::from_millis(500)));
+        assert_eq!(o.margin, Duration::from_millis(20));
+        assert_eq!(o.pgn, Some("out.pgn".to_string()));
+        assert_eq!(options(&["--tc", "2"]).increment, Duration::from_secs(0));
+
+        assert!(args(&["--engine1", "a"]).is_err());
+        assert!(args(&["--engine1", "a", "--engine2"]).is_err());
+        assert!(args(&["--engine1", "a", "--engine2", "b", "--option1", "Hash"]).is_err());
+        assert!(args(&["--engine1", "a", "--engine2", "b", "--games", "x"]).is_err());
+        assert!(args(&["--engine1", "a", "--engine2", "b", "--fast"]).is_err());
+    }
+
+    #[test]
+    fn elo() {
+        assert_eq!(elo_report(0, 0, 0), "no games");
+        assert_eq!(elo_report(60, 20, 20), "score 70.0%, Elo difference +147.2 +/- 66.0");
+        assert_eq!(elo_report(1, 1, 1), "score 50.0%, Elo difference +0.0 +/- 561.2");
+        assert_eq!(elo_report(1, 0, 1), "score 50.0%, Elo difference +0.0 +/- inf");
+        assert_eq!(elo_report(3, 0, 0), "score 100.0%, Elo difference out of range");
+        assert_eq!(elo_report(0, 0, 3), "score 0.0%, Elo difference out of range");
+    }
+
+    #[test]
+    fn variants() {
+        let engine = fake("");
+        assert_eq!(uci_variant(Rules::ThreeCheck), Some("3check"));
+        assert_eq!(uci_variant(Rules::KingOfTheHill), Some("kingofthehill"));
+        assert_eq!(uci_variant(Rules::RacingKings), Some("racingkings"));
+        assert!(plays(&engine, Rules::Standard));
+        assert!(plays(&engine, Rules::Atomic));
+        assert!(!plays(&engine, Rules::Crazyhouse));
+        engine.quit();
+    }
+
+    #[test]
+    fn games() {
+        let (mut white, mut black) = (fake(""), fake(""));
+        let (game, termination) = play(&start(), [&mut black, &mut white], &options(&[]));
+        assert_eq!((game.result, termination), (Some(Outcome::Win(Color::Black)), "normal"));
+        assert_eq!(game.moves.len(), 4);
+        let (game, termination) =
+            play(&start(), [&mut black, &mut white], &options(&["--max-plies", "2"]));
+        assert_eq!((game.result, termination), (Some(Outcome::Draw), "adjudication"));
+        assert_eq!(game.moves.len(), 2);
+        white.quit();
+        black.quit();
+    }
+
+    #[test]
+    fn forfeits() {
+        let (mut good, mut illegal) = (fake(""), fake("illegal"));
+        let (game, termination) = play(&start(), [&mut illegal, &mut good], &options(&[]));
+        assert_eq!((game.result, termination), (Some(Outcome::Win(Color::White)), "illegal move"));
+        assert_eq!(game.moves.len(), 1);
+
+        let mut slow = fake("wait");
+        let fast = options(&["--tc", "0.05", "--margin", "50"]);
+        let (game, termination) = play(&start(), [&mut good, &mut slow], &fast);
+        assert_eq!((game.result, termination), (Some(Outcome::Win(Color::Black)), "time forfeit"));
+        assert!(game.moves.is_empty());
+
+        let mut gone = fake("");
+        gone.send("quit").unwrap();
+        while !gone.has_exited() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let (game, termination) = play(&start(), [&mut gone, &mut good], &options(&[]));
+        assert_eq!((game.result, termination), (Some(Outcome::Win(Color::White)), "engine exited"));
+        for engine in vec![good, illegal, slow, gone] {
+            engine.quit();
+        }
+    }
+
+    #[test]
+    fn rounds() {
+        let mut engines = vec![fake(""), fake("illegal")];
+        let names = vec!["first".to_string(), "second".to_string()];
+        let options = options(&[]);
+        let (game, _) = play_round(0, &start(), &mut engines, &names, &options);
+        assert_eq!((game.tag("White"), game.tag("Black")), (Some("first"), Some("second")));
+        assert_eq!(game.result, Some(Outcome::Win(Color::White)));
+        let (game, termination) = play_round(1, &start(), &mut engines, &names, &options);
+        assert_eq!((game.tag("White"), game.tag("Black")), (Some("second"), Some("first")));
+        assert_eq!(game.result, Some(Outcome::Win(Color::Black)));
+        assert_eq!((game.tag("Round"), game.tag("Termination")), (Some("2"), Some(termination)));
+        assert_eq!(first_color(1), Color::Black);
+        for engine in engines {
+            engine.quit();
+        }
+    }
+}
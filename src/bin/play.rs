@@ -0,0 +1,213 @@
+// A terminal board to play moves on, one command or move per line:
+//
+//     play [--unicode] [FEN]
+//
+// Moves can be SAN ("Nf3", "exd8=Q+"), UCI ("g1f3", "e7e8q") or the
+// crate's own coordinates ("g1-f3", "e7-e8=Q"). `go` only knows forced
+// mates, otherwise it plays at random.
+extern crate chess;
+extern crate nom;
+extern crate rand;
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use chess::castle;
+use chess::mask::masks::EMPTY;
+use chess::moves::Move;
+use chess::pgn::Game;
+use chess::position::{Position, parse_position};
+use chess::render::{Style, render};
+use chess::rules::Rules;
+use chess::search::{MATE_DEPTH, choose_move};
+
+const HELP: &'static str = "\
+<move>       play a move in SAN, UCI or coordinates
+undo         take the last move back
+fen          print the position
+load <fen>   start over from a position
+moves        list the legal moves
+flip         turn the board around
+go           let the computer move
+pgn          print the game so far
+help, quit";
+
+struct Session {
+    game: Game,
+    // the position after the last move
+    current: Position,
+    style: Style,
+}
+
+fn main() {
+    let mut style = Style::default();
+    let mut fen = None;
+    for arg in env::args().skip(1) {
+        if arg == "--unicode" {
+            style.unicode = true;
+        } else if arg.starts_with("--") {
+            eprintln!("unknown option {}", arg);
+            eprintln!("usage: play [--unicode] [FEN]");
+            process::exit(2);
+        } else {
+            fen = Some(arg);
+        }
+    }
+    let start = match fen {
+        Some(fen) => {
+            load(&fen).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(2);
+            })
+        }
+        None => Position::start(Rules::Standard),
+    };
+    let mut session = Session {
+        game: Game::new(start),
+        current: start,
+        style: style,
+    };
+    session.show();
+
+    let stdin = io::stdin();
+    prompt();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+        let line = line.trim();
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        if !line.is_empty() {
+            session.command(line);
+        }
+        prompt();
+    }
+}
+
+fn prompt() {
+    print!("> ");
+    let _ = io::stdout().flush();
+}
+
+impl Session {
+    fn command(&mut self, line: &str) {
+        let (command, argument) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+        match command {
+            "help" => println!("{}", HELP),
+            "undo" => {
+                if self.game.moves.pop().is_none() {
+                    println!("nothing to undo");
+                    return;
+                }
+                self.replay();
+                self.show();
+            }
+            "fen" => println!("{}", self.current),
+            "load" => {
+                match load(argument) {
+                    Ok(p) => {
+                        self.game = Game::new(p);
+                        self.current = p;
+                        self.show();
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            }
+            "moves" => {
+                let mut moves: Vec<String> = self.current
+                    .legal_moves()
+                    .into_iter()
+                    .map(|mv| self.current.san(mv))
+                    .collect();
+                moves.sort();
+                println!("{}", moves.join(" "));
+            }
+            "flip" => {
+                self.style.flipped = !self.style.flipped;
+                self.show();
+            }
+            "go" => self.go(),
+            "pgn" => {
+                self.game.result = self.current.outcome();
+                print!("{}", self.game);
+            }
+            _ => {
+                match parse_input(&self.current, line) {
+                    Some(mv) => self.play(mv),
+                    None => println!("no legal move '{}', try 'help'", line),
+                }
+            }
+        }
+    }
+
+    fn play(&mut self, mv: Move) {
+        if self.current.outcome().is_some() {
+            println!("the game is over, 'undo' or 'load' to go on");
+            return;
+        }
+        println!("{}", self.current.san(mv));
+        self.game.moves.push(mv);
+        self.current = self.current.make_move(mv);
+        self.show();
+    }
+
+    fn go(&mut self) {
+        let choice = match choose_move(&self.current, MATE_DEPTH, None, &mut rand::thread_rng()) {
+            Some(choice) => choice,
+            None => return println!("the game is over"),
+        };
+        match choice.mate_length() {
+            Some(n) => println!("mate in {}", n),
+            None => println!("no mate in {}, a random move", MATE_DEPTH),
+        }
+        self.play(choice.mv);
+    }
+
+    fn replay(&mut self) {
+        self.current = self.game.positions().pop().unwrap();
+    }
+
+    fn show(&self) {
+        let highlight = match self.game.moves.last() {
+            Some(mv) if mv.castle == castle::NONE && !mv.is_drop() => mv.from.mask() | mv.to.mask(),
+            Some(mv) if mv.is_drop() => mv.to.mask(),
+            _ => EMPTY,
+        };
+        let style = Style { highlight: highlight, ..self.style };
+        print!("{}", render(&self.current.board, &style));
+        match self.current.outcome() {
+            Some(outcome) => println!("game over: {:?}", outcome),
+            None => {
+                println!("{:?} to move{}",
+                         self.current.active,
+                         if self.current.is_check() { ", check" } else { "" })
+            }
+        }
+    }
+}
+
+fn load(fen: &str) -> Result<Position, String> {
+    if fen.trim().is_empty() {
+        return Err("no FEN given".to_string());
+    }
+    let p = match parse_position(fen.trim().as_bytes()) {
+        nom::IResult::Done(_, p) => p,
+        _ => return Err(format!("can not parse '{}'", fen)),
+    };
+    let assessment = p.validate();
+    if !assessment.is_empty() {
+        return Err(format!("invalid position: {:?}", assessment));
+    }
+    Ok(p)
+}
+
+fn parse_input(p: &Position, input: &str) -> Option<Move> {
+    p.parse_san(input).ok().or_else(|| p.parse_uci(input))
+}
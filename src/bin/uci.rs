@@ -0,0 +1,287 @@
+// Universal Chess Interface front-end to the search.
+//
+// The search runs on its own thread so `stop` and `isready` get an
+// answer while it thinks. Options: `Threads` (lazy SMP helpers share
+// the hash table) and `Hash` in megabytes.
+extern crate chess;
+extern crate nom;
+
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use chess::color::Color;
+use chess::moves::Move;
+use chess::position::{Position, parse_position};
+use chess::rules::Rules;
+use chess::search::{DEFAULT_HASH_MB, Limits, Report, Search};
+
+const MAX_THREADS: usize = 256;
+const MAX_HASH_MB: usize = 65536;
+// moves the clock is assumed to be shared by without `movestogo`
+const MOVES_LEFT: u64 = 30;
+
+struct Engine {
+    position: Position,
+    search: Search,
+    thinking: Option<(Arc<AtomicBool>, JoinHandle<Option<Report>>)>,
+    // the answer to the last `go`
+    last: Option<Report>,
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !engine.command(line.trim()) {
+            break;
+        }
+        let _ = io::stdout().flush();
+    }
+    engine.stop();
+}
+
+impl Engine {
+    fn new() -> Self {
+        Engine {
+            position: Position::start(Rules::Standard),
+            search: Search::default(),
+            thinking: None,
+            last: None,
+        }
+    }
+
+    // false to quit
+    fn command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().cloned().unwrap_or("") {
+            "quit" => return false,
+            "uci" => {
+                println!("id name chess");
+                println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                println!("option name Hash type spin default {} min 1 max {}",
+                         DEFAULT_HASH_MB,
+                         MAX_HASH_MB);
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "setoption" => {
+                self.wait();
+                self.set_option(&words[1..]);
+            }
+            "ucinewgame" => {
+                self.wait();
+                self.search.clear();
+            }
+            "position" => {
+                self.wait();
+                match position(&words[1..]) {
+                    Some(p) => self.position = p,
+                    None => println!("info string illegal position: {}", line),
+                }
+            }
+            "go" => {
+                self.wait();
+                self.go(&words[1..]);
+            }
+            "stop" => self.stop(),
+            "" => {}
+            _ => println!("info string unknown command: {}", line),
+        }
+        true
+    }
+
+    // "name Threads value 4"
+    fn set_option(&mut self, words: &[&str]) {
+        let value = words.iter().position(|&w| w == "value").unwrap_or(words.len());
+        let name = words[..value].iter().skip(1).cloned().collect::<Vec<_>>().join(" ");
+        let number = words.get(value + 1).and_then(|v| v.parse::<usize>().ok());
+        match (name.to_lowercase().as_str(), number) {
+            ("threads", Some(n)) if n >= 1 && n <= MAX_THREADS => self.search.set_threads(n),
+            ("hash", Some(n)) if n >= 1 && n <= MAX_HASH_MB => self.search.set_hash(n),
+            _ => println!("info string unsupported option: {}", words.join(" ")),
+        }
+    }
+
+    fn go(&mut self, words: &[&str]) {
+        let start = Instant::now();
+        let stop = Arc::new(AtomicBool::new(false));
+        let limits = Limits {
+            depth: number(words, "depth").map(|d| d as u32),
+            deadline: budget(words, self.position.active).map(|b| start + b),
+            stop: Some(stop.clone()),
+        };
+        let search = self.search.clone();
+        let position = self.position;
+        let handle = thread::spawn(move || {
+            let result = search.run(&position, &limits, |r| {
+                println!("info depth {} score {} nodes {} time {} pv {}",
+                         r.depth,
+                         r.score,
+                         r.nodes,
+                         millis(start.elapsed()),
+                         line(&position, &r.pv));
+                let _ = io::stdout().flush();
+            });
+            match result {
+                Some(ref r) => println!("bestmove {}", position.uci(r.pv[0])),
+                None => println!("bestmove 0000"),
+            }
+            let _ = io::stdout().flush();
+            result
+        });
+        self.thinking = Some((stop, handle));
+    }
+
+    fn stop(&mut self) {
+        if let Some((ref stop, _)) = self.thinking {
+            stop.store(true, Ordering::Relaxed);
+        }
+        self.wait();
+    }
+
+    // Lets the search finish on its own
+    fn wait(&mut self) {
+        if let Some((_, handle)) = self.thinking.take() {
+            self.last = handle.join().unwrap_or(None);
+        }
+    }
+}
+
+// "startpos moves e2e4 e7e5" or "fen <fen> moves ..."
+fn position(words: &[&str]) -> Option<Position> {
+    let moves = words.iter().position(|&w| w == "moves").unwrap_or(words.len());
+    let mut p = match words.first().cloned() {
+        Some("startpos") => Position::start(Rules::Standard),
+        Some("fen") => {
+            let fen = words[1..moves].join(" ");
+            match parse_position(fen.as_bytes()) {
+                nom::IResult::Done(_, p) if p.validate().is_empty() => p,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    for word in words.iter().skip(moves + 1) {
+        let mv = p.parse_uci(word)?;
+        p = p.make_move(mv);
+    }
+    Some(p)
+}
+
+fn number(words: &[&str], name: &str) -> Option<u64> {
+    words.iter()
+        .position(|&w| w == name)
+        .and_then(|i| words.get(i + 1))
+        .and_then(|v| v.parse().ok())
+}
+
+// `movetime`, or a share of the clock
+fn budget(words: &[&str], active: Color) -> Option<Duration> {
+    if let Some(ms) = number(words, "movetime") {
+        return Some(Duration::from_millis(ms));
+    }
+    let (time, increment) = if active == Color::White {
+        ("wtime", "winc")
+    } else {
+        ("btime", "binc")
+    };
+    number(words, time).map(|left| {
+        let moves = number(words, "movestogo").unwrap_or(MOVES_LEFT).max(1);
+        let share = left / moves + number(words, increment).unwrap_or(0);
+        // never the whole clock
+        Duration::from_millis(share.min(left / 2))
+    })
+}
+
+fn line(position: &Position, pv: &[Move]) -> String {
+    let mut p = *position;
+    let moves: Vec<String> = pv.iter()
+        .map(|&mv| {
+            let uci = p.uci(mv);
+            p = p.make_move(mv);
+            uci
+        })
+        .collect();
+    moves.join(" ")
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1000000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn engine(commands: &[&str]) -> Engine {
+        let mut engine = Engine::new();
+        for c in commands {
+            assert!(engine.command(c));
+        }
+        engine.wait();
+        engine
+    }
+
+    fn best(engine: &Engine) -> String {
+        let r = engine.last.as_ref().unwrap();
+        engine.position.uci(r.pv[0])
+    }
+
+    #[test]
+    fn positions() {
+        let e = engine(&["position startpos moves e2e4 e7e5 g1f3"]);
+        assert_eq!(format!("{}", e.position),
+                   "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq -");
+        let e = engine(&["position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1 moves a1a2"]);
+        assert_eq!(e.position.active, Color::Black);
+        // an illegal move or position leaves the old one
+        let e = engine(&["position startpos moves e2e4", "position startpos moves e2e5"]);
+        assert_eq!(e.position.active, Color::Black);
+        let e = engine(&["position fen 8/8/8/8/8/8/8/8 w - - 0 1"]);
+        assert_eq!(e.position, Position::start(Rules::Standard));
+    }
+
+    #[test]
+    fn go() {
+        let e = engine(&["position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "go depth 3"]);
+        assert_eq!(best(&e), "a1a8");
+        // `stop` right away still gives a move
+        let mut e = Engine::new();
+        assert!(e.command("go infinite"));
+        assert!(e.command("stop"));
+        assert!(e.position.legal_moves().contains(&e.last.as_ref().unwrap().pv[0]));
+        let e = engine(&["position fen R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", "go depth 1"]);
+        assert_eq!(e.last, None);
+    }
+
+    #[test]
+    fn options() {
+        let e = engine(&["setoption name Threads value 4", "setoption name Hash value 2"]);
+        assert_eq!(e.search.threads(), 4);
+        let e = engine(&["setoption name Threads value 0", "setoption name Threads"]);
+        assert_eq!(e.search.threads(), 1);
+        let e = engine(&["setoption name Threads value 3",
+                         "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+                         "go depth 3"]);
+        assert_eq!(best(&e), "a1a8");
+    }
+
+    #[test]
+    fn time() {
+        let go = |line: &str, active| budget(&line.split_whitespace().collect::<Vec<_>>(), active);
+        assert_eq!(go("movetime 500", Color::White), Some(Duration::from_millis(500)));
+        assert_eq!(go("wtime 60000 btime 30000", Color::White),
+                   Some(Duration::from_secs(2)));
+        assert_eq!(go("wtime 60000 btime 30000 binc 1000 movestogo 10", Color::Black),
+                   Some(Duration::from_secs(4)));
+        assert_eq!(go("btime 1000 binc 5000", Color::Black), Some(Duration::from_millis(500)));
+        assert_eq!(go("depth 3", Color::White), None);
+    }
+}
@@ -0,0 +1,347 @@
+// Chess Engine Communication Protocol (xboard, protocol 2) front-end.
+//
+// The engine plays a forced mate when it finds one within the depth
+// and time limits, and a random legal move otherwise. The protocol
+// side is complete enough for GUIs to run games, undo moves and show
+// the thinking output.
+extern crate chess;
+extern crate nom;
+extern crate rand;
+
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use chess::color::Color;
+use chess::moves::Move;
+use chess::position::{Outcome, Position, parse_position};
+use chess::rules::Rules;
+use chess::search::{MATE_DEPTH, choose_move};
+
+// xboard names of the variants we play
+static VARIANTS: [(&'static str, Rules); 8] = [("normal", Rules::Standard),
+                                               ("crazyhouse", Rules::Crazyhouse),
+                                               ("atomic", Rules::Atomic),
+                                               ("giveaway", Rules::Antichess),
+                                               ("3check", Rules::ThreeCheck),
+                                               ("kingofthehill", Rules::KingOfTheHill),
+                                               ("horde", Rules::Horde),
+                                               ("racingkings", Rules::RacingKings)];
+
+struct Engine {
+    start: Position,
+    moves: Vec<Move>,
+    current: Position,
+    // the engine only moves when told to, or when it is its turn
+    force: bool,
+    engine_color: Color,
+    post: bool,
+    // for the mate search, in moves; `sd` gives plies
+    depth: Option<u32>,
+    // `st`
+    move_time: Option<Duration>,
+    // `level`: moves per session (0 for all), increment
+    moves_per_session: u32,
+    increment: Duration,
+    // `time`
+    clock: Option<Duration>,
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !engine.command(line.trim()) {
+            break;
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+impl Engine {
+    fn new() -> Self {
+        let start = Position::start(Rules::Standard);
+        Engine {
+            start: start,
+            moves: vec![],
+            current: start,
+            force: false,
+            engine_color: Color::Black,
+            post: false,
+            depth: None,
+            move_time: None,
+            moves_per_session: 0,
+            increment: Duration::from_secs(0),
+            clock: None,
+        }
+    }
+
+    // false to quit
+    fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(c) => c,
+            None => return true,
+        };
+        let rest: Vec<&str> = words.collect();
+        let argument = |i: usize| rest.get(i).cloned().unwrap_or("");
+        match command {
+            "quit" => return false,
+            "protover" => {
+                let variants: Vec<&str> = VARIANTS.iter().map(|v| v.0).collect();
+                println!("feature myname=\"chess\" setboard=1 usermove=1 ping=1 playother=1 \
+                          san=0 colors=0 sigint=0 sigterm=0 analyze=0 variants=\"{}\"",
+                         variants.join(","));
+                println!("feature done=1");
+            }
+            "new" => {
+                self.reset(Position::start(Rules::Standard));
+                self.force = false;
+                self.engine_color = Color::Black;
+                self.depth = None;
+                self.move_time = None;
+            }
+            "variant" => {
+                match VARIANTS.iter().find(|v| v.0 == argument(0)) {
+                    Some(&(_, rules)) => self.reset(Position::start(rules)),
+                    None => println!("Error (unsupported variant): {}", argument(0)),
+                }
+            }
+            "setboard" => {
+                let fen = rest.join(" ");
+                match parse_position(fen.as_bytes()) {
+                    nom::IResult::Done(_, p) => {
+                        // in the variant chosen before
+                        let p = Position { rules: self.start.rules, ..p };
+                        if p.validate().is_empty() {
+                            self.reset(p)
+                        } else {
+                            println!("tellusererror Illegal position")
+                        }
+                    }
+                    _ => println!("tellusererror Illegal position"),
+                }
+            }
+            "usermove" => {
+                match self.current.parse_uci(argument(0)) {
+                    Some(mv) if self.current.outcome().is_none() => {
+                        self.play(mv);
+                        if !self.force && self.current.active == self.engine_color {
+                            self.think();
+                        }
+                    }
+                    _ => println!("Illegal move: {}", argument(0)),
+                }
+            }
+            "go" => {
+                self.force = false;
+                self.engine_color = self.current.active;
+                self.think();
+            }
+            "playother" => {
+                self.force = false;
+                self.engine_color = self.current.active.invert();
+            }
+            "force" => self.force = true,
+            "result" => self.force = true,
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "level" => {
+                self.moves_per_session = argument(0).parse().unwrap_or(0);
+                self.increment = seconds(argument(2));
+                self.move_time = None;
+            }
+            "st" => self.move_time = Some(seconds(argument(0))),
+            "sd" => self.depth = argument(0).parse().ok().map(|plies: u32| (plies + 1) / 2),
+            "time" => {
+                self.clock = argument(0).parse().ok().map(|cs: u64| Duration::from_millis(cs * 10))
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => println!("pong {}", argument(0)),
+            "xboard" | "accepted" | "rejected" | "otim" | "hard" | "easy" | "random" |
+            "computer" | "name" | "rating" | "ics" | "draw" | "?" => {}
+            _ => println!("Error (unknown command): {}", command),
+        }
+        true
+    }
+
+    fn reset(&mut self, start: Position) {
+        self.start = start;
+        self.current = start;
+        self.moves.clear();
+    }
+
+    fn play(&mut self, mv: Move) {
+        self.moves.push(mv);
+        self.current = self.current.make_move(mv);
+    }
+
+    fn take_back(&mut self, n: usize) {
+        for _ in 0..n {
+            self.moves.pop();
+        }
+        let mut p = self.start;
+        for &mv in &self.moves {
+            p = p.make_move(mv);
+        }
+        self.current = p;
+    }
+
+    fn budget(&self) -> Option<Duration> {
+        if self.move_time.is_some() {
+            return self.move_time;
+        }
+        let moves_left = if self.moves_per_session == 0 {
+            30
+        } else {
+            let played = self.moves.len() as u32 / 2;
+            self.moves_per_session - played % self.moves_per_session
+        };
+        self.clock.map(|c| c / moves_left + self.increment)
+    }
+
+    fn think(&mut self) {
+        let start = Instant::now();
+        let deadline = self.budget().map(|b| start + b);
+        let depth = self.depth.unwrap_or(MATE_DEPTH);
+        let choice = match choose_move(&self.current, depth, deadline, &mut rand::thread_rng()) {
+            Some(choice) => choice,
+            None => return self.announce(),
+        };
+        if self.post {
+            let n = choice.mate_length().unwrap_or(0);
+            match choice.mate {
+                Some(ref line) => self.thinking(2 * n - 1, 100000 + n as i32, start, line),
+                None => self.thinking(1, 0, start, &[choice.mv]),
+            }
+        }
+        println!("move {}", self.current.uci(choice.mv));
+        self.play(choice.mv);
+        if self.current.outcome().is_some() {
+            self.announce();
+        }
+    }
+
+    // "ply score time nodes pv", time in centiseconds
+    fn thinking(&self, ply: u32, score: i32, start: Instant, line: &[Move]) {
+        let elapsed = start.elapsed();
+        let centiseconds = elapsed.as_secs() * 100 + elapsed.subsec_nanos() as u64 / 10000000;
+        let mut p = self.current;
+        let pv: Vec<String> = line.iter()
+            .map(|&mv| {
+                let san = p.san(mv);
+                p = p.make_move(mv);
+                san
+            })
+            .collect();
+        println!("{} {} {} 0 {}", ply, score, centiseconds, pv.join(" "));
+    }
+
+    fn announce(&self) {
+        let (result, reason) = match self.current.outcome() {
+            Some(Outcome::Win(Color::White)) => ("1-0", "White wins"),
+            Some(Outcome::Win(Color::Black)) => ("0-1", "Black wins"),
+            Some(Outcome::Draw) => ("1/2-1/2", "Draw"),
+            None => return,
+        };
+        println!("{} {{{}}}", result, reason);
+    }
+}
+
+// "30" or "0.5" seconds, or "1:30"
+fn seconds(input: &str) -> Duration {
+    let mut parts = input.splitn(2, ':');
+    let first: f64 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+    let millis = match parts.next().and_then(|s| s.parse::<f64>().ok()) {
+        Some(s) => (first * 60.0 + s) * 1000.0,
+        None => first * 1000.0,
+    };
+    Duration::from_millis(millis as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn engine(commands: &[&str]) -> Engine {
+        let mut engine = Engine::new();
+        for c in commands {
+            assert!(engine.command(c));
+        }
+        engine
+    }
+
+    #[test]
+    fn parse_seconds() {
+        assert_eq!(seconds("30"), Duration::from_secs(30));
+        assert_eq!(seconds("0.5"), Duration::from_millis(500));
+        assert_eq!(seconds("1:30"), Duration::from_secs(90));
+        assert_eq!(seconds(""), Duration::from_secs(0));
+        assert_eq!(seconds("x"), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn budget() {
+        assert_eq!(engine(&[]).budget(), None);
+        assert_eq!(engine(&["st 2", "time 6000"]).budget(), Some(Duration::from_secs(2)));
+        // 60 seconds for the 30 moves assumed left
+        assert_eq!(engine(&["level 0 5 0", "time 6000"]).budget(), Some(Duration::from_secs(2)));
+        // 40 moves in the session, 5 seconds increment
+        let mut e = engine(&["level 40 1 5", "time 6000"]);
+        assert_eq!(e.budget(), Some(Duration::from_millis(6500)));
+        let mv = e.current.parse_uci("e2e4").unwrap();
+        // 20 moves played, 20 left
+        e.moves = vec![mv; 40];
+        assert_eq!(e.budget(), Some(Duration::from_secs(8)));
+        // `level` cancels `st`
+        assert_eq!(engine(&["st 2", "level 40 1 5"]).budget(), None);
+    }
+
+    #[test]
+    fn moves() {
+        let mut e = engine(&["new", "force", "usermove e2e4", "usermove e7e5"]);
+        assert_eq!(e.moves.len(), 2);
+        assert!(e.command("usermove e1e3"));
+        assert_eq!(e.moves.len(), 2);
+        assert!(e.command("undo"));
+        assert_eq!(e.moves.len(), 1);
+        assert_eq!(e.current.active, Color::Black);
+        assert!(e.command("remove"));
+        assert_eq!(e.current, e.start);
+        assert!(!e.command("quit"));
+    }
+
+    #[test]
+    fn engine_moves() {
+        let e = engine(&["setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "sd 1", "go"]);
+        assert_eq!(e.engine_color, Color::White);
+        assert_eq!(e.moves.len(), 1);
+        assert!(e.current.is_checkmate());
+        assert_eq!(engine(&["sd 3"]).depth, Some(2));
+        assert_eq!(engine(&["sd 4"]).depth, Some(2));
+        // the engine answers when it is its turn
+        let e = engine(&["new", "usermove e2e4"]);
+        assert_eq!(e.moves.len(), 2);
+        let e = engine(&["new", "force", "usermove e2e4"]);
+        assert_eq!(e.moves.len(), 1);
+    }
+
+    #[test]
+    fn setboard() {
+        let horde = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+        let e = engine(&["variant horde", &format!("setboard {}", horde)]);
+        assert_eq!(e.start.rules, Rules::Horde);
+        assert_eq!(e.start, Position::start(Rules::Horde));
+        // not without the variant
+        let e = engine(&[&format!("setboard {}", horde)]);
+        assert_eq!(e.start, Position::start(Rules::Standard));
+        let e = engine(&["setboard 8/8/8/8/8/8/8/8 w - - 0 1"]);
+        assert_eq!(e.start, Position::start(Rules::Standard));
+        let e = engine(&["variant atomic", "setboard 4k3/8/8/8/8/8/8/4K3 w - - 0 1"]);
+        assert_eq!(e.start.rules, Rules::Atomic);
+    }
+}
@@ -7,7 +7,7 @@ use color::Color;
 use side::*;
 use sided_mask::*;
 
-#[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
 pub struct BitBoard([Mask; PIECES_COUNT]);
 
 impl BitBoard {
@@ -18,6 +18,10 @@ impl BitBoard {
         self.0[piece.bits() as usize]
     }
 
+    pub fn pieces(&self, piece: Piece) -> Mask {
+        self.index(piece)
+    }
+
     pub fn pawns<S: Side>(&self) -> S::Mask {
         S::Mask::wrap(self.index(S::PAWN))
     }
@@ -59,6 +63,11 @@ impl BitBoard {
         let idx = piece.bits() as usize;
         self.0[idx] |= square;
     }
+    pub fn remove_piece(&mut self, square: Mask) {
+        for m in self.0.iter_mut() {
+            *m &= !square;
+        }
+    }
     pub fn get_piece(&self, square: Mask) -> Piece {
         for probe in ALL_PIECES {
             if self.index(probe).intersects(square) {
@@ -92,6 +101,20 @@ impl BitBoard {
     pub fn castling_move_masks<S: Side>(&self) -> Mask {
         EMPTY
     }
+    pub fn flip_horizontally(&self) -> Self {
+        let mut result = *self;
+        for m in result.0.iter_mut() {
+            *m = m.flip_horizontally();
+        }
+        result
+    }
+    pub fn flip_vertically(&self) -> Self {
+        let mut result = *self;
+        for m in result.0.iter_mut() {
+            *m = m.flip_vertically();
+        }
+        result
+    }
     pub fn swap_colors(&self) -> Self {
         let x = self.0;
         BitBoard([x[6].flip_vertically(),
@@ -75,7 +75,7 @@ pub fn parse_castle(input: &[u8]) -> IResult<&[u8], Castle, ParsingError> {
         match consume(e as char) {
             None => {
                 if consumed > 0 {
-                    return Done(&input[consumed + 1..], result);
+                    return Done(&input[consumed..], result);
                 } else {
                     return Error(Position(Custom(UnrecognizedToken), &input[consumed..]));
                 }
@@ -174,6 +174,13 @@ mod test {
         check("?Q", 0);
     }
 
+    #[test]
+    fn parse_leaves_the_rest() {
+        assert_eq!(parse_castle(b"Kq -"), Done(&b" -"[..], WK | BQ));
+        assert_eq!(parse_castle(b"- -"), Done(&b" -"[..], NONE));
+        assert_eq!(parse_castle(b"KQkq -"), Done(&b" -"[..], ALL));
+    }
+
     #[test]
     fn display() {
         assert_eq!(format!("{}", ALL), "KQkq");
@@ -0,0 +1,214 @@
+use moves::Move;
+use position::Position;
+
+// Games as move indices: the move count as a LEB128 varint, then each
+// move as its index into the sorted legal moves, in just as many bits
+// as the number of legal moves needs (none for a forced move), most
+// significant bit first. Decoding replays the game from the start.
+
+// plies in the longest game the seventy-five-move rule lets through
+pub const LONGEST_GAME: usize = 17697;
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum GameCodecError {
+    // the n-th move is not legal
+    IllegalMove(usize),
+    Truncated,
+    // a move count beyond 28 bits or longer than any game
+    Count,
+    // the n-th index is past the legal moves
+    BadIndex(usize),
+}
+
+// The legal moves in an order that only depends on the position
+pub fn sorted_legal_moves(p: &Position) -> Vec<Move> {
+    let mut moves = p.legal_moves();
+    moves.sort_by_key(|m| {
+        (m.castle.bits(), m.drop.bits(), m.from.bits(), m.to.bits(), m.promote.bits())
+    });
+    moves
+}
+
+pub fn encode_game(start: &Position, moves: &[Move]) -> Result<Vec<u8>, GameCodecError> {
+    if moves.len() > LONGEST_GAME {
+        return Err(GameCodecError::Count);
+    }
+    let mut writer = BitWriter::default();
+    let mut n = moves.len();
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            writer.bytes.push(byte);
+            break;
+        }
+        writer.bytes.push(byte | 0x80);
+    }
+    let mut p = *start;
+    for (i, &mv) in moves.iter().enumerate() {
+        let legal = sorted_legal_moves(&p);
+        let index = legal.iter().position(|&m| m == mv).ok_or(GameCodecError::IllegalMove(i))?;
+        writer.write(index as u32, width(legal.len()));
+        p = p.make_move(mv);
+    }
+    Ok(writer.bytes)
+}
+
+pub fn decode_game(start: &Position, bytes: &[u8]) -> Result<Vec<Move>, GameCodecError> {
+    let mut count = 0usize;
+    let mut header = 0;
+    loop {
+        let byte = *bytes.get(header).ok_or(GameCodecError::Truncated)?;
+        if header == 4 {
+            return Err(GameCodecError::Count);
+        }
+        count |= ((byte & 0x7F) as usize) << (7 * header);
+        header += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    // forced moves take no bits, so the input length does not bound
+    // the work: the count does
+    if count > LONGEST_GAME {
+        return Err(GameCodecError::Count);
+    }
+    let mut reader = BitReader {
+        bytes: &bytes[header..],
+        position: 0,
+    };
+    let mut p = *start;
+    let mut result = Vec::new();
+    for i in 0..count {
+        let legal = sorted_legal_moves(&p);
+        let index = reader.read(width(legal.len())).ok_or(GameCodecError::Truncated)?;
+        let mv = *legal.get(index as usize).ok_or(GameCodecError::BadIndex(i))?;
+        result.push(mv);
+        p = p.make_move(mv);
+    }
+    Ok(result)
+}
+
+// bits needed for an index below `n`
+fn width(n: usize) -> u32 {
+    if n <= 1 {
+        0
+    } else {
+        32 - (n as u32 - 1).leading_zeros()
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    // bits used in the last byte, 0 for a fresh one
+    used: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, width: u32) {
+        for bit in (0..width).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= (((value >> bit) & 1) as u8) << (7 - self.used);
+            self.used = (self.used + 1) % 8;
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    // in bits
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn read(&mut self, width: u32) -> Option<u32> {
+        let mut result = 0;
+        for _ in 0..width {
+            let byte = *self.bytes.get(self.position / 8)?;
+            result = result << 1 | ((byte >> (7 - self.position % 8)) & 1) as u32;
+            self.position += 1;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn game(start: &Position, moves: &[&str]) -> Vec<Move> {
+        let mut p = *start;
+        moves.iter()
+            .map(|san| {
+                let mv = p.parse_san(san).unwrap();
+                p = p.make_move(mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width(0), 0);
+        assert_eq!(width(1), 0);
+        assert_eq!(width(2), 1);
+        assert_eq!(width(20), 5);
+        assert_eq!(width(32), 5);
+        assert_eq!(width(33), 6);
+    }
+
+    #[test]
+    fn round_trip() {
+        let start = Position::start(Rules::Standard);
+        let moves = game(&start,
+                         &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7",
+                           "Re1", "b5", "Bb3", "d6", "c3", "O-O", "h3", "Nb8", "d4", "Nbd7"]);
+        let bytes = encode_game(&start, &moves).unwrap();
+        // one byte of count, at most 6 bits a move
+        assert!(bytes.len() <= 1 + (moves.len() * 6 + 7) / 8);
+        assert_eq!(decode_game(&start, &bytes), Ok(moves));
+        assert_eq!(decode_game(&start, &encode_game(&start, &[]).unwrap()), Ok(vec![]));
+    }
+
+    #[test]
+    fn forced_moves_take_no_bits() {
+        // the black king has just h7
+        let start = Position::parse("7k/8/8/8/8/8/8/K5R1 b - - 0 1");
+        let moves = game(&start, &["Kh7"]);
+        assert_eq!(encode_game(&start, &moves), Ok(vec![1]));
+        assert_eq!(decode_game(&start, &[1]), Ok(moves));
+    }
+
+    #[test]
+    fn variants() {
+        let start = Position::start(Rules::Antichess);
+        let moves = game(&start, &["e3", "b5", "Bxb5", "c6", "Bxc6"]);
+        let bytes = encode_game(&start, &moves).unwrap();
+        assert_eq!(decode_game(&start, &bytes), Ok(moves));
+    }
+
+    #[test]
+    fn errors() {
+        let start = Position::start(Rules::Standard);
+        assert_eq!(encode_game(&start, &[Move::parse("e2e5")]),
+                   Err(GameCodecError::IllegalMove(0)));
+        assert_eq!(decode_game(&start, &[]), Err(GameCodecError::Truncated));
+        assert_eq!(decode_game(&start, &[2, 0]), Err(GameCodecError::Truncated));
+        // 2^28 - 1 moves announced
+        assert_eq!(decode_game(&start, &[0xFF, 0xFF, 0xFF, 0x7F]), Err(GameCodecError::Count));
+        // as many as there can be, none there
+        assert_eq!(decode_game(&start, &[0xA1, 0x8A, 0x01]), Err(GameCodecError::Truncated));
+        let forced = Position::parse("7k/8/8/8/8/8/8/K5R1 b - - 0 1");
+        assert_eq!(decode_game(&forced, &[0xA2, 0x8A, 0x01]), Err(GameCodecError::Count));
+        assert_eq!(encode_game(&forced, &vec![Move::parse("h8h7"); LONGEST_GAME + 1]),
+                   Err(GameCodecError::Count));
+        assert_eq!(decode_game(&start, &[0xFF; 8]), Err(GameCodecError::Count));
+        // 20 moves fit in 5 bits, 31 does not point at any
+        assert_eq!(decode_game(&start, &[1, 0xF8]), Err(GameCodecError::BadIndex(0)));
+    }
+}
@@ -24,6 +24,10 @@ extern crate quickcheck;
 extern crate bitflags;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
 
 pub mod file;
 pub mod rank;
@@ -41,7 +45,21 @@ pub mod bit_board;
 mod board88;
 pub mod moves;
 pub mod castle;
+pub mod pocket;
+pub mod rules;
+pub mod variant;
 pub mod position;
 pub mod analysis;
+pub mod search;
+pub mod book;
+pub mod tablebase;
+pub mod retro_check;
+pub mod game_codec;
+pub mod render;
+pub mod svg;
+pub mod pgn;
+pub mod uci_client;
+#[cfg(feature = "serde")]
+mod serde_support;
 
 mod check_namespaces;
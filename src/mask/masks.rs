@@ -93,6 +93,9 @@ pub const F1: Mask = Mask(0x2000000000000000);
 pub const G1: Mask = Mask(0x4000000000000000);
 pub const H1: Mask = Mask(0x8000000000000000);
 
+// d4, d5, e4, e5
+pub const CENTER: Mask = Mask(0x1818000000);
+
 #[derive(Copy, Clone, Debug)]
 pub struct Masks;
 impl IntoIterator for Masks {
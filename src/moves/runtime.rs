@@ -10,6 +10,8 @@ pub struct Move {
     pub to: Square,
     pub promote: Kind,
     pub castle: Castle,
+    // crazyhouse: the kind put on `to` from the pocket
+    pub drop: Kind,
 }
 
 const CASTLE_Q: Move = Move {
@@ -17,12 +19,14 @@ const CASTLE_Q: Move = Move {
     to: UNDEFINED_SQUARE,
     promote: UNKNOWN,
     castle: castle::Q,
+    drop: UNKNOWN,
 };
 const CASTLE_K: Move = Move {
     from: UNDEFINED_SQUARE,
     to: UNDEFINED_SQUARE,
     promote: UNKNOWN,
     castle: castle::K,
+    drop: UNKNOWN,
 };
 
 impl Move {
@@ -32,6 +36,7 @@ impl Move {
             to: to,
             promote: UNKNOWN,
             castle: castle::NONE,
+            drop: UNKNOWN,
         }
     }
     pub fn promote(from: Square, to: Square, promote: Kind) -> Self {
@@ -40,8 +45,21 @@ impl Move {
             to: to,
             promote: promote,
             castle: castle::NONE,
+            drop: UNKNOWN,
         }
     }
+    pub fn drop(drop: Kind, to: Square) -> Self {
+        Move {
+            from: UNDEFINED_SQUARE,
+            to: to,
+            promote: UNKNOWN,
+            castle: castle::NONE,
+            drop: drop,
+        }
+    }
+    pub fn is_drop(&self) -> bool {
+        self.drop != UNKNOWN
+    }
 
     pub fn parse(input: &str) -> Self {
         parse_move(input.as_bytes()).unwrap().1
@@ -57,6 +75,9 @@ impl Display for Move {
                 "O-O"
             });
         }
+        if self.is_drop() {
+            return write!(f, "{}@{}", self.drop, self.to);
+        }
         write!(f, "{}-{}", self.from, self.to)?;
         if self.promote != UNKNOWN {
             write!(f, "={}", self.promote)?;
@@ -72,7 +93,8 @@ complete!(chain!(
         value!(KNIGHT, char!('N')) |
         value!(BISHOP, char!('B')) |
         value!(ROOK, char!('R')) |
-        value!(QUEEN, char!('Q')) ),
+        value!(QUEEN, char!('Q')) |
+        value!(KING, char!('K')) ),
 || result)));
 
 named!(parse_straight(&[u8]) -> Move,
@@ -85,6 +107,19 @@ chain!(
             .unwrap_or(UNKNOWN)))
 );
 
+named!(parse_drop(&[u8]) -> Move,
+chain!(
+    kind: alt!(
+        value!(PAWN, char!('P')) |
+        value!(KNIGHT, char!('N')) |
+        value!(BISHOP, char!('B')) |
+        value!(ROOK, char!('R')) |
+        value!(QUEEN, char!('Q')) ) ~
+    char!('@') ~
+    to: parse_square,
+    || Move::drop(kind, to))
+);
+
 named!(parse_castle(&[u8]) -> Move,
 alt!(
     complete!(value!(CASTLE_Q, tag!("o-o-o"))) |
@@ -96,7 +131,7 @@ alt!(
 ));
 
 named!(pub parse_move(&[u8]) -> Move,
-alt!(parse_straight | parse_castle));
+alt!(parse_straight | parse_drop | parse_castle));
 
 
 #[cfg(test)]
@@ -108,14 +143,14 @@ mod test {
     fn usual_move() {
         let m = Move::new(E2, E4);
         assert_eq!(format!("{:?}", m),
-        "Move { from: Square(52), to: Square(36), promote: Kind(16), castle: NONE }");
+        "Move { from: Square(52), to: Square(36), promote: Kind(16), castle: NONE, drop: Kind(16) }");
     }
 
     #[test]
     fn promotion_move() {
         let m = Move::promote(E2, E4, QUEEN);
         assert_eq!(format!("{:?}", m),
-        "Move { from: Square(52), to: Square(36), promote: Kind(4), castle: NONE }");
+        "Move { from: Square(52), to: Square(36), promote: Kind(4), castle: NONE, drop: Kind(16) }");
     }
 
     #[test]
@@ -144,4 +179,13 @@ mod test {
     fn parse_promotion() {
         assert_eq!(format!("{}", Move::parse("e2-e4=Q")), "e2-e4=Q");
     }
+
+    #[test]
+    fn parse_drop() {
+        let m = Move::parse("N@f3");
+        assert_eq!(m, Move::drop(KNIGHT, F3));
+        assert!(m.is_drop());
+        assert_eq!(format!("{}", m), "N@f3");
+        assert_eq!(format!("{}", Move::parse("P@e4")), "P@e4");
+    }
 }
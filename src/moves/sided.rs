@@ -8,6 +8,7 @@ pub trait SidedMove {
     fn to(&self) -> Square;
     fn promote(&self) -> Kind;
     fn castle(&self) -> Castle;
+    fn drop(&self) -> Kind;
 }
 
 #[derive(Eq, Hash, Debug, Copy, Clone, PartialEq)]
@@ -31,6 +32,9 @@ impl SidedMove for WhiteMove {
         self.0.castle
 
     }
+    fn drop(&self) -> Kind{
+        self.0.drop
+    }
 
 }
 
@@ -49,6 +53,9 @@ impl SidedMove for BlackMove {
         self.0.castle
 
     }
+    fn drop(&self) -> Kind{
+        self.0.drop
+    }
 }
 
 
@@ -0,0 +1,471 @@
+use color::Color;
+use moves::Move;
+use nom::IResult;
+use position::{Outcome, Position, SanError, parse_position};
+use rules::Rules;
+use std::fmt::{Display, Formatter, Result};
+use std::str::Lines;
+
+// Movetext lines are kept below this
+const WIDTH: usize = 80;
+
+static ROSTER: [&'static str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+// One game of a PGN file. The seven tag roster, `Variant`, `SetUp` and
+// `FEN` are written from the fields, only the other tags come from `tags`.
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+    // None while the game goes on
+    pub result: Option<Outcome>,
+}
+
+impl Game {
+    pub fn new(start: Position) -> Self {
+        Game {
+            tags: vec![],
+            start: start,
+            moves: vec![],
+            result: None,
+        }
+    }
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|t| t.0 == name).map(|t| t.1.as_str())
+    }
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter().position(|t| t.0 == name) {
+            Some(i) => self.tags[i].1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+    // The start and every position after it
+    pub fn positions(&self) -> Vec<Position> {
+        let mut result = vec![self.start];
+        for &mv in &self.moves {
+            let next = result[result.len() - 1].make_move(mv);
+            result.push(next);
+        }
+        result
+    }
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum PgnError {
+    // a line in the tag section that is not a tag pair
+    Tag,
+    Fen,
+    Variant,
+    // the n-th move of the game, counting from 0
+    San(usize, SanError),
+    // a comment or variation still open at the end of the game
+    Unterminated,
+}
+
+impl Game {
+    // A single game: tag pairs, then the movetext. Comments, variations
+    // and NAGs are skipped; the result token wins over the Result tag.
+    pub fn parse(input: &str) -> ::std::result::Result<Game, PgnError> {
+        let mut tags = vec![];
+        let mut movetext = String::new();
+        for line in input.lines() {
+            let line = line.trim();
+            if line.starts_with('%') {
+                continue;
+            }
+            if movetext.is_empty() && line.starts_with('[') {
+                tags.push(parse_tag(line).ok_or(PgnError::Tag)?);
+            } else {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+        let mut game = Game::new(Position::start(Rules::Standard));
+        game.tags = tags;
+        let rules = match game.tag("Variant") {
+            Some(name) => Some(Rules::from_name(name).ok_or(PgnError::Variant)?),
+            None => None,
+        };
+        game.start = match game.tag("FEN") {
+            Some(fen) => {
+                let p = match parse_position(fen.as_bytes()) {
+                    IResult::Done(_, p) => p,
+                    _ => return Err(PgnError::Fen),
+                };
+                // without the tag, the FEN tells the variant
+                let p = Position { rules: rules.unwrap_or(p.rules), ..p };
+                if !p.validate().is_empty() {
+                    return Err(PgnError::Fen);
+                }
+                p
+            }
+            None => Position::start(rules.unwrap_or(Rules::Standard)),
+        };
+        game.result = game.tag("Result").and_then(parse_result);
+
+        let mut p = game.start;
+        for token in Tokens(&movetext) {
+            let token = token?;
+            if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+                game.result = parse_result(token);
+                break;
+            }
+            let mv = p.parse_san(token).map_err(|e| PgnError::San(game.moves.len(), e))?;
+            game.moves.push(mv);
+            p = p.make_move(mv);
+        }
+        Ok(game)
+    }
+}
+
+// The games of a PGN file, one after the other. A game goes on until
+// the next tag section, so a broken game does not take the rest along.
+pub fn parse_games<'a>(input: &'a str) -> Games<'a> {
+    Games { lines: input.lines() }
+}
+
+#[derive(Debug)]
+pub struct Games<'a> {
+    lines: Lines<'a>,
+}
+
+impl<'a> Iterator for Games<'a> {
+    type Item = ::std::result::Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = String::new();
+        let mut movetext = false;
+        // open braces, a comment may hold a line starting with '['
+        let mut comment = false;
+        loop {
+            let rest = self.lines.clone();
+            let line = match self.lines.next() {
+                Some(line) => line,
+                None => break,
+            };
+            let trimmed = line.trim();
+            if !comment && movetext && trimmed.starts_with('[') {
+                self.lines = rest;
+                break;
+            }
+            if !comment && !trimmed.is_empty() && !trimmed.starts_with('[') &&
+               !trimmed.starts_with('%') {
+                movetext = true;
+            }
+            for c in trimmed.chars() {
+                match c {
+                    '{' => comment = true,
+                    '}' => comment = false,
+                    ';' if !comment => break,
+                    _ => {}
+                }
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+        if text.trim().is_empty() {
+            None
+        } else {
+            Some(Game::parse(&text))
+        }
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    if !line.starts_with('[') || !line.ends_with(']') {
+        return None;
+    }
+    let inner = line[1..line.len() - 1].trim();
+    let space = inner.find(char::is_whitespace)?;
+    let value = inner[space..].trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return None;
+    }
+    let mut unescaped = String::new();
+    let mut escape = false;
+    for c in value[1..value.len() - 1].chars() {
+        if c == '\\' && !escape {
+            escape = true;
+        } else {
+            unescaped.push(c);
+            escape = false;
+        }
+    }
+    Some((inner[..space].to_string(), unescaped))
+}
+
+// "*" is the only token for a game that goes on
+fn parse_result(token: &str) -> Option<Outcome> {
+    match token {
+        "1-0" => Some(Outcome::Win(Color::White)),
+        "0-1" => Some(Outcome::Win(Color::Black)),
+        "1/2-1/2" => Some(Outcome::Draw),
+        _ => None,
+    }
+}
+
+// The moves and the result of movetext, without move numbers,
+// comments, variations and NAGs
+struct Tokens<'a>(&'a str);
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = ::std::result::Result<&'a str, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let s = self.0.trim_left();
+            let skip = match s.chars().next() {
+                None => return None,
+                Some('{') => s.find('}').map(|i| i + 1),
+                Some(';') => Some(s.find('\n').unwrap_or(s.len())),
+                Some('(') => variation_end(s),
+                Some('$') => Some(1 + s[1..].find(|c: char| !c.is_digit(10)).unwrap_or(s.len() - 1)),
+                Some(_) => {
+                    let end = s.find(|c: char| c.is_whitespace() || "{}();$".contains(c))
+                        .unwrap_or(s.len());
+                    self.0 = &s[end..];
+                    // "12." and "12..." alone or glued to the move
+                    let token = s[..end].trim_left_matches(|c: char| c.is_digit(10));
+                    let token = if token.starts_with('.') {
+                        token.trim_left_matches('.')
+                    } else {
+                        &s[..end]
+                    };
+                    if token.is_empty() {
+                        continue;
+                    }
+                    return Some(Ok(token));
+                }
+            };
+            match skip {
+                Some(n) => self.0 = &s[n..],
+                None => {
+                    self.0 = "";
+                    return Some(Err(PgnError::Unterminated));
+                }
+            }
+        }
+    }
+}
+
+// past the parenthesis closing the one `s` starts with
+fn variation_end(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut comment = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => comment = true,
+            '}' => comment = false,
+            '(' if !comment => depth += 1,
+            ')' if !comment => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn result_str(result: Option<Outcome>) -> &'static str {
+    match result {
+        Some(Outcome::Win(Color::White)) => "1-0",
+        Some(Outcome::Win(Color::Black)) => "0-1",
+        Some(Outcome::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        for &name in &ROSTER {
+            let value = match name {
+                "Result" => result_str(self.result),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            write_tag(f, name, value)?;
+        }
+        if self.start.rules != Rules::Standard {
+            write_tag(f, "Variant", self.start.rules.name())?;
+        }
+        if self.start != Position::start(self.start.rules) {
+            write_tag(f, "SetUp", "1")?;
+            // there are no clocks in a position
+            write_tag(f, "FEN", &self.start.fen(0, 1))?;
+        }
+        for &(ref name, ref value) in &self.tags {
+            let derived = ["Variant", "SetUp", "FEN"];
+            if !ROSTER.contains(&name.as_str()) && !derived.contains(&name.as_str()) {
+                write_tag(f, name, value)?;
+            }
+        }
+        writeln!(f)?;
+
+        let mut line = String::new();
+        let mut p = self.start;
+        let mut number = 1;
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 / 2 + 1);
+        for (i, &mv) in self.moves.iter().enumerate() {
+            if p.active == Color::White {
+                tokens.push(format!("{}.", number));
+            } else {
+                if i == 0 {
+                    tokens.push(format!("{}...", number));
+                }
+                number += 1;
+            }
+            tokens.push(p.san(mv));
+            p = p.make_move(mv);
+        }
+        tokens.push(result_str(self.result).to_string());
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() >= WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+fn write_tag(f: &mut Formatter, name: &str, value: &str) -> Result {
+    writeln!(f,
+             "[{} \"{}\"]",
+             name,
+             value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(start: Position, moves: &[&str]) -> Game {
+        let mut game = Game::new(start);
+        let mut p = start;
+        for san in moves {
+            let mv = p.parse_san(san).unwrap();
+            game.moves.push(mv);
+            p = p.make_move(mv);
+        }
+        game
+    }
+
+    #[test]
+    fn export() {
+        let mut game = play(Position::start(Rules::Standard),
+                            &["f3", "e5", "g4", "Qh4#"]);
+        game.result = Some(Outcome::Win(Color::Black));
+        game.set_tag("White", "Fool");
+        game.set_tag("Annotator", "Say \"hi\"");
+        assert_eq!(format!("{}", game),
+                   "[Event \"?\"]\n\
+                    [Site \"?\"]\n\
+                    [Date \"????.??.??\"]\n\
+                    [Round \"?\"]\n\
+                    [White \"Fool\"]\n\
+                    [Black \"?\"]\n\
+                    [Result \"0-1\"]\n\
+                    [Annotator \"Say \\\"hi\\\"\"]\n\
+                    \n\
+                    1. f3 e5 2. g4 Qh4# 0-1\n");
+        assert_eq!(game.positions().len(), 5);
+        assert_eq!(game.positions()[4].outcome(), game.result);
+    }
+
+    #[test]
+    fn set_up_positions() {
+        let game = play(Position::parse("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1"),
+                        &["Kd7", "O-O-O+"]);
+        let pgn = format!("{}", game);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n\n1... Kd7 2. O-O-O+ *\n"));
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let game = Game::new(Position::parse(fen));
+        assert!(format!("{}", game).contains(&format!("[FEN \"{}\"]\n", fen)));
+        assert_eq!(Game::parse(&format!("{}", game)).unwrap().start, game.start);
+        let game = Game::new(Position::start(Rules::Horde));
+        assert!(format!("{}", game).contains("[Variant \"Horde\"]\n"));
+        assert!(!format!("{}", game).contains("[FEN "));
+    }
+
+    #[test]
+    fn wrapping() {
+        let moves = ["Nf3", "Nf6", "Ng1", "Ng8"];
+        let game = play(Position::start(Rules::Standard),
+                        &moves.iter().cycle().take(60).cloned().collect::<Vec<_>>());
+        let pgn = format!("{}", game);
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|l| l.len() < WIDTH));
+        assert!(movetext.ends_with("30. Ng1 Ng8 *\n"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = play(Position::parse("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1"),
+                            &["Kd7", "O-O-O+", "Ke6"]);
+        game.set_tag("Event", "Test \"quoted\" \\ slash");
+        let parsed = Game::parse(&format!("{}", game)).unwrap();
+        assert_eq!(parsed.start, game.start);
+        assert_eq!(parsed.moves, game.moves);
+        assert_eq!(parsed.tag("Event"), game.tag("Event"));
+        let game = play(Position::start(Rules::Crazyhouse), &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5", "Bc4", "P@e6"]);
+        let parsed = Game::parse(&format!("{}", game)).unwrap();
+        assert_eq!(parsed.start.rules, Rules::Crazyhouse);
+        assert_eq!(parsed.moves, game.moves);
+    }
+
+    #[test]
+    fn movetext() {
+        let game = Game::parse("[Result \"1-0\"]\n\
+                                1.e4 {best by test} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) \
+                                2... Nc6!? ; rest of line 3. Bb5\n\
+                                3. Bb5 a6 *")
+            .unwrap();
+        assert_eq!(game.moves.len(), 6);
+        assert_eq!(game.result, None);
+        assert_eq!(game.positions()[5].san(game.moves[5]), "a6");
+        assert_eq!(Game::parse("1. e4 e5 2. Ke3"), Err(PgnError::San(2, SanError::Illegal)));
+        assert_eq!(Game::parse("1. e4 { open"), Err(PgnError::Unterminated));
+        assert_eq!(Game::parse("[Event \"x\"\n1. e4"), Err(PgnError::Tag));
+        assert_eq!(Game::parse("[Variant \"Chess 960\"]\n1. e4"), Err(PgnError::Variant));
+        assert_eq!(Game::parse("[FEN \"9/8\"]\n1. e4"), Err(PgnError::Fen));
+        // no kings
+        assert_eq!(Game::parse("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n*"), Err(PgnError::Fen));
+    }
+
+    #[test]
+    fn variant_from_fen() {
+        let game = Game::parse("[FEN \"4k3/8/8/8/8/8/8/4K3[Q] w - - 0 1\"]\n1. Q@e2 *").unwrap();
+        assert_eq!(game.start.rules, Rules::Crazyhouse);
+        assert_eq!(game.moves.len(), 1);
+        let game = Game::parse("[FEN \"4k3/8/8/8/8/8/8/4K3 w - - +2+0 0 1\"]\n*").unwrap();
+        assert_eq!(game.start.rules, Rules::ThreeCheck);
+        // the tag wins
+        let game = Game::parse("[Variant \"Atomic\"]\n[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n*")
+            .unwrap();
+        assert_eq!(game.start.rules, Rules::Atomic);
+    }
+
+    #[test]
+    fn many_games() {
+        let input = "[Event \"a\"]\n\n1. e4 e5 1-0\n\n\
+                     [Event \"b\"]\n1. e4 {\n[not a tag]\n} Nf6 2. Ke3\n\n\
+                     [Event \"c\"]\n[Result \"1/2-1/2\"]\n\n1. d4\n";
+        let games: Vec<_> = parse_games(input).collect();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].as_ref().unwrap().result, Some(Outcome::Win(Color::White)));
+        assert_eq!(games[1], Err(PgnError::San(2, SanError::Illegal)));
+        let last = games[2].as_ref().unwrap();
+        assert_eq!((last.tag("Event"), last.moves.len()), (Some("c"), 1));
+        assert_eq!(last.result, Some(Outcome::Draw));
+    }
+}
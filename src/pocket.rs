@@ -0,0 +1,146 @@
+use std::fmt::{Display, Result, Formatter};
+use nom::IResult;
+use nom::IResult::*;
+use color::Color;
+use kind::*;
+use piece::*;
+
+// Crazyhouse reserves: how many pieces of every kind each side
+// holds in hand. Kings are never there.
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Pockets([u8; PIECES_COUNT]);
+
+// the order pieces are printed in
+static DISPLAY_ORDER: [Kind; 5] = [QUEEN, ROOK, BISHOP, KNIGHT, PAWN];
+
+impl Pockets {
+    pub fn new() -> Self {
+        Pockets([0; PIECES_COUNT])
+    }
+    pub fn count(&self, piece: Piece) -> u8 {
+        self.0[piece.bits() as usize]
+    }
+    // Fails when the pocket already holds 255 pieces of the kind
+    pub fn add(&mut self, piece: Piece) -> ::std::result::Result<(), ParsingError> {
+        debug_assert_ne!(piece.kind(), KING);
+        let count = &mut self.0[piece.bits() as usize];
+        *count = count.checked_add(1).ok_or(ParsingError::TooManyPieces)?;
+        Ok(())
+    }
+    pub fn take(&mut self, piece: Piece) {
+        debug_assert!(self.count(piece) > 0, "{} is not in the pocket", piece);
+        self.0[piece.bits() as usize] -= 1;
+    }
+    pub fn kinds_of(&self, color: Color) -> Vec<Kind> {
+        DISPLAY_ORDER.iter()
+            .cloned()
+            .filter(|k| self.count(k.of(color)) > 0)
+            .collect()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&n| n == 0)
+    }
+    pub fn parse(input: &str) -> Self {
+        parse_pockets(input.as_bytes()).unwrap().1
+    }
+}
+
+impl Display for Pockets {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[")?;
+        for &color in &[Color::White, Color::Black] {
+            for kind in &DISPLAY_ORDER {
+                let piece = kind.of(color);
+                for _ in 0..self.count(piece) {
+                    write!(f, "{}", piece)?;
+                }
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum ParsingError {
+    MissingOpeningBracket,
+    UnrecognizedToken,
+    TooManyPieces,
+}
+
+// "[QNnp]", "[]" or "[-]"
+pub fn parse_pockets(input: &[u8]) -> IResult<&[u8], Pockets, ParsingError> {
+    use nom::Err::Position;
+    use nom::ErrorKind::Custom;
+    use nom::Needed::Unknown;
+    use self::ParsingError::*;
+
+    if input.is_empty() {
+        return Incomplete(Unknown);
+    }
+    if input[0] != b'[' {
+        return Error(Position(Custom(MissingOpeningBracket), input));
+    }
+    let mut result = Pockets::new();
+    for (i, &c) in input.iter().enumerate().skip(1) {
+        match c {
+            b']' => return Done(&input[i + 1..], result),
+            b'-' => {}
+            b'P' | b'N' | b'B' | b'R' | b'Q' | b'p' | b'n' | b'b' | b'r' | b'q' => {
+                if let Err(e) = result.add(Piece::parse(c as char)) {
+                    return Error(Position(Custom(e), &input[i..]));
+                }
+            }
+            _ => return Error(Position(Custom(UnrecognizedToken), &input[i..])),
+        }
+    }
+    Incomplete(Unknown)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_display() {
+        assert_eq!(format!("{}", Pockets::parse("[]")), "[]");
+        assert_eq!(format!("{}", Pockets::parse("[-]")), "[]");
+        assert_eq!(format!("{}", Pockets::parse("[pNQqPP]")), "[QNPPqp]");
+    }
+
+    #[test]
+    fn count() {
+        let p = Pockets::parse("[NNb]");
+        assert_eq!(p.count(WHITE_KNIGHT), 2);
+        assert_eq!(p.count(BLACK_BISHOP), 1);
+        assert_eq!(p.count(BLACK_KNIGHT), 0);
+        assert_eq!(p.kinds_of(Color::White), [KNIGHT]);
+        assert_eq!(p.kinds_of(Color::Black), [BISHOP]);
+    }
+
+    #[test]
+    fn add_take() {
+        let mut p = Pockets::new();
+        assert!(p.is_empty());
+        p.add(BLACK_QUEEN).unwrap();
+        assert!(!p.is_empty());
+        p.take(BLACK_QUEEN);
+        assert_eq!(p, Pockets::new());
+    }
+
+    #[test]
+    fn parse_errors() {
+        use nom::Err::Position;
+        use nom::ErrorKind::Custom;
+        use nom::Needed::Unknown;
+
+        assert_eq!(parse_pockets(b" w"),
+                   Error(Position(Custom(ParsingError::MissingOpeningBracket), &b" w"[..])));
+        assert_eq!(parse_pockets(b"[Qk]"),
+                   Error(Position(Custom(ParsingError::UnrecognizedToken), &b"k]"[..])));
+        assert_eq!(parse_pockets(b"[Q"), Incomplete(Unknown));
+        assert_eq!(parse_pockets(b"[Q] w"), Done(&b" w"[..], Pockets::parse("[Q]")));
+        let full = format!("[{}]", "P".repeat(256));
+        assert_eq!(parse_pockets(full.as_bytes()),
+                   Error(Position(Custom(ParsingError::TooManyPieces), &b"P]"[..])));
+    }
+}
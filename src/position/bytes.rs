@@ -0,0 +1,162 @@
+use super::root::*;
+use super::validate_position::Assessment;
+use bit_board::BitBoard;
+use castle::Castle;
+use color::Color;
+use file::File;
+use mask::Mask;
+use piece::*;
+use rules::Rules;
+
+// occupancy, 32 piece codes, side and castling, en passant
+pub const ENCODED_SIZE: usize = 8 + 16 + 1 + 1;
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum CodecError {
+    // only standard positions fit
+    Variant,
+    TooManyPieces,
+    PieceCode,
+    // the unused bits must be zero
+    Padding,
+    EnPassant,
+    Invalid(Assessment),
+}
+
+impl Position {
+    // Fixed-size encoding: the occupancy mask (little endian), then a
+    // 4-bit piece code per occupied square in square order, low nibble
+    // first, then white-to-move in bit 0 and the castling rights in
+    // bits 1-4, then the en passant file plus one or zero. There are
+    // no move clocks in a `Position`, so none are stored.
+    pub fn to_bytes(&self) -> Result<[u8; ENCODED_SIZE], CodecError> {
+        if self.rules != Rules::Standard {
+            return Err(CodecError::Variant);
+        }
+        let occupation = self.board.occupation();
+        if occupation.count() > 32 {
+            return Err(CodecError::TooManyPieces);
+        }
+        let mut result = [0; ENCODED_SIZE];
+        for (i, byte) in result[..8].iter_mut().enumerate() {
+            *byte = (occupation.bits() >> (8 * i)) as u8;
+        }
+        for (i, square) in occupation.single_bits().enumerate() {
+            let code = self.board.get_piece(square).bits();
+            result[8 + i / 2] |= code << (4 * (i % 2));
+        }
+        let white = if self.active == Color::White { 1 } else { 0 };
+        result[24] = white | self.available.bits() << 1;
+        result[25] = self.en_passant.map_or(0, |f| f.bits() + 1);
+        Ok(result)
+    }
+
+    // The inverse of `to_bytes`, which also insists on a valid position
+    pub fn from_bytes(bytes: &[u8; ENCODED_SIZE]) -> Result<Position, CodecError> {
+        let occupation = Mask::new(bytes[..8]
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, &b)| acc | (b as u64) << (8 * i)));
+        if occupation.count() > 32 {
+            return Err(CodecError::TooManyPieces);
+        }
+        let mut board = BitBoard::new();
+        let mut used = 0;
+        for (i, square) in occupation.single_bits().enumerate() {
+            let code = bytes[8 + i / 2] >> (4 * (i % 2)) & 0xF;
+            if code as usize >= PIECES_COUNT {
+                return Err(CodecError::PieceCode);
+            }
+            board.set_piece(square, Piece::new(code));
+            used = i + 1;
+        }
+        let padding = (used..32).any(|i| bytes[8 + i / 2] >> (4 * (i % 2)) & 0xF != 0);
+        if padding || bytes[24] >> 5 != 0 {
+            return Err(CodecError::Padding);
+        }
+        if bytes[25] > 8 {
+            return Err(CodecError::EnPassant);
+        }
+        let active = if bytes[24] & 1 == 1 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let p = Position {
+            available: Castle::from_bits_truncate(bytes[24] >> 1),
+            en_passant: if bytes[25] == 0 {
+                None
+            } else {
+                Some(File::from_bits(bytes[25] - 1))
+            },
+            ..Position::new(board, active)
+        };
+        let assessment = p.validate();
+        if !assessment.is_empty() {
+            return Err(CodecError::Invalid(assessment));
+        }
+        Ok(p)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use position::validate_position::*;
+
+    fn round_trip(fen: &str) {
+        let p = Position::parse(fen);
+        let bytes = p.to_bytes().unwrap();
+        assert_eq!(format!("{}", Position::from_bytes(&bytes).unwrap()),
+                   format!("{}", p));
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        round_trip("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 0 1");
+        round_trip("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f 0 3");
+        round_trip("8/8/8/8/8/8/8/k6K b - - 0 1");
+    }
+
+    #[test]
+    fn layout() {
+        let p = Position::parse("8/8/8/8/8/8/8/k6K b - - 0 1");
+        let bytes = p.to_bytes().unwrap();
+        // a1 and h1 are the two top bits
+        assert_eq!(bytes[..8], [0, 0, 0, 0, 0, 0, 0, 0x81]);
+        assert_eq!(bytes[8], BLACK_KING.bits() | WHITE_KING.bits() << 4);
+        assert_eq!(bytes[24], 0);
+        assert_eq!(bytes[25], 0);
+    }
+
+    #[test]
+    fn encoding_errors() {
+        let p = Position::start(Rules::Horde);
+        assert_eq!(p.to_bytes(), Err(CodecError::Variant));
+        let p = Position { rules: Rules::Standard, ..p };
+        assert_eq!(p.to_bytes(), Err(CodecError::TooManyPieces));
+    }
+
+    #[test]
+    fn decoding_errors() {
+        let good = Position::parse("8/8/8/8/8/8/8/k6K b - - 0 1").to_bytes().unwrap();
+        let mut bytes = good;
+        bytes[8] = 0xFF;
+        assert_eq!(Position::from_bytes(&bytes), Err(CodecError::PieceCode));
+        let mut bytes = good;
+        bytes[9] = 1;
+        assert_eq!(Position::from_bytes(&bytes), Err(CodecError::Padding));
+        let mut bytes = good;
+        bytes[24] = 0x80;
+        assert_eq!(Position::from_bytes(&bytes), Err(CodecError::Padding));
+        let mut bytes = good;
+        bytes[25] = 9;
+        assert_eq!(Position::from_bytes(&bytes), Err(CodecError::EnPassant));
+        // both kings white
+        let mut bytes = good;
+        bytes[8] = WHITE_KING.bits() | WHITE_KING.bits() << 4;
+        assert_eq!(Position::from_bytes(&bytes),
+                   Err(CodecError::Invalid(HAS_NO_BLACK_KING | HAS_MORE_THAN_ONE_WHITE_KING)));
+    }
+}
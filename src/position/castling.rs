@@ -0,0 +1,95 @@
+use castle;
+use castle::Castle;
+use color::Color;
+use mask::Mask;
+use mask::masks::*;
+
+// Squares involved in castling for the given color and wing
+// (castle::K or castle::Q)
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub struct Castling {
+    pub king_from: Mask,
+    pub king_to: Mask,
+    pub rook_from: Mask,
+    pub rook_to: Mask,
+    // have to be vacant
+    pub empty: Mask,
+    // can not be attacked, king included
+    pub safe: Mask,
+}
+
+pub fn castling(color: Color, wing: Castle) -> Castling {
+    let king_side = wing.intersects(castle::K);
+    match (color, king_side) {
+        (Color::White, true) => Castling {
+            king_from: E1,
+            king_to: G1,
+            rook_from: H1,
+            rook_to: F1,
+            empty: F1 | G1,
+            safe: E1 | F1 | G1,
+        },
+        (Color::White, false) => Castling {
+            king_from: E1,
+            king_to: C1,
+            rook_from: A1,
+            rook_to: D1,
+            empty: B1 | C1 | D1,
+            safe: E1 | D1 | C1,
+        },
+        (Color::Black, true) => Castling {
+            king_from: E8,
+            king_to: G8,
+            rook_from: H8,
+            rook_to: F8,
+            empty: F8 | G8,
+            safe: E8 | F8 | G8,
+        },
+        (Color::Black, false) => Castling {
+            king_from: E8,
+            king_to: C8,
+            rook_from: A8,
+            rook_to: D8,
+            empty: B8 | C8 | D8,
+            safe: E8 | D8 | C8,
+        },
+    }
+}
+
+// Castling rights lost when something moves from or to the square
+pub fn spoiled_castling(square: Mask) -> Castle {
+    if square == E1 {
+        castle::W
+    } else if square == A1 {
+        castle::WQ
+    } else if square == H1 {
+        castle::WK
+    } else if square == E8 {
+        castle::B
+    } else if square == A8 {
+        castle::BQ
+    } else if square == H8 {
+        castle::BK
+    } else {
+        castle::NONE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spoiled() {
+        assert_eq!(spoiled_castling(E1), castle::W);
+        assert_eq!(spoiled_castling(H8), castle::BK);
+        assert_eq!(spoiled_castling(E4), castle::NONE);
+    }
+    #[test]
+    fn black_queen_side() {
+        let c = castling(Color::Black, castle::Q);
+        assert_eq!(c.king_to, C8);
+        assert_eq!(c.rook_to, D8);
+        assert!(!c.safe.intersects(B8));
+    }
+}
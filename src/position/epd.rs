@@ -0,0 +1,317 @@
+use std::fmt::{Display, Formatter, Result};
+use std::str;
+use nom::IResult;
+use nom::IResult::*;
+use moves::Move;
+use super::root::{Position, PositionError, parse_position};
+use super::san::SanError;
+
+// A position with the operations of a test suite line, e.g.
+// "r1b1k2r/... w KQkq - bm Nxe5; id \"WAC.001\";"
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Epd {
+    pub position: Position,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub enum Operation {
+    // bm
+    BestMoves(Vec<Move>),
+    // am
+    AvoidMoves(Vec<Move>),
+    Id(String),
+    // c0..c9
+    Comment(u8, String),
+    // hmvc
+    HalfmoveClock(u32),
+    // fmvn
+    FullmoveNumber(u32),
+    // dm, in moves
+    DirectMate(u32),
+    // pv, every move made on top of the previous one
+    PrincipalVariation(Vec<Move>),
+    // acd
+    AnalysisDepth(u32),
+    // ce, in centipawns for the side to move
+    CentipawnEvaluation(i32),
+    // anything else, kept as is
+    Other(String, Vec<String>),
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum EpdError {
+    Position(PositionError),
+    Opcode,
+    Operand,
+    UnterminatedString,
+    San(SanError),
+}
+
+impl Epd {
+    pub fn parse(input: &str) -> Self {
+        parse_epd(input.as_bytes()).unwrap().1
+    }
+    pub fn best_moves(&self) -> &[Move] {
+        self.moves(|op| match *op {
+            Operation::BestMoves(ref moves) => Some(moves),
+            _ => None,
+        })
+    }
+    pub fn avoid_moves(&self) -> &[Move] {
+        self.moves(|op| match *op {
+            Operation::AvoidMoves(ref moves) => Some(moves),
+            _ => None,
+        })
+    }
+    pub fn id(&self) -> Option<&str> {
+        self.operations.iter().filter_map(|op| match *op {
+            Operation::Id(ref id) => Some(id.as_str()),
+            _ => None,
+        }).next()
+    }
+    fn moves<F>(&self, f: F) -> &[Move]
+        where F: Fn(&Operation) -> Option<&Vec<Move>>
+    {
+        self.operations.iter().filter_map(f).next().map_or(&[], |v| &v[..])
+    }
+}
+
+impl Display for Epd {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}", self.position)?;
+        for op in &self.operations {
+            write!(f, " ")?;
+            match *op {
+                Operation::BestMoves(ref moves) => self.fmt_moves(f, "bm", moves, false)?,
+                Operation::AvoidMoves(ref moves) => self.fmt_moves(f, "am", moves, false)?,
+                Operation::PrincipalVariation(ref moves) => self.fmt_moves(f, "pv", moves, true)?,
+                Operation::Id(ref s) => write!(f, "id \"{}\"", s)?,
+                Operation::Comment(n, ref s) => write!(f, "c{} \"{}\"", n, s)?,
+                Operation::HalfmoveClock(n) => write!(f, "hmvc {}", n)?,
+                Operation::FullmoveNumber(n) => write!(f, "fmvn {}", n)?,
+                Operation::DirectMate(n) => write!(f, "dm {}", n)?,
+                Operation::AnalysisDepth(n) => write!(f, "acd {}", n)?,
+                Operation::CentipawnEvaluation(n) => write!(f, "ce {}", n)?,
+                Operation::Other(ref opcode, ref operands) => {
+                    write!(f, "{}", opcode)?;
+                    for operand in operands {
+                        write!(f, " {}", operand)?;
+                    }
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+impl Epd {
+    fn fmt_moves(&self, f: &mut Formatter, opcode: &str, moves: &[Move], line: bool) -> Result {
+        write!(f, "{}", opcode)?;
+        let mut p = self.position;
+        for &mv in moves {
+            write!(f, " {}", p.san(mv))?;
+            if line {
+                p = p.make_move(mv);
+            }
+        }
+        Ok(())
+    }
+}
+
+// The four FEN fields, then operations: an opcode, operands split by
+// spaces, strings in double quotes, and a semicolon. SAN operands are
+// resolved against the position.
+pub fn parse_epd(input: &[u8]) -> IResult<&[u8], Epd, EpdError> {
+    use nom::Err::Position as P;
+    use nom::ErrorKind::Custom;
+
+    let (mut rest, position) = match parse_position(input) {
+        Done(rest, position) => (rest, position),
+        Error(P(Custom(e), at)) => return Error(P(Custom(EpdError::Position(e)), at)),
+        Error(_) => return Error(P(Custom(EpdError::Position(PositionError::Whitespace)), input)),
+        Incomplete(needed) => return Incomplete(needed),
+    };
+    let mut operations = Vec::new();
+    loop {
+        rest = skip_spaces(rest);
+        if rest.is_empty() || rest[0] == b'\n' || rest[0] == b'\r' {
+            return Done(rest, Epd {
+                position: position,
+                operations: operations,
+            });
+        }
+        let at = rest;
+        let (after, opcode) = token(rest, |c| c.is_ascii_alphanumeric() || c == b'_');
+        if opcode.is_empty() || !opcode[0].is_ascii_alphabetic() {
+            return Error(P(Custom(EpdError::Opcode), at));
+        }
+        rest = after;
+        let mut operands = Vec::new();
+        loop {
+            rest = skip_spaces(rest);
+            if rest.is_empty() || rest[0] == b';' || rest[0] == b'\n' || rest[0] == b'\r' {
+                break;
+            }
+            if rest[0] == b'"' {
+                match rest[1..].iter().position(|&c| c == b'"') {
+                    Some(end) => {
+                        operands.push(&rest[1..end + 1]);
+                        rest = &rest[end + 2..];
+                    }
+                    None => return Error(P(Custom(EpdError::UnterminatedString), rest)),
+                }
+            } else {
+                let (after, operand) = token(rest, |c| c != b' ' && c != b';' && c != b'\n' &&
+                                                       c != b'\r');
+                operands.push(operand);
+                rest = after;
+            }
+        }
+        if !rest.is_empty() && rest[0] == b';' {
+            rest = &rest[1..];
+        }
+        let opcode = str::from_utf8(opcode).unwrap();
+        let operands = operands.into_iter()
+            .map(|o| String::from_utf8_lossy(o).into_owned())
+            .collect::<Vec<_>>();
+        match operation(&position, opcode, operands) {
+            Ok(op) => operations.push(op),
+            Err(e) => return Error(P(Custom(e), at)),
+        }
+    }
+}
+
+fn operation(p: &Position,
+             opcode: &str,
+             operands: Vec<String>)
+             -> ::std::result::Result<Operation, EpdError> {
+    use self::Operation::*;
+
+    let moves = |line: bool| {
+        let mut p = *p;
+        let mut result = Vec::new();
+        for san in &operands {
+            let mv = p.parse_san(san).map_err(EpdError::San)?;
+            if line {
+                p = p.make_move(mv);
+            }
+            result.push(mv);
+        }
+        Ok(result)
+    };
+    let string = || {
+        if operands.len() == 1 {
+            Ok(operands[0].clone())
+        } else {
+            Err(EpdError::Operand)
+        }
+    };
+    let number = || {
+        if operands.len() == 1 {
+            operands[0].parse::<i64>().map_err(|_| EpdError::Operand)
+        } else {
+            Err(EpdError::Operand)
+        }
+    };
+    let unsigned = || {
+        number().and_then(|n| if n < 0 { Err(EpdError::Operand) } else { Ok(n as u32) })
+    };
+    let comment = opcode.len() == 2 && opcode.as_bytes()[0] == b'c' &&
+                  opcode.as_bytes()[1].is_ascii_digit();
+    Ok(match opcode {
+        "bm" => BestMoves(moves(false)?),
+        "am" => AvoidMoves(moves(false)?),
+        "pv" => PrincipalVariation(moves(true)?),
+        "id" => Id(string()?),
+        "hmvc" => HalfmoveClock(unsigned()?),
+        "fmvn" => FullmoveNumber(unsigned()?),
+        "dm" => DirectMate(unsigned()?),
+        "acd" => AnalysisDepth(unsigned()?),
+        "ce" => CentipawnEvaluation(number()? as i32),
+        _ if comment => Comment(opcode.as_bytes()[1] - b'0', string()?),
+        _ => Other(opcode.to_string(), operands),
+    })
+}
+
+fn skip_spaces(input: &[u8]) -> &[u8] {
+    let n = input.iter().take_while(|&&c| c == b' ' || c == b'\t').count();
+    &input[n..]
+}
+
+fn token<F: Fn(u8) -> bool>(input: &[u8], f: F) -> (&[u8], &[u8]) {
+    let n = input.iter().take_while(|&&c| f(c)).count();
+    (&input[n..], &input[..n])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nom::Err::Position as P;
+    use nom::ErrorKind::Custom;
+
+    #[test]
+    fn win_at_chess() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+        let epd = Epd::parse(line);
+        assert_eq!(epd.best_moves(), [Move::parse("g3g6")]);
+        assert_eq!(epd.avoid_moves(), []);
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(format!("{}", epd), line);
+    }
+
+    #[test]
+    fn operations() {
+        let line = "4k3/8/8/8/8/8/8/R3K3 w Q - bm Ra8+ Kd2; am O-O-O; pv Ra8+ Kd7 Ra7+; \
+                    c0 \"two words\"; hmvc 3; fmvn 40; dm 5; acd 12; ce -35; sv Ra8;";
+        let epd = Epd::parse(line);
+        assert_eq!(epd.operations,
+                   vec![Operation::BestMoves(vec![Move::parse("a1a8"), Move::parse("e1d2")]),
+                        Operation::AvoidMoves(vec![Move::parse("O-O-O")]),
+                        Operation::PrincipalVariation(vec![Move::parse("a1a8"),
+                                                           Move::parse("e8d7"),
+                                                           Move::parse("a8a7")]),
+                        Operation::Comment(0, "two words".to_string()),
+                        Operation::HalfmoveClock(3),
+                        Operation::FullmoveNumber(40),
+                        Operation::DirectMate(5),
+                        Operation::AnalysisDepth(12),
+                        Operation::CentipawnEvaluation(-35),
+                        Operation::Other("sv".to_string(), vec!["Ra8".to_string()])]);
+        assert_eq!(format!("{}", epd), line);
+    }
+
+    #[test]
+    fn en_passant() {
+        // 1. e4 Nf6 2. e5 d5
+        let line = "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 bm exd6; \
+                    id \"ep\";";
+        let epd = Epd::parse(line);
+        assert_eq!(epd.best_moves(), [Move::parse("e5d6")]);
+        assert!(epd.position.is_capture(Move::parse("e5d6")));
+        assert_eq!(format!("{}", epd), line);
+    }
+
+    #[test]
+    fn no_operations() {
+        let epd = Epd::parse("8/8/8/8/8/8/8/k6K b - -");
+        assert_eq!(epd.operations, vec![]);
+        assert_eq!(epd.best_moves(), []);
+        assert_eq!(epd.id(), None);
+    }
+
+    #[test]
+    fn errors() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w Q -";
+        let error = |ops: &str, e: EpdError, at: &str| {
+            let line = format!("{} {}", fen, ops);
+            assert_eq!(parse_epd(line.as_bytes()), Error(P(Custom(e), at.as_bytes())));
+        };
+        error("bm Qg6;", EpdError::San(SanError::Illegal), "bm Qg6;");
+        error("id \"open;", EpdError::UnterminatedString, "\"open;");
+        error("id \"a\" \"b\";", EpdError::Operand, "id \"a\" \"b\";");
+        error("ce x;", EpdError::Operand, "ce x;");
+        error("1 2", EpdError::Opcode, "1 2");
+    }
+}
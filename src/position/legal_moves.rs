@@ -0,0 +1,215 @@
+use super::root::*;
+use super::castling::*;
+use castle;
+use color::Color;
+use kind::*;
+use mask::*;
+use moves::Move;
+use side::*;
+use sided_mask::*;
+use square::Square;
+
+static PROMOTIONS: [Kind; 4] = [QUEEN, ROOK, BISHOP, KNIGHT];
+
+impl Position {
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.active == Color::White {
+            self.legal_moves_of::<White>()
+        } else {
+            self.legal_moves_of::<Black>()
+        }
+    }
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        if self.active == Color::White {
+            self.pseudo_legal_moves_of::<White>()
+        } else {
+            self.pseudo_legal_moves_of::<Black>()
+        }
+    }
+    pub fn is_check(&self) -> bool {
+        if self.active == Color::White {
+            self.is_check_to::<White>()
+        } else {
+            self.is_check_to::<Black>()
+        }
+    }
+    pub fn is_check_to<S: Side>(&self) -> bool {
+        self.rules.variant().is_check(&self.board, S::KING.color())
+    }
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.captured_square(mv).is_some()
+    }
+    // Where the piece the move takes stands, differs from
+    // the target square for en passant
+    pub fn captured_square(&self, mv: Move) -> Option<Mask> {
+        if mv.is_drop() || mv.castle != castle::NONE {
+            return None;
+        }
+        let to = mv.to.mask();
+        if self.board.occupation().intersects(to) {
+            return Some(to);
+        }
+        let from = mv.from.mask();
+        match self.en_passant {
+            Some(file) if self.board.pawns_of(self.active).intersects(from) &&
+                          to == Mask::from_file_rank(file, self.active.en_passant_rank()) => {
+                Some(Mask::from_file_rank(file, mv.from.rank()))
+            }
+            _ => None,
+        }
+    }
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves.into_iter().map(|mv| self.make_move(mv).perft(depth - 1)).sum()
+    }
+
+    pub fn legal_moves_of<S: Side>(&self) -> Vec<Move> {
+        let variant = self.rules.variant();
+        if variant.outcome(self).is_some() {
+            return Vec::new();
+        }
+        let color = S::KING.color();
+        let moves = self.pseudo_legal_moves_of::<S>()
+            .into_iter()
+            .filter(|&mv| variant.is_legal(&self.make_move(mv), color))
+            .collect();
+        variant.filter_legal(self, moves)
+    }
+
+    pub fn pseudo_legal_moves_of<S: Side>(&self) -> Vec<Move> {
+        let mut result = Vec::with_capacity(64);
+        let own = self.board.occupation_gen::<S>().mask();
+        let occupation = self.board.occupation();
+        let targets = !own;
+
+        self.pseudo_legal_pawn_moves_to::<S>(&mut result);
+        for from in self.board.knights::<S>().mask().single_bits() {
+            push_moves(&mut result, from, from.knight_attacks() & targets);
+        }
+        let diagonal = self.board.bishops::<S>().mask() | self.board.queens::<S>().mask();
+        for from in diagonal.single_bits() {
+            push_moves(&mut result, from, from.bishop_attacks(occupation) & targets);
+        }
+        let straight = self.board.rooks::<S>().mask() | self.board.queens::<S>().mask();
+        for from in straight.single_bits() {
+            push_moves(&mut result, from, from.rook_attacks(occupation) & targets);
+        }
+        for from in self.board.kings::<S>().mask().single_bits() {
+            push_moves(&mut result, from, from.king_attacks() & targets);
+        }
+        self.castling_moves::<S>(&mut result);
+        self.rules.variant().pseudo_legal_moves(self, S::KING.color(), &mut result);
+        result
+    }
+
+    fn pseudo_legal_pawn_moves_to<S: Side>(&self, result: &mut Vec<Move>) {
+        let empty_squares = !self.board.occupation();
+        let captures = self.board.occupation_gen::<S::Opposite>().mask() |
+                       self.en_passant_take_square_mask::<S>();
+        for from in self.board.pawns::<S>().mask().single_bits() {
+            let pawn = S::Mask::wrap(from);
+            let single = pawn.advance().filter(empty_squares);
+            let double = single.advance().filter(empty_squares & S::DOUBLE_PUSH_RANK_MASK);
+            let attacks = pawn.attack().filter(captures);
+            let all = single.mask() | double.mask() | attacks.mask();
+            for to in all.single_bits() {
+                if to.intersects(S::PROMOTION_RANK_MASK) {
+                    for &kind in &PROMOTIONS {
+                        result.push(Move::promote(square(from), square(to), kind));
+                    }
+                } else {
+                    result.push(Move::new(square(from), square(to)));
+                }
+            }
+        }
+    }
+
+    fn castling_moves<S: Side>(&self, result: &mut Vec<Move>) {
+        let color = S::KING.color();
+        let occupation = self.board.occupation();
+        for &wing in &[castle::K, castle::Q] {
+            if !self.available.contains(wing & color.castle()) {
+                continue;
+            }
+            let c = castling(color, wing);
+            if !self.board.kings::<S>().mask().contains(c.king_from) ||
+               !self.board.rooks::<S>().mask().contains(c.rook_from) ||
+               occupation.intersects(c.empty) {
+                continue;
+            }
+            if c.safe.single_bits().any(|m| self.board.is_attacked_by::<S::Opposite>(m)) {
+                continue;
+            }
+            let mut mv = Move::new(::square::UNDEFINED_SQUARE, ::square::UNDEFINED_SQUARE);
+            mv.castle = wing;
+            result.push(mv);
+        }
+    }
+}
+
+fn square(m: Mask) -> Square {
+    Square::from_bits(m.index_of_least_significant_bit() as u8)
+}
+
+fn push_moves(result: &mut Vec<Move>, from: Mask, targets: Mask) {
+    let from = square(from);
+    for to in targets.single_bits() {
+        result.push(Move::new(from, square(to)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn start_position() {
+        let p = Position::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(p.legal_moves().len(), 20);
+        assert_eq!(p.perft(3), 8902);
+    }
+
+    #[test]
+    fn kiwipete() {
+        let p = Position::parse(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(p.perft(1), 48);
+        assert_eq!(p.perft(2), 2039);
+        assert_eq!(p.perft(3), 97862);
+    }
+
+    #[test]
+    fn en_passant_and_pins() {
+        let p = Position::parse("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(p.perft(4), 43238);
+    }
+
+    #[test]
+    fn promotions() {
+        let p = Position::parse(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+        assert_eq!(p.perft(3), 9467);
+    }
+
+    #[test]
+    fn castling_through_check() {
+        let p = Position::parse("3r1r1k/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let castles = p.legal_moves().into_iter().filter(|m| m.castle != castle::NONE).count();
+        assert_eq!(castles, 0);
+        let p = Position::parse("4k3/8/8/8/8/8/1r6/R3K2R w KQ - 0 1");
+        let castles = p.legal_moves().into_iter().filter(|m| m.castle != castle::NONE).count();
+        assert_eq!(castles, 2);
+    }
+
+    #[test]
+    fn is_check() {
+        assert!(Position::parse("4k3/8/8/8/8/8/8/4RK2 b - - 0 1").is_check());
+        assert!(!Position::parse("4k3/8/8/8/8/8/8/4RK2 w - - 0 1").is_check());
+    }
+}
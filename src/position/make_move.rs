@@ -0,0 +1,95 @@
+use super::root::*;
+use super::castling::*;
+use castle;
+use kind::*;
+use mask::Mask;
+use moves::Move;
+
+impl Position {
+    // Does not check the move, feed it with legal ones only.
+    pub fn make_move(&self, mv: Move) -> Position {
+        let mut next = *self;
+        let color = self.active;
+        next.en_passant = None;
+        if mv.is_drop() {
+            let piece = mv.drop.of(color);
+            next.pockets.take(piece);
+            next.board.set_piece(mv.to.mask(), piece);
+        } else if mv.castle != castle::NONE {
+            let c = castling(color, mv.castle);
+            next.board.remove_piece(c.king_from | c.rook_from);
+            next.board.set_piece(c.king_to, KING.of(color));
+            next.board.set_piece(c.rook_to, ROOK.of(color));
+            next.available.remove(color.castle());
+        } else {
+            let from = mv.from.mask();
+            let to = mv.to.mask();
+            let piece = self.board.get_piece(from);
+            if piece.kind() == PAWN {
+                if let Some(file) = self.en_passant {
+                    if to == Mask::from_file_rank(file, color.en_passant_rank()) {
+                        next.board.remove_piece(Mask::from_file_rank(file, mv.from.rank()));
+                    }
+                }
+                let distance = (mv.from.rank().bits() as i8 - mv.to.rank().bits() as i8).abs();
+                if distance == 2 {
+                    next.en_passant = Some(mv.from.file());
+                }
+            }
+            next.board.remove_piece(from | to);
+            let placed = if mv.promote != UNKNOWN {
+                mv.promote.of(color)
+            } else {
+                piece
+            };
+            next.board.set_piece(to, placed);
+            next.available.remove(spoiled_castling(from) | spoiled_castling(to));
+        }
+        next.active = color.invert();
+        self.rules.variant().after_move(self, mv, &mut next);
+        next
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn after(fen: &str, mv: &str) -> String {
+        format!("{}", Position::parse(fen).make_move(Move::parse(mv)))
+    }
+
+    #[test]
+    fn double_push() {
+        assert_eq!(after("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2e4"),
+                   "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3");
+    }
+
+    #[test]
+    fn en_passant() {
+        assert_eq!(after("4k3/8/8/3pP3/8/8/8/4K3 w - d 0 1", "e5d6"),
+                   "4k3/8/3P4/8/8/8/8/4K3 b - -");
+        assert_eq!(after("4k3/8/8/8/3pP3/8/8/4K3 b - e 0 1", "d4e3"),
+                   "4k3/8/8/8/8/4p3/8/4K3 w - -");
+    }
+
+    #[test]
+    fn castle() {
+        assert_eq!(after("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "O-O"),
+                   "r3k2r/8/8/8/8/8/8/R4RK1 b kq -");
+        assert_eq!(after("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "O-O-O"),
+                   "2kr3r/8/8/8/8/8/8/R3K2R w KQ -");
+    }
+
+    #[test]
+    fn rook_capture_spoils_castling() {
+        assert_eq!(after("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "a1a8"),
+                   "R3k2r/8/8/8/8/8/8/4K2R b Kk -");
+    }
+
+    #[test]
+    fn promotion() {
+        assert_eq!(after("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8=N"),
+                   "1N2k3/8/8/8/8/8/8/4K3 b - -");
+    }
+}
@@ -1,8 +1,23 @@
-mod validate_position;
+pub mod validate_position;
 mod validate_move;
 mod pawn_moves;
 mod king_moves;
+mod legal_moves;
+mod make_move;
+pub mod castling;
+mod solve_mate;
+mod unmoves;
+mod outcome;
+mod san;
+mod uci;
+pub mod epd;
+mod bytes;
 mod wrappers;
 mod root;
 
-pub use self::root::Position;
\ No newline at end of file
+pub use self::root::{Position, PositionError, parse_position};
+pub use self::outcome::Outcome;
+pub use self::unmoves::Unmove;
+pub use self::san::SanError;
+pub use self::epd::Epd;
+pub use self::bytes::{CodecError, ENCODED_SIZE};
@@ -0,0 +1,37 @@
+use super::root::*;
+use color::Color;
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq, Hash)]
+pub enum Outcome {
+    Win(Color),
+    Draw,
+}
+
+impl Position {
+    // None while the game goes on
+    pub fn outcome(&self) -> Option<Outcome> {
+        let variant = self.rules.variant();
+        if let Some(outcome) = variant.outcome(self) {
+            return Some(outcome);
+        }
+        if self.legal_moves().is_empty() {
+            Some(variant.no_moves(self))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn standard() {
+        assert_eq!(Position::parse("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").outcome(),
+                   Some(Outcome::Win(Color::White)));
+        assert_eq!(Position::parse("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").outcome(),
+                   Some(Outcome::Draw));
+        assert_eq!(Position::parse("k7/2Q5/1K6/8/8/8/8/8 w - - 0 1").outcome(), None);
+    }
+}
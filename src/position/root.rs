@@ -4,20 +4,59 @@ use castle::Castle;
 use color::Color;
 use file::File;
 use bit_board::fen;
+use mask::Mask;
+use mask::masks::EMPTY;
+use nom::IResult;
+use nom::IResult::*;
+use nom::Err::Position as P;
+use nom::ErrorKind::Custom as C;
+use pocket::{self, Pockets};
+use rules::Rules;
+use variant::{Parsed, Variant};
 use super::wrappers::*;
 
-#[derive(Eq, Debug, Copy, Clone, PartialEq)]
+#[derive(Eq, Debug, Copy, Clone, PartialEq, Hash)]
 pub struct Position {
     pub board: BitBoard,
     pub active: Color,
     pub available: Castle,
     pub en_passant: Option<File>,
+    pub rules: Rules,
+    // crazyhouse only
+    pub pockets: Pockets,
+    // pieces that were pawns once, they go back to pawns when captured
+    pub promoted: Mask,
+    // three-check: checks each side has yet to give, indexed by color
+    pub remaining_checks: [u8; 2],
 }
 
 impl Position {
+    pub fn new(board: BitBoard, active: Color) -> Self {
+        Position {
+            board: board,
+            active: active,
+            available: castle::NONE,
+            en_passant: None,
+            rules: Rules::Standard,
+            pockets: Pockets::new(),
+            promoted: EMPTY,
+            remaining_checks: [3, 3],
+        }
+    }
+    pub fn start(rules: Rules) -> Self {
+        Position { rules: rules, ..Position::parse(rules.start_fen()) }
+    }
+    pub fn remaining_checks(&self, color: Color) -> u8 {
+        self.remaining_checks[color as usize]
+    }
     pub fn parse(input: &str) -> Self {
         parse_position(input.as_bytes()).unwrap().1
     }
+    // FEN for other programs: `Display` plus the clocks the position
+    // does not keep, the halfmove clock and the move number
+    pub fn fen(&self, halfmove: u32, fullmove: u32) -> String {
+        format!("{} {} {}", self, halfmove, fullmove)
+    }
 }
 
 
@@ -25,33 +64,67 @@ use std::fmt::{Display, Formatter, Result};
 
 impl Display for Position {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        let r = self.en_passant.map_or('-', |x| x.char());
-        write!(f, "{} {} {} {}", self.board, self.active, self.available, r)
+        let variant = self.rules.variant();
+        write!(f, "{}", self.board)?;
+        variant.fmt_board_suffix(self, f)?;
+        write!(f, " {} {} ", self.active, self.available)?;
+        match self.en_passant {
+            Some(file) => write!(f, "{}{}", file, self.active.en_passant_rank())?,
+            None => write!(f, "-")?,
+        }
+        variant.fmt_extension(self, f)
     }
 }
 
 #[derive(Eq, Copy, Clone, Debug, PartialEq)]
 pub enum PositionError {
     Board(fen::ParsingError),
+    Pockets(pocket::ParsingError),
     Active(u32),
     Available(castle::ParsingError),
     EnPassant(u32),
+    Checks,
     Whitespace,
 }
 
-// "8/8/8/8/8/8/8/8 w KQkq - 0 1"
-named!(pub parse_position<&[u8], Position, PositionError>,
-chain!(
-    squares: parse_bit_board ~ ws ~
-    side: parse_color ~ ws ~
-    castle: parse_castle ~ ws ~
-    file: parse_file_or_dash,
-    || Position {
-            board: squares,
-            active: side,
-            available: castle,
-            en_passant: file
-    }));
+// "8/8/8/8/8/8/8/8 w KQkq - 0 1", the variants read their own bits:
+// crazyhouse: "8/8/8/8/8/8/8/8[Qn] w KQkq - 0 1",
+// three-check, checks given so far: "8/8/8/8/8/8/8/8 w KQkq - +1+0 0 1"
+pub fn parse_position(input: &[u8]) -> IResult<&[u8], Position, PositionError> {
+    let (rest, board) = try_parse!(input, parse_bit_board);
+    let mut p = Position::new(board, Color::White);
+    let rest = match extension(rest, &mut p, |v, i, p| v.parse_board_suffix(i, p)) {
+        Ok(rest) => rest,
+        Err(e) => return Error(P(C(e), rest)),
+    };
+    let (rest, _) = try_parse!(rest, ws);
+    let (rest, active) = try_parse!(rest, parse_color);
+    let (rest, _) = try_parse!(rest, ws);
+    let (rest, available) = try_parse!(rest, parse_castle);
+    let (rest, _) = try_parse!(rest, ws);
+    let (rest, en_passant) = try_parse!(rest, call!(parse_en_passant, active));
+    p.active = active;
+    p.available = available;
+    p.en_passant = en_passant;
+    match extension(rest, &mut p, |v, i, p| v.parse_extension(i, p)) {
+        Ok(rest) => Done(rest, p),
+        Err(e) => Error(P(C(e), rest)),
+    }
+}
+
+// Offers the text to the built-in variants
+fn extension<'a, F>(input: &'a [u8], p: &mut Position, parse: F)
+                    -> ::std::result::Result<&'a [u8], PositionError>
+    where F: Fn(&Variant, &'a [u8], &mut Position) -> Parsed<'a>
+{
+    for &rules in Rules::built_in() {
+        if let Some(rest) = parse(rules.variant(), input, p)? {
+            p.rules = rules;
+            return Ok(rest);
+        }
+    }
+    Ok(input)
+}
 
 #[cfg(test)]
 mod test {
@@ -62,8 +135,35 @@ mod test {
     #[test]
     fn correct_fen() {
         assert_eq!(format!("{}",
-                           Position::parse("8/8/8/8/8/8/8/8 w KQkq e 0 1")),
-        "8/8/8/8/8/8/8/8 w KQkq e");
+                           Position::parse("8/8/8/8/8/8/8/8 w KQkq e6 0 1")),
+        "8/8/8/8/8/8/8/8 w KQkq e6");
+    }
+
+    #[test]
+    fn fen() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(Position::parse(fen).fen(0, 1), fen);
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - +2+0 7 40";
+        assert_eq!(Position::parse(fen).fen(7, 40), fen);
+    }
+
+    #[test]
+    fn en_passant_square() {
+        let p = Position::parse("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        assert_eq!(p.en_passant, Some(::file::E));
+        // a file alone is read too
+        assert_eq!(Position::parse("4k3/8/8/8/3pP3/8/8/4K3 b - e 0 1"), p);
+        assert_eq!(format!("{}", Position::parse("4k3/8/8/8/3pP3/8/8/4K3 b - e3 +1+0 0 1")),
+                   "4k3/8/8/8/3pP3/8/8/4K3 b - e3 +1+0");
+        let error = |fen: &str| match parse_position(fen.as_bytes()) {
+            Error(::nom::Err::Position(::nom::ErrorKind::Custom(e), at)) => Some((e, at.len())),
+            _ => None,
+        };
+        // the rank of the other side
+        assert_eq!(error("4k3/8/8/8/3pP3/8/8/4K3 b - e6 0 1"),
+                   Some((PositionError::EnPassant(0), "6 0 1".len())));
+        assert_eq!(error("4k3/8/8/8/3pP3/8/8/4K3 b - x 0 1"),
+                   Some((PositionError::EnPassant(0), "x 0 1".len())));
     }
 
     #[test]
@@ -73,6 +173,37 @@ mod test {
         "8/8/8/8/8/8/8/8 w - -");
     }
 
+    #[test]
+    fn crazyhouse_fen() {
+        let p = Position::parse("8/8/8/8/8/8/8/8[Qnn] w KQkq - 0 1");
+        assert_eq!(p.rules, Rules::Crazyhouse);
+        assert_eq!(format!("{}", p), "8/8/8/8/8/8/8/8[Qnn] w KQkq -");
+        assert_eq!(format!("{}", Position::parse("8/8/8/8/8/8/8/8[] w - - 0 1")),
+                   "8/8/8/8/8/8/8/8[] w - -");
+        assert_eq!(Position::parse("8/8/8/8/8/8/8/8 w - - 0 1").rules, Rules::Standard);
+    }
+
+    #[test]
+    fn three_check_fen() {
+        let p = Position::parse("8/8/8/8/8/8/8/8 b KQkq - +2+0 0 1");
+        assert_eq!(p.rules, Rules::ThreeCheck);
+        assert_eq!(p.remaining_checks(Color::White), 1);
+        assert_eq!(p.remaining_checks(Color::Black), 3);
+        assert_eq!(format!("{}", p), "8/8/8/8/8/8/8/8 b KQkq - +2+0");
+        assert_eq!(Position::parse("8/8/8/8/8/8/8/8 b KQkq -").rules, Rules::Standard);
+    }
+
+    #[test]
+    fn broken_extensions() {
+        let error = |fen: &str| match parse_position(fen.as_bytes()) {
+            Error(::nom::Err::Position(::nom::ErrorKind::Custom(e), _)) => Some(e),
+            _ => None,
+        };
+        assert_eq!(error("8/8/8/8/8/8/8/8[Qx] w - - 0 1"),
+                   Some(PositionError::Pockets(pocket::ParsingError::UnrecognizedToken)));
+        assert_eq!(error("8/8/8/8/8/8/8/8 w - - +4+0 0 1"), Some(PositionError::Checks));
+    }
+
     #[test]
     fn en_passant_file_mask_dash() {
         let p = Position::parse("8/8/8/8/8/8/8/8 w - - 0 1");
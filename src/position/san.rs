@@ -0,0 +1,252 @@
+use super::root::*;
+use super::outcome::Outcome;
+use castle;
+use file::File;
+use kind::*;
+use moves::Move;
+use rank::Rank;
+use square::Square;
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum SanError {
+    Syntax,
+    // no legal move fits
+    Illegal,
+    // more than one legal move fits
+    Ambiguous,
+}
+
+impl Position {
+    // Standard algebraic notation of a legal move: "Nbd7", "exd6",
+    // "e8=Q+", "O-O-O#", "N@f3". The check and mate suffixes come
+    // from the rules the position is played by.
+    pub fn san(&self, mv: Move) -> String {
+        let mut result = self.san_without_suffix(mv);
+        let next = self.make_move(mv);
+        if next.is_check() {
+            result.push(match next.outcome() {
+                Some(Outcome::Win(color)) if color == self.active => '#',
+                _ => '+',
+            });
+        }
+        result
+    }
+
+    fn san_without_suffix(&self, mv: Move) -> String {
+        if mv.castle != castle::NONE || mv.is_drop() {
+            return format!("{}", mv);
+        }
+        let kind = self.board.get_piece(mv.from.mask()).kind();
+        let capture = self.is_capture(mv);
+        let mut result = String::new();
+        if kind == PAWN {
+            if capture {
+                result.push(mv.from.file().char());
+            }
+        } else {
+            result.push(kind.char());
+            let rivals = self.legal_moves()
+                .into_iter()
+                .filter(|m| {
+                    m.to == mv.to && m.from != mv.from && m.castle == castle::NONE &&
+                    !m.is_drop() && self.board.get_piece(m.from.mask()).kind() == kind
+                })
+                .collect::<Vec<_>>();
+            if !rivals.is_empty() {
+                let same_file = rivals.iter().any(|m| m.from.file() == mv.from.file());
+                let same_rank = rivals.iter().any(|m| m.from.rank() == mv.from.rank());
+                if !same_file {
+                    result.push(mv.from.file().char());
+                } else if !same_rank {
+                    result.push(mv.from.rank().char());
+                } else {
+                    result.push_str(&format!("{}", mv.from));
+                }
+            }
+        }
+        if capture {
+            result.push('x');
+        }
+        result.push_str(&format!("{}", mv.to));
+        if mv.promote != UNKNOWN {
+            result.push('=');
+            result.push(mv.promote.char());
+        }
+        result
+    }
+
+    // Resolves a SAN move against the legal moves. Lenient about
+    // suffixes ("+", "#", "!?"), a missing "=" and "0-0".
+    pub fn parse_san(&self, input: &str) -> Result<Move, SanError> {
+        let san = input.trim_right_matches(|c| "+#!?".contains(c));
+        let legal = self.legal_moves();
+        if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+            let wing = if san.len() == 3 { castle::K } else { castle::Q };
+            return legal.into_iter()
+                .find(|m| m.castle == wing)
+                .ok_or(SanError::Illegal);
+        }
+        let pattern = parse_pattern(san.as_bytes()).ok_or(SanError::Syntax)?;
+        let mut matches = legal.into_iter().filter(|&m| pattern.matches(self, m));
+        match (matches.next(), matches.next()) {
+            (Some(m), None) => Ok(m),
+            (None, _) => Err(SanError::Illegal),
+            _ => Err(SanError::Ambiguous),
+        }
+    }
+}
+
+// What a SAN move tells about the move it stands for
+struct Pattern {
+    kind: Kind,
+    drop: bool,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Square,
+    promote: Kind,
+}
+
+impl Pattern {
+    fn matches(&self, p: &Position, mv: Move) -> bool {
+        if mv.castle != castle::NONE || mv.to != self.to || mv.promote != self.promote {
+            return false;
+        }
+        if self.drop || mv.is_drop() {
+            return mv.drop == self.kind && self.drop;
+        }
+        p.board.get_piece(mv.from.mask()).kind() == self.kind &&
+        self.from_file.map_or(true, |f| f == mv.from.file()) &&
+        self.from_rank.map_or(true, |r| r == mv.from.rank())
+    }
+}
+
+fn is_file(c: u8) -> bool {
+    b'a' <= c && c <= b'h'
+}
+
+fn is_rank(c: u8) -> bool {
+    b'1' <= c && c <= b'8'
+}
+
+fn is_kind(c: u8) -> bool {
+    b"PNBRQK".contains(&c)
+}
+
+// [piece][from file][from rank][x|@]to[[=]promotion], the capture
+// sign is optional as it is redundant
+fn parse_pattern(input: &[u8]) -> Option<Pattern> {
+    let mut s = input;
+    let mut kind = PAWN;
+    if !s.is_empty() && is_kind(s[0]) {
+        kind = Kind::parse(s[0] as char);
+        s = &s[1..];
+    }
+    let mut promote = UNKNOWN;
+    if s.len() >= 2 && is_kind(s[s.len() - 1]) {
+        promote = Kind::parse(s[s.len() - 1] as char);
+        s = &s[..s.len() - 1];
+        if s[s.len() - 1] == b'=' {
+            s = &s[..s.len() - 1];
+        }
+    }
+    if s.len() < 2 || !is_file(s[s.len() - 2]) || !is_rank(s[s.len() - 1]) {
+        return None;
+    }
+    let to = Square::from(File::parse(s[s.len() - 2] as char),
+                          Rank::parse(s[s.len() - 1] as char));
+    s = &s[..s.len() - 2];
+    let mut drop = false;
+    if !s.is_empty() && (s[s.len() - 1] == b'x' || s[s.len() - 1] == b'@') {
+        drop = s[s.len() - 1] == b'@';
+        s = &s[..s.len() - 1];
+    }
+    let mut from_file = None;
+    let mut from_rank = None;
+    if !s.is_empty() && is_file(s[0]) {
+        from_file = Some(File::parse(s[0] as char));
+        s = &s[1..];
+    }
+    if !s.is_empty() && is_rank(s[0]) {
+        from_rank = Some(Rank::parse(s[0] as char));
+        s = &s[1..];
+    }
+    if !s.is_empty() || drop && (from_file.is_some() || from_rank.is_some()) {
+        return None;
+    }
+    Some(Pattern {
+        kind: kind,
+        drop: drop,
+        from_file: from_file,
+        from_rank: from_rank,
+        to: to,
+        promote: promote,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn san(fen: &str, mv: &str) -> String {
+        Position::parse(fen).san(Move::parse(mv))
+    }
+
+    fn parse(fen: &str, san: &str) -> Result<Move, SanError> {
+        Position::parse(fen).parse_san(san)
+    }
+
+    #[test]
+    fn display() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "e2e4"), "e4");
+        assert_eq!(san(start, "g1f3"), "Nf3");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d 0 1", "e5d6"), "exd6");
+        assert_eq!(san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8=Q"), "b8=Q+");
+        assert_eq!(san("r3k3/8/8/8/8/8/5PPP/6K1 b q - 0 1", "O-O-O"), "O-O-O");
+        assert_eq!(san("r3k3/8/8/8/8/8/5PPP/6K1 b - - 0 1", "a8a1"), "Ra1#");
+    }
+
+    #[test]
+    fn disambiguation() {
+        let fen = "4k3/8/8/8/8/8/K7/R6R w - - 0 1";
+        assert_eq!(san(fen, "a1d1"), "Rad1");
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, "a1a3"), "R1a3");
+        let fen = "k7/8/8/8/8/2Q1Q3/8/K3Q3 w - - 0 1";
+        assert_eq!(san(fen, "e3d2"), "Qe3d2");
+        assert_eq!(parse(fen, "Qe3d2"), Ok(Move::parse("e3d2")));
+        assert_eq!(parse(fen, "Qd2"), Err(SanError::Ambiguous));
+    }
+
+    #[test]
+    fn round_trip() {
+        let p = Position::parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        for mv in p.legal_moves() {
+            assert_eq!(p.parse_san(&p.san(mv)), Ok(mv), "{}", p.san(mv));
+        }
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(parse(start, "Nf3!?"), Ok(Move::parse("g1f3")));
+        assert_eq!(parse(start, "Ng1f3"), Ok(Move::parse("g1f3")));
+        assert_eq!(parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8Q"),
+                   Ok(Move::parse("b7b8=Q")));
+        assert_eq!(parse(start, "e5"), Err(SanError::Illegal));
+        assert_eq!(parse(start, "Zz9"), Err(SanError::Syntax));
+    }
+
+    #[test]
+    fn variants() {
+        let p = Position::parse("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1");
+        assert_eq!(p.san(Move::drop(KNIGHT, Square::parse("f6"))), "N@f6+");
+        assert_eq!(p.parse_san("N@f6"), Ok(Move::drop(KNIGHT, Square::parse("f6"))));
+        // the third check wins
+        let p = Position::parse("4k3/8/8/8/8/8/8/R3K3 w - - +2+0 0 1");
+        assert_eq!(p.san(Move::parse("a1a8")), "Ra8#");
+        let p = Position { rules: Rules::Standard, ..p };
+        assert_eq!(p.san(Move::parse("a1a8")), "Ra8+");
+    }
+}
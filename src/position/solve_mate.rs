@@ -0,0 +1,177 @@
+use std::cell::Cell;
+use std::time::Instant;
+use super::root::*;
+use moves::Move;
+
+// Stops the search once the deadline has passed. Running out of time
+// only ever hides mates, it never makes one up.
+struct Clock {
+    deadline: Option<Instant>,
+    expired: Cell<bool>,
+}
+
+impl Clock {
+    fn new(deadline: Option<Instant>) -> Self {
+        Clock {
+            deadline: deadline,
+            expired: Cell::new(false),
+        }
+    }
+    fn expired(&self) -> bool {
+        if !self.expired.get() && self.deadline.map_or(false, |d| Instant::now() >= d) {
+            self.expired.set(true);
+        }
+        self.expired.get()
+    }
+}
+
+// Exhaustive, no heuristics: every defense is tried,
+// so the answers are exact (and slow for deep problems).
+impl Position {
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.legal_moves().is_empty()
+    }
+
+    // The shortest forced mate within `n` moves: the key move,
+    // then the most stubborn defense and the answer to it, etc.
+    pub fn solve_mate(&self, n: u32) -> Option<Vec<Move>> {
+        self.solve_mate_until(n, None)
+    }
+
+    // `solve_mate` giving up at the deadline. Once the mate is proven
+    // the line is filled in regardless, which costs about as much again.
+    pub fn solve_mate_until(&self, n: u32, deadline: Option<Instant>) -> Option<Vec<Move>> {
+        let clock = Clock::new(deadline);
+        let moves = (1..n + 1)
+            .take_while(|_| !clock.expired())
+            .filter_map(|k| self.mate_in(k, &clock).map(|mv| (k, mv)))
+            .next();
+        let clock = Clock::new(None);
+        moves.map(|(k, mv)| {
+            let mut line = vec![mv];
+            let mut position = self.make_move(mv);
+            let mut left = k - 1;
+            while left > 0 && !position.is_checkmate() {
+                // the defense that postpones the mate the most
+                let (defense, attack, length) = position.legal_moves()
+                    .into_iter()
+                    .map(|d| {
+                        let next = position.make_move(d);
+                        let (j, a) = (1..left + 1)
+                            .filter_map(|j| next.mate_in(j, &clock).map(|a| (j, a)))
+                            .next()
+                            .expect("the mate was proven");
+                        (d, a, j)
+                    })
+                    .max_by_key(|&(_, _, j)| j)
+                    .expect("checkmate and stalemate are handled above");
+                line.push(defense);
+                line.push(attack);
+                position = position.make_move(defense).make_move(attack);
+                left = length - 1;
+            }
+            line
+        })
+    }
+
+    // "Cooks": every first move that mates within `n` moves
+    pub fn solve_mate_cooks(&self, n: u32) -> Vec<Move> {
+        if n == 0 {
+            return Vec::new();
+        }
+        let clock = Clock::new(None);
+        self.legal_moves()
+            .into_iter()
+            .filter(|&mv| self.make_move(mv).is_lost_within(n - 1, &clock))
+            .collect()
+    }
+
+    fn mate_in(&self, n: u32, clock: &Clock) -> Option<Move> {
+        if clock.expired() {
+            return None;
+        }
+        self.legal_moves()
+            .into_iter()
+            .find(|&mv| {
+                let next = self.make_move(mv);
+                // only checks can mate on the last move
+                (n > 1 || next.is_check()) && next.is_lost_within(n - 1, clock)
+            })
+    }
+
+    // side to move can not avoid the mate within `n` moves
+    fn is_lost_within(&self, n: u32, clock: &Clock) -> bool {
+        let defenses = self.legal_moves();
+        if defenses.is_empty() {
+            return self.is_check();
+        }
+        n > 0 &&
+        defenses.into_iter().all(|d| self.make_move(d).mate_in(n, clock).is_some())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    fn solve(fen: &str, n: u32) -> Option<String> {
+        Position::parse(fen).solve_mate(n).map(|line| line.iter().join(" "))
+    }
+
+    #[test]
+    fn mate_in_one() {
+        assert_eq!(solve("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1),
+                   Some("a1-a8".to_string()));
+    }
+
+    #[test]
+    fn mate_in_two() {
+        let fen = "k7/8/2K5/8/8/8/8/1R6 w - - 0 1";
+        assert_eq!(solve(fen, 1), None);
+        let p = Position::parse(fen);
+        let line = p.solve_mate(3).unwrap();
+        assert_eq!(line.len(), 3);
+        let end = line.iter().fold(p, |p, &mv| p.make_move(mv));
+        assert!(end.is_checkmate());
+    }
+
+    #[test]
+    fn no_mate() {
+        assert_eq!(solve("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 1),
+                   None);
+        assert_eq!(solve("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1", 0), None);
+    }
+
+    #[test]
+    fn cooks() {
+        let p = Position::parse("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1");
+        assert_eq!(p.solve_mate_cooks(1).iter().join(" "), "a1-a8 b1-b8");
+        assert_eq!(p.solve_mate(1).unwrap(), vec![Move::parse("a1a8")]);
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        assert!(Position::parse("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").is_checkmate());
+        assert!(Position::parse("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").is_stalemate());
+        assert!(!Position::parse("k7/2Q5/1K6/8/8/8/8/8 w - - 0 1").is_stalemate());
+    }
+
+    #[test]
+    fn deadline() {
+        use std::time::Duration;
+        let p = Position::parse("k7/8/2K5/8/8/8/8/1R6 w - - 0 1");
+        let past = Instant::now();
+        assert_eq!(p.solve_mate_until(3, Some(past)), None);
+        let future = Instant::now() + Duration::from_secs(60);
+        assert_eq!(p.solve_mate_until(3, Some(future)), p.solve_mate(3));
+        // a deep search on a busy board gives up in time
+        let start = Instant::now();
+        let p = Position::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(p.solve_mate_until(6, Some(start + Duration::from_millis(50))), None);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
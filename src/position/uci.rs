@@ -0,0 +1,118 @@
+use super::root::*;
+use super::castling::castling;
+use castle;
+use kind::UNKNOWN;
+use mask::Mask;
+use moves::{Move, parse_move};
+use nom::IResult;
+use square::Square;
+
+impl Position {
+    // UCI long algebraic notation of a legal move: "e2e4", "e7e8q",
+    // castling as the king's move "e1g1", drops as "N@f3"
+    pub fn uci(&self, mv: Move) -> String {
+        if mv.castle != castle::NONE {
+            let c = castling(self.active, mv.castle);
+            return format!("{}{}", square(c.king_from), square(c.king_to));
+        }
+        if mv.is_drop() {
+            return format!("{}", mv);
+        }
+        let mut result = format!("{}{}", mv.from, mv.to);
+        if mv.promote != UNKNOWN {
+            result.push(mv.promote.char().to_ascii_lowercase());
+        }
+        result
+    }
+
+    // The legal move written in UCI notation, or in the crate's own
+    // coordinates: "e2-e4", "e7-e8=Q", "O-O"
+    pub fn parse_uci(&self, input: &str) -> Option<Move> {
+        let mut input = input.trim().to_string();
+        if input.is_empty() {
+            return None;
+        }
+        // "e7e8q" -> "e7e8=Q"
+        if input.len() == 5 && !input.contains('-') && !input.contains('@') {
+            let promote = input.pop().map_or(' ', |c| c.to_ascii_uppercase());
+            input.push('=');
+            input.push(promote);
+        }
+        // the square parsers choke on an empty tail, hence the space
+        input.push(' ');
+        let candidate = match parse_move(input.as_bytes()) {
+            IResult::Done(rest, mv) if rest == b" " => mv,
+            _ => return None,
+        };
+        self.legal_moves().into_iter().find(|&mv| {
+            if mv == candidate {
+                return true;
+            }
+            if mv.castle == castle::NONE || candidate.castle != castle::NONE ||
+               candidate.is_drop() || candidate.promote != UNKNOWN {
+                return false;
+            }
+            let c = castling(self.active, mv.castle);
+            c.king_from == candidate.from.mask() && c.king_to == candidate.to.mask()
+        })
+    }
+}
+
+fn square(mask: Mask) -> Square {
+    Square::from_bits(mask.index_of_least_significant_bit() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kind::*;
+    use square::*;
+
+    fn round_trips(fen: &str) {
+        let p = Position::parse(fen);
+        for mv in p.legal_moves() {
+            assert_eq!(p.parse_uci(&p.uci(mv)), Some(mv), "{}", p.uci(mv));
+        }
+    }
+
+    #[test]
+    fn notation() {
+        let p = Position::parse("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+        assert_eq!(p.uci(Move::new(A1, A8)), "a1a8");
+        assert_eq!(p.uci(Move::promote(B7, A8, QUEEN)), "b7a8q");
+        assert_eq!(p.uci(Move::parse("O-O")), "e1g1");
+        assert_eq!(p.uci(Move::parse("O-O-O")), "e1c1");
+        let p = Position::parse("r3k3/8/8/8/8/8/8/4K3[n] b q - 0 1");
+        assert_eq!(p.uci(Move::parse("O-O-O")), "e8c8");
+        assert_eq!(p.uci(Move::drop(KNIGHT, F3)), "N@f3");
+    }
+
+    #[test]
+    fn parsing() {
+        round_trips("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        round_trips("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+        round_trips("r3k3/8/8/8/8/8/8/4K3[n] b q - 0 1");
+        let p = Position::parse("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+        assert_eq!(p.parse_uci("e1-g1"), Some(Move::parse("O-O")));
+        assert_eq!(p.parse_uci("O-O-O"), Some(Move::parse("O-O-O")));
+        assert_eq!(p.parse_uci("b7-b8=N"), Some(Move::promote(B7, B8, KNIGHT)));
+        assert_eq!(p.parse_uci(" b7b8Q "), Some(Move::promote(B7, B8, QUEEN)));
+    }
+
+    #[test]
+    fn rejects() {
+        let p = Position::parse("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+        // illegal, unfinished or trailing garbage
+        assert_eq!(p.parse_uci("a1a2a"), None);
+        assert_eq!(p.parse_uci("b7b8"), None);
+        assert_eq!(p.parse_uci("e1e3"), None);
+        assert_eq!(p.parse_uci("e1g1q"), None);
+        assert_eq!(p.parse_uci("e1g1 x"), None);
+        assert_eq!(p.parse_uci("Nf3"), None);
+        assert_eq!(p.parse_uci(""), None);
+        assert_eq!(p.parse_uci("e"), None);
+        assert_eq!(p.parse_uci("e2"), None);
+        assert_eq!(p.parse_uci("e2e"), None);
+        assert_eq!(p.parse_uci("N@"), None);
+    }
+}
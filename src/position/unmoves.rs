@@ -0,0 +1,262 @@
+use super::root::*;
+use super::castling::*;
+use castle;
+use castle::Castle;
+use color::Color;
+use kind::*;
+use mask::Mask;
+use mask::masks::*;
+use moves::Move;
+use square::Square;
+use variant::is_check_to;
+
+// A move taken back: the move as it was played,
+// plus what the position before it had and this one lost.
+#[derive(Eq, Copy, Clone, Debug, PartialEq, Hash)]
+pub struct Unmove {
+    pub mv: Move,
+    // the kind of the piece the move took, UNKNOWN for none
+    pub uncapture: Kind,
+    // the move took en passant
+    pub en_passant: bool,
+    // castling rights the move spoiled
+    pub castling: Castle,
+}
+
+// nothing, or any of the pieces
+static UNCAPTURES: [Kind; 6] = [UNKNOWN, PAWN, KNIGHT, BISHOP, ROOK, QUEEN];
+
+impl Position {
+    // The position before the unmove, feed it with pseudo-legal unmoves only.
+    pub fn unmake_move(&self, u: Unmove) -> Position {
+        let mut prev = *self;
+        let mover = self.active.invert();
+        prev.active = mover;
+        prev.available |= u.castling;
+        prev.en_passant = None;
+        let mv = u.mv;
+        if mv.castle != castle::NONE {
+            let c = castling(mover, mv.castle);
+            prev.board.remove_piece(c.king_to | c.rook_to);
+            prev.board.set_piece(c.king_from, KING.of(mover));
+            prev.board.set_piece(c.rook_from, ROOK.of(mover));
+            return prev;
+        }
+        let (from, to) = (mv.from.mask(), mv.to.mask());
+        let piece = if mv.promote != UNKNOWN {
+            PAWN.of(mover)
+        } else {
+            self.board.get_piece(to)
+        };
+        prev.board.remove_piece(to);
+        prev.board.set_piece(from, piece);
+        if u.en_passant {
+            let taken = Mask::from_file_rank(mv.to.file(), mv.from.rank());
+            prev.board.set_piece(taken, PAWN.of(self.active));
+            prev.en_passant = Some(mv.to.file());
+        } else if u.uncapture != UNKNOWN {
+            prev.board.set_piece(to, u.uncapture.of(self.active));
+        }
+        prev
+    }
+
+    // Every unmove leading to a valid position, in which the side to
+    // move now was not left in check, and whose move gives this one
+    // back. Standard rules only; the position before is assumed to
+    // have had no en passant square unless the unmove is one.
+    pub fn pseudo_legal_unmoves(&self) -> Vec<Unmove> {
+        let mover = self.active.invert();
+        let occupation = self.board.occupation();
+        let empty = !occupation;
+        let (back, forward): (fn(Mask) -> Mask, fn(Mask) -> Mask) = if mover == Color::White {
+            (Mask::shift_south, Mask::shift_north)
+        } else {
+            (Mask::shift_north, Mask::shift_south)
+        };
+        let (promotion_rank, double_push_rank, en_passant_rank) = if mover == Color::White {
+            (_8, _4, _6)
+        } else {
+            (_1, _5, _3)
+        };
+        let mut result = Vec::new();
+        for to in self.board.occupation_of(mover).single_bits() {
+            let kind = self.board.get_piece(to).kind();
+            if kind == PAWN {
+                let single = back(to) & empty;
+                self.push_unmoves(&mut result, single, to, UNKNOWN, &UNCAPTURES[..1]);
+                if to.intersects(double_push_rank) {
+                    let double = back(single) & empty;
+                    self.push_unmoves(&mut result, double, to, UNKNOWN, &UNCAPTURES[..1]);
+                }
+                let diagonal = (back(to).shift_east() | back(to).shift_west()) & empty;
+                self.push_unmoves(&mut result, diagonal, to, UNKNOWN, &UNCAPTURES[1..]);
+                // the pawn taken en passant stood behind, and came from in front
+                if to.intersects(en_passant_rank) && back(to).intersects(empty) &&
+                   forward(to).intersects(empty) {
+                    for from in diagonal.single_bits() {
+                        self.push_unmove(&mut result, Unmove {
+                            mv: Move::new(square(from), square(to)),
+                            uncapture: PAWN,
+                            en_passant: true,
+                            castling: castle::NONE,
+                        });
+                    }
+                }
+                continue;
+            }
+            let origins = match kind {
+                KNIGHT => to.knight_attacks(),
+                BISHOP => to.bishop_attacks(occupation),
+                ROOK => to.rook_attacks(occupation),
+                QUEEN => to.queen_attacks(occupation),
+                _ => to.king_attacks(),
+            } & empty;
+            self.push_unmoves(&mut result, origins, to, UNKNOWN, &UNCAPTURES);
+            if kind != KING && to.intersects(promotion_rank) {
+                self.push_unmoves(&mut result, back(to) & empty, to, kind, &UNCAPTURES[..1]);
+                let diagonal = (back(to).shift_east() | back(to).shift_west()) & empty;
+                self.push_unmoves(&mut result, diagonal, to, kind, &UNCAPTURES[1..]);
+            }
+        }
+        for &wing in &[castle::K, castle::Q] {
+            let c = castling(mover, wing);
+            let vacated = c.king_from | c.rook_from | (c.empty & !(c.king_to | c.rook_to));
+            if self.board.get_piece(c.king_to) == KING.of(mover) &&
+               self.board.get_piece(c.rook_to) == ROOK.of(mover) &&
+               !occupation.intersects(vacated) {
+                let mut mv = Move::new(::square::UNDEFINED_SQUARE, ::square::UNDEFINED_SQUARE);
+                mv.castle = wing;
+                self.push_castling_options(&mut result, Unmove {
+                    mv: mv,
+                    uncapture: UNKNOWN,
+                    en_passant: false,
+                    castling: wing & mover.castle(),
+                }, mover.castle());
+            }
+        }
+        result
+    }
+
+    fn push_unmoves(&self, result: &mut Vec<Unmove>, origins: Mask, to: Mask,
+                    promote: Kind, uncaptures: &[Kind]) {
+        for from in origins.single_bits() {
+            for &uncapture in uncaptures {
+                let spoiled = spoiled_castling(from) | spoiled_castling(to);
+                self.push_castling_options(result, Unmove {
+                    mv: Move::promote(square(from), square(to), promote),
+                    uncapture: uncapture,
+                    en_passant: false,
+                    castling: castle::NONE,
+                }, spoiled);
+            }
+        }
+    }
+
+    // Tries the unmove with every subset of the rights it could have spoiled
+    fn push_castling_options(&self, result: &mut Vec<Unmove>, u: Unmove, spoiled: Castle) {
+        let optional = spoiled - self.available - u.castling;
+        for bits in 0..castle::ALL.bits() + 1 {
+            let option = Castle::from_bits_truncate(bits);
+            if optional.contains(option) {
+                self.push_unmove(result, Unmove { castling: u.castling | option, ..u });
+            }
+        }
+    }
+
+    fn push_unmove(&self, result: &mut Vec<Unmove>, u: Unmove) {
+        let prev = self.unmake_move(u);
+        if prev.validate().is_empty() && !is_check_to(&prev.board, self.active) &&
+           prev.make_move(u.mv) == *self {
+            result.push(u);
+        }
+    }
+}
+
+fn square(m: Mask) -> Square {
+    Square::from_bits(m.index_of_least_significant_bit() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unmoves(fen: &str) -> Vec<Unmove> {
+        let p = Position::parse(fen);
+        let result = p.pseudo_legal_unmoves();
+        for &u in &result {
+            assert_eq!(p.unmake_move(u).make_move(u.mv), p, "{:?}", u);
+        }
+        result
+    }
+
+    fn before(fen: &str, u: Unmove) -> String {
+        format!("{}", Position::parse(fen).unmake_move(u))
+    }
+
+    fn quiet(mv: &str) -> Unmove {
+        Unmove {
+            mv: Move::parse(mv),
+            uncapture: UNKNOWN,
+            en_passant: false,
+            castling: castle::NONE,
+        }
+    }
+
+    #[test]
+    fn double_push() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e 0 1";
+        assert_eq!(unmoves(fen), vec![quiet("e2e4")]);
+    }
+
+    #[test]
+    fn uncaptures() {
+        // three squares, nothing or any of four pieces taken
+        let result = unmoves("k7/8/8/8/8/8/8/K7 b - - 0 1");
+        assert_eq!(result.len(), 3 * 5);
+        let u = Unmove { uncapture: ROOK, ..quiet("b2a1") };
+        assert!(result.contains(&u));
+        assert_eq!(before("k7/8/8/8/8/8/8/K7 b - - 0 1", u), "k7/8/8/8/8/8/1K6/r7 w - -");
+    }
+
+    #[test]
+    fn unpromotions() {
+        let fen = "Q6k/8/8/8/8/8/8/K7 b - - 0 1";
+        let result = unmoves(fen);
+        assert!(result.contains(&quiet("a7a8=Q")));
+        let u = Unmove { uncapture: ROOK, ..quiet("b7a8=Q") };
+        assert!(result.contains(&u));
+        assert_eq!(before(fen, u), "r6k/1P6/8/8/8/8/8/K7 w - -");
+        assert!(!result.contains(&Unmove { uncapture: PAWN, ..quiet("a7a8") }));
+    }
+
+    #[test]
+    fn un_en_passant() {
+        let fen = "k7/8/3P4/8/8/8/8/K7 b - - 0 1";
+        let u = Unmove { uncapture: PAWN, en_passant: true, ..quiet("e5d6") };
+        assert!(unmoves(fen).contains(&u));
+        assert_eq!(before(fen, u), "k7/8/8/3pP3/8/8/8/K7 w - d6");
+    }
+
+    #[test]
+    fn castling_rights() {
+        let fen = "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 0 1";
+        let result = unmoves(fen);
+        let o_o = Unmove { castling: castle::WK, ..quiet("O-O") };
+        assert!(result.contains(&o_o));
+        assert!(result.contains(&Unmove { castling: castle::W, ..o_o }));
+        assert_eq!(before(fen, o_o), "r3k2r/8/8/8/8/8/8/R3K2R w Kkq -");
+        // the rook could have left the corner
+        let fen = "4k3/8/8/8/8/8/8/1R2K3 b - - 0 1";
+        let result = unmoves(fen);
+        assert!(result.contains(&quiet("a1b1")));
+        assert!(result.contains(&Unmove { castling: castle::WQ, ..quiet("a1b1") }));
+    }
+
+    #[test]
+    fn side_to_move_was_not_in_check() {
+        // the rook can not come from b8, it would have given check
+        let result = unmoves("k7/8/8/8/8/8/8/KR6 b - - 0 1");
+        assert!(!result.contains(&quiet("b8b1")));
+        assert!(result.contains(&quiet("b7b1")));
+    }
+}
@@ -9,6 +9,9 @@ use kind::*;
 
 impl Position {
     pub fn is_pseudo_legal(&self, mv: Move) -> bool {
+        if mv.is_drop() {
+            return self.pseudo_legal_moves().contains(&mv);
+        }
         // Source square must not be vacant.
         let from = mv.from.mask();
         let piece = self.board.get_piece(from);
@@ -27,14 +27,21 @@ bitflags! {
         const CASTLING_WITHOUT_ROOK_H8 = 1 << 11;
         const CASTLING_WITHOUT_KING_E1 = 1 << 12;
         const CASTLING_WITHOUT_KING_E8 = 1 << 13;
+        const WHITE_PAWNS_ON_FIRST_RANK = 1 << 14;
+        const BLACK_PAWNS_ON_FIRST_RANK = 1 << 15;
         const WTF= 1 << 20;
     }
 }
 
 impl Position {
-    fn validate(&self) -> Assessment {
+    pub fn validate(&self) -> Assessment {
+        self.rules.variant().validate(self)
+    }
+    // what every variant starts with
+    pub fn validate_standard(&self) -> Assessment {
         self.white_pawns_on_promotion_rank() |
             self.black_pawns_on_promotion_rank() |
+            self.pawns_on_first_rank() |
             self.has_more_than_one_white_king() |
             self.has_no_white_king() |
             self.has_more_than_one_black_king() |
@@ -42,6 +49,16 @@ impl Position {
             self.validate_en_passant() |
             self.validate_castling()
     }
+    fn pawns_on_first_rank(&self) -> Assessment {
+        let mut result = VALID;
+        if self.board.pawns::<White>().0 & _1 != EMPTY {
+            result |= WHITE_PAWNS_ON_FIRST_RANK;
+        }
+        if self.board.pawns::<Black>().0 & _8 != EMPTY {
+            result |= BLACK_PAWNS_ON_FIRST_RANK;
+        }
+        result
+    }
     fn white_pawns_on_promotion_rank(&self) -> Assessment {
         if self.board.pawns::<White>().0 & _8 != EMPTY {
             WHITE_PAWNS_ON_PROMOTION_RANK
@@ -270,7 +287,12 @@ mod tests {
             "r3k2r/8/8/8/8/8/8/4K2R w KQkq - 0 1",
             CASTLING_WITHOUT_ROOK_A1);
     }
-
+    #[test]
+    fn pawns_on_first_rank() {
+        assert_assessment(
+            "p7/8/8/8/k7/8/K7/P7 w - - 0 1",
+            WHITE_PAWNS_ON_FIRST_RANK | BLACK_PAWNS_ON_FIRST_RANK);
+    }
 
     fn assert_assessment(fen: &str, expected: Assessment) {
         assert_eq!(Position::parse(fen).validate(), expected);
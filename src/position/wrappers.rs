@@ -36,19 +36,31 @@ pub fn parse_castle(input: &[u8]) -> R<Castle, PositionError> {
     })
 }
 
-use ::file::parse_file as blah;
-named!(parse_file_or_dash_inner(&[u8]) -> Option<File>,
-    alt!(
-        value!(None, char!('-')) |
-        map!(blah, |x| Some(x))));
+// "-" or the square taken on, e.g. "e3". The rank has to be the one
+// the side to move takes on; a file alone, the way this crate once
+// wrote it, is still read.
+pub fn parse_en_passant(input: &[u8], active: Color) -> R<Option<File>, PositionError> {
+    use nom::IResult::*;
+    use nom::Needed::Unknown;
 
-pub fn parse_file_or_dash(input: &[u8]) -> R<Option<File>, PositionError> {
-    parse_file_or_dash_inner(input).map_err(|err| {
-        match err {
-            P(C(pe), x) => P(C(EnPassant(pe)), x),
-            _ => panic!("parse_file"),
+    match input.first() {
+        None => return Incomplete(Unknown),
+        Some(&b'-') => return Done(&input[1..], None),
+        _ => {}
+    }
+    let (rest, file) = match ::file::parse_file(&input[..1]) {
+        Done(_, file) => (&input[1..], file),
+        _ => return Error(P(C(EnPassant(0)), input)),
+    };
+    match rest.first() {
+        Some(&c) if c.is_ascii_digit() => {
+            if c as char != active.en_passant_rank().char() {
+                return Error(P(C(EnPassant(0)), rest));
+            }
+            Done(&rest[1..], Some(file))
         }
-    })
+        _ => Done(rest, Some(file)),
+    }
 }
 
 named!(ws_inner(&[u8]) -> char, char!(' '));
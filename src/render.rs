@@ -0,0 +1,167 @@
+use bit_board::BitBoard;
+use mask::Mask;
+use mask::masks::EMPTY;
+use piece::*;
+use square::Square;
+
+// How to draw a board
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub struct Style {
+    // figurines and box drawing instead of letters and `+-|`
+    pub unicode: bool,
+    // black at the bottom
+    pub flipped: bool,
+    pub labels: bool,
+    // squares to mark, e.g. legal targets or attacked squares
+    pub highlight: Mask,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            unicode: false,
+            flipped: false,
+            labels: true,
+            highlight: EMPTY,
+        }
+    }
+}
+
+// in piece order: PNBRQK, then pnbrqk
+static FIGURINES: [char; PIECES_COUNT] = ['♙', '♘', '♗', '♖', '♕', '♔',
+                                         '♟', '♞', '♝', '♜', '♛', '♚'];
+
+// A framed 8x8 diagram, one line per rank. Every square takes three
+// columns: " p " normally, "[p]" when highlighted, and an empty square
+// shows a dot, or a star when highlighted.
+//
+//   +------------------------+
+// 8 | r  n  b  q  k  b  n  r |
+//   ...
+//   +------------------------+
+//     a  b  c  d  e  f  g  h
+pub fn render(board: &BitBoard, style: &Style) -> String {
+    let (top, bottom, side) = if style.unicode {
+        (('┌', '─', '┐'), ('└', '─', '┘'), '│')
+    } else {
+        (('+', '-', '+'), ('+', '-', '+'), '|')
+    };
+    let margin = if style.labels { "  " } else { "" };
+    let border = |(left, middle, right): (char, char, char)| {
+        let mut line = String::from(margin);
+        line.push(left);
+        line.extend((0..24).map(|_| middle));
+        line.push(right);
+        line.push('\n');
+        line
+    };
+    let order = |i: u8| if style.flipped { 7 - i } else { i };
+
+    let mut result = border(top);
+    for row in 0..8 {
+        let rank = order(row);
+        if style.labels {
+            result.push_str(&format!("{} ", 8 - rank));
+        }
+        result.push(side);
+        for column in 0..8 {
+            let square = Square::from_bits(rank * 8 + order(column)).mask();
+            result.push_str(&cell(board, square, style));
+        }
+        result.push(side);
+        result.push('\n');
+    }
+    result.push_str(&border(bottom));
+    if style.labels {
+        result.push_str(margin);
+        result.push(' ');
+        for column in 0..8 {
+            result.push_str(&format!(" {} ", (b'a' + order(column)) as char));
+        }
+        result.push('\n');
+    }
+    result
+}
+
+fn cell(board: &BitBoard, square: Mask, style: &Style) -> String {
+    let highlighted = style.highlight.intersects(square);
+    if !board.occupation().intersects(square) {
+        let mark = match (style.unicode, highlighted) {
+            (false, false) => '.',
+            (false, true) => '*',
+            (true, false) => '·',
+            (true, true) => '•',
+        };
+        return format!(" {} ", mark);
+    }
+    let piece = board.get_piece(square);
+    let symbol = if style.unicode {
+        FIGURINES[piece.bits() as usize]
+    } else {
+        piece.char()
+    };
+    if highlighted {
+        format!("[{}]", symbol)
+    } else {
+        format!(" {} ", symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mask::masks::*;
+    use position::Position;
+
+    fn board(fen: &str) -> BitBoard {
+        Position::parse(fen).board
+    }
+
+    #[test]
+    fn ascii() {
+        let b = board("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e 0 1");
+        assert_eq!(render(&b, &Style::default()),
+                   "  +------------------------+\n\
+                    8 | r  n  b  q  k  b  n  r |\n\
+                    7 | p  p  p  p  p  p  p  p |\n\
+                    6 | .  .  .  .  .  .  .  . |\n\
+                    5 | .  .  .  .  .  .  .  . |\n\
+                    4 | .  .  .  .  P  .  .  . |\n\
+                    3 | .  .  .  .  .  .  .  . |\n\
+                    2 | P  P  P  P  .  P  P  P |\n\
+                    1 | R  N  B  Q  K  B  N  R |\n\
+                    \x20 +------------------------+\n\
+                    \x20   a  b  c  d  e  f  g  h \n");
+    }
+
+    #[test]
+    fn unicode_flipped_highlighted() {
+        let b = board("8/8/8/8/8/8/8/k6K w - - 0 1");
+        let style = Style {
+            unicode: true,
+            flipped: true,
+            highlight: A1 | B1 | G2,
+            ..Style::default()
+        };
+        assert_eq!(render(&b, &style),
+                   "  ┌────────────────────────┐\n\
+                    1 │ ♔  ·  ·  ·  ·  ·  • [♚]│\n\
+                    2 │ ·  •  ·  ·  ·  ·  ·  · │\n\
+                    3 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    4 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    5 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    6 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    7 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    8 │ ·  ·  ·  ·  ·  ·  ·  · │\n\
+                    \x20 └────────────────────────┘\n\
+                    \x20   h  g  f  e  d  c  b  a \n");
+    }
+
+    #[test]
+    fn no_labels() {
+        let style = Style { labels: false, ..Style::default() };
+        let lines = render(&BitBoard::new(), &style).lines().count();
+        assert_eq!(lines, 10);
+        assert!(render(&BitBoard::new(), &style).starts_with("+---"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::cmp;
+use color::Color;
+use kind::*;
+use mask::Mask;
+use mask::masks::*;
+use position::Position;
+use position::validate_position::Assessment;
+use square::Square;
+use variant::is_check_to;
+
+// Why a position that passes `Position::validate` still
+// can not arise in a game. Standard rules only.
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum Reason {
+    // `Position::validate` objects already
+    Invalid(Assessment),
+    TooManyPieces(Color),
+    // pawns off their files took more than the opponent has lost
+    PawnCaptures {
+        color: Color,
+        needed: u32,
+        possible: u32,
+    },
+    // pieces beyond the initial set had to be promoted from pawns
+    TooManyPromotedPieces {
+        color: Color,
+        promoted: u32,
+        missing_pawns: u32,
+    },
+    // the home pawns never let the bishop out, and it can not be promoted
+    BishopBehindPawns {
+        color: Color,
+        square: Square,
+    },
+    OpponentInCheck,
+    // no move could have given it
+    ImpossibleCheck,
+}
+
+pub fn retro_check(p: &Position) -> Vec<Reason> {
+    let assessment = p.validate();
+    if !assessment.is_empty() {
+        return vec![Reason::Invalid(assessment)];
+    }
+    let mut result = Vec::new();
+    for &color in &[Color::White, Color::Black] {
+        check_material(p, color, &mut result);
+    }
+    if is_check_to(&p.board, p.active.invert()) {
+        result.push(Reason::OpponentInCheck);
+    } else if p.is_check() && p.pseudo_legal_unmoves().is_empty() {
+        result.push(Reason::ImpossibleCheck);
+    }
+    result
+}
+
+fn check_material(p: &Position, color: Color, result: &mut Vec<Reason>) {
+    let own = p.board.occupation_of(color);
+    if own.count() > 16 {
+        result.push(Reason::TooManyPieces(color));
+    }
+    let pawns = p.board.pawns_of(color);
+    let needed = pawn_captures(pawns);
+    let possible = 16 - cmp::min(16, p.board.occupation_of(color.invert()).count());
+    if needed > possible {
+        result.push(Reason::PawnCaptures {
+            color: color,
+            needed: needed,
+            possible: possible,
+        });
+    }
+
+    let missing_pawns = 8 - cmp::min(8, pawns.count());
+    let count = |kind: Kind| p.board.pieces(kind.of(color)).count();
+    let excess = |kind: Kind, initial: u32| count(kind).saturating_sub(initial);
+    let bishops = p.board.pieces(BISHOP.of(color));
+    let light = bishops.single_bits().filter(|&b| square(b).color() == Color::White).count();
+    let dark = bishops.count() as usize - light;
+    let promoted = excess(QUEEN, 1) + excess(ROOK, 2) + excess(KNIGHT, 2) +
+                   (light as u32).saturating_sub(1) + (dark as u32).saturating_sub(1);
+    if promoted > missing_pawns {
+        result.push(Reason::TooManyPromotedPieces {
+            color: color,
+            promoted: promoted,
+            missing_pawns: missing_pawns,
+        });
+        return;
+    }
+    // a trapped bishop is either at home or gone, the other
+    // bishops of its square color have to be promoted
+    let mut trapped_promoted = promoted;
+    let mut behind = Vec::new();
+    for &(home, guards) in &trapped_bishops(color) {
+        if p.board.pawns_of(color) & guards != guards {
+            continue;
+        }
+        let home_color = square(home).color();
+        let same = bishops.single_bits()
+            .filter(|&b| square(b).color() == home_color)
+            .collect::<Vec<_>>();
+        let away = same.iter().filter(|&&b| b != home).count() as u32;
+        let before = (same.len() as u32).saturating_sub(1);
+        trapped_promoted += away - cmp::min(away, before);
+        behind.extend(same.into_iter().filter(|&b| b != home));
+    }
+    if trapped_promoted > missing_pawns {
+        for b in behind {
+            result.push(Reason::BishopBehindPawns {
+                color: color,
+                square: square(b),
+            });
+        }
+    }
+}
+
+// The bishop's home and the two pawns that lock it in
+fn trapped_bishops(color: Color) -> [(Mask, Mask); 2] {
+    if color == Color::White {
+        [(C1, B2 | D2), (F1, E2 | G2)]
+    } else {
+        [(C8, B7 | D7), (F8, E7 | G7)]
+    }
+}
+
+// Captures needed to get the pawns to their files: each pawn comes
+// from its own initial file, a capture moves it one file aside.
+fn pawn_captures(pawns: Mask) -> u32 {
+    let mut files = pawns.single_bits()
+        .map(|p| square(p).file().bits() as i32)
+        .collect::<Vec<_>>();
+    files.sort();
+    if files.len() > 8 {
+        return 0;
+    }
+    // best[j]: the cost of the pawns so far, with the last one
+    // coming from an initial file below j
+    let mut best = [0u32; 9];
+    for (i, &file) in files.iter().enumerate() {
+        let mut next = [u32::max_value(); 9];
+        for origin in i..8 {
+            let cost = best[origin].saturating_add((file - origin as i32).abs() as u32);
+            next[origin + 1] = cmp::min(next[origin], cost);
+        }
+        for j in (i + 1)..9 {
+            next[j] = cmp::min(next[j], next[j - 1]);
+        }
+        best = next;
+    }
+    best[8]
+}
+
+fn square(m: Mask) -> Square {
+    Square::from_bits(m.index_of_least_significant_bit() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(fen: &str) -> Vec<Reason> {
+        retro_check(&Position::parse(fen))
+    }
+
+    #[test]
+    fn start_position() {
+        assert_eq!(check("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+                   vec![]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(check("8/8/8/8/8/8/8/K7 w - - 0 1"),
+                   vec![Reason::Invalid(::position::validate_position::HAS_NO_BLACK_KING)]);
+    }
+
+    #[test]
+    fn pawn_structure() {
+        assert_eq!(pawn_captures(A2 | B2 | C2), 0);
+        assert_eq!(pawn_captures(A2 | A3), 1);
+        assert_eq!(pawn_captures(A2 | A3 | A4), 3);
+        assert_eq!(pawn_captures(H2 | H3 | H4), 3);
+        // tripled a-pawns with the whole black army still there
+        assert_eq!(check("rnbqkbnr/pppppppp/8/8/P7/P7/P7/RNBQKBNR w - - 0 1"),
+                   vec![Reason::PawnCaptures {
+                            color: Color::White,
+                            needed: 3,
+                            possible: 0,
+                        }]);
+        // three black pieces are gone, enough for it
+        assert_eq!(check("1nbqkbn1/1ppppppp/8/8/P7/P7/P7/RNBQKBNR w - - 0 1"), vec![]);
+    }
+
+    #[test]
+    fn promoted_pieces() {
+        assert_eq!(check("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNQ w Qkq - 0 1"),
+                   vec![Reason::TooManyPromotedPieces {
+                            color: Color::White,
+                            promoted: 1,
+                            missing_pawns: 0,
+                        }]);
+        assert_eq!(check("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNQ w Qkq - 0 1"), vec![]);
+    }
+
+    #[test]
+    fn bishop_behind_pawns() {
+        // the c1 bishop is out while b2 and d2 never moved
+        let fen = "rnbqkbnr/pppppppp/8/8/5B2/4P3/PPPP1PPP/RN1QKBNR w KQkq - 0 1";
+        assert_eq!(check(fen),
+                   vec![Reason::BishopBehindPawns {
+                            color: Color::White,
+                            square: Square::parse("f4"),
+                        }]);
+        // with a pawn missing it could be a promoted one
+        let fen = "rnbqkbnr/pppppppp/8/8/5B2/4P3/PPPP1P1P/RN1QKBNR w KQkq - 0 1";
+        assert_eq!(check(fen), vec![]);
+    }
+
+    #[test]
+    fn checks() {
+        assert_eq!(check("k7/8/8/8/8/8/8/R6K w - - 0 1"), vec![Reason::OpponentInCheck]);
+        // two rooks can not give a double check
+        assert_eq!(check("R6k/8/8/8/8/8/8/K6R b - - 0 1"), vec![Reason::ImpossibleCheck]);
+        assert_eq!(check("R6k/8/8/8/8/8/8/K5R1 b - - 0 1"), vec![]);
+        // the castling right pins the rook to h1, nothing could discover it
+        assert_eq!(check("7k/8/8/8/8/8/8/4K2R b K - 0 1"), vec![Reason::ImpossibleCheck]);
+        assert_eq!(check("7k/8/8/8/8/8/8/4K2R b - - 0 1"), vec![]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use variant::*;
+
+// Which rules a position is played by
+#[derive(Copy, Clone)]
+pub enum Rules {
+    Standard,
+    // captured pieces go to the capturer's pocket and can be dropped back
+    Crazyhouse,
+    // captures explode everything but pawns around the target square
+    Atomic,
+    // captures are compulsory, losing all the pieces wins
+    Antichess,
+    // giving the third check wins
+    ThreeCheck,
+    // bringing the king to the center wins
+    KingOfTheHill,
+    // 36 white pawns against the usual black army
+    Horde,
+    // no checks, bringing the king to the eighth rank wins
+    RacingKings,
+    // house rules, told apart by name
+    Custom(&'static Variant),
+}
+
+static STANDARD: Standard = Standard;
+static CRAZYHOUSE: Crazyhouse = Crazyhouse;
+static ATOMIC: Atomic = Atomic;
+static ANTICHESS: Antichess = Antichess;
+static THREE_CHECK: ThreeCheck = ThreeCheck;
+static KING_OF_THE_HILL: KingOfTheHill = KingOfTheHill;
+static HORDE: Horde = Horde;
+static RACING_KINGS: RacingKings = RacingKings;
+
+static BUILT_IN: [Rules; 8] = [
+    Rules::Standard,
+    Rules::Crazyhouse,
+    Rules::Atomic,
+    Rules::Antichess,
+    Rules::ThreeCheck,
+    Rules::KingOfTheHill,
+    Rules::Horde,
+    Rules::RacingKings,
+];
+
+impl Rules {
+    pub fn variant(self) -> &'static Variant {
+        match self {
+            Rules::Standard => &STANDARD,
+            Rules::Crazyhouse => &CRAZYHOUSE,
+            Rules::Atomic => &ATOMIC,
+            Rules::Antichess => &ANTICHESS,
+            Rules::ThreeCheck => &THREE_CHECK,
+            Rules::KingOfTheHill => &KING_OF_THE_HILL,
+            Rules::Horde => &HORDE,
+            Rules::RacingKings => &RACING_KINGS,
+            Rules::Custom(variant) => variant,
+        }
+    }
+    // as in the PGN `[Variant "..."]` tag
+    pub fn name(self) -> &'static str {
+        self.variant().name()
+    }
+    // case-insensitive, a missing tag means standard chess
+    pub fn from_name(name: &str) -> Option<Rules> {
+        let name = name.to_lowercase();
+        match name.as_str() {
+            "chess" | "normal" | "" => return Some(Rules::Standard),
+            "losing" | "giveaway" => return Some(Rules::Antichess),
+            "threecheck" | "3-check" => return Some(Rules::ThreeCheck),
+            "kingofthehill" | "koth" => return Some(Rules::KingOfTheHill),
+            "racingkings" => return Some(Rules::RacingKings),
+            _ => {}
+        }
+        BUILT_IN.iter().cloned().find(|r| r.name().to_lowercase() == name)
+    }
+    pub fn start_fen(self) -> &'static str {
+        self.variant().start_fen()
+    }
+    // all but the custom ones
+    pub fn built_in() -> &'static [Rules] {
+        &BUILT_IN
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::Standard
+    }
+}
+
+impl PartialEq for Rules {
+    fn eq(&self, other: &Rules) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for Rules {}
+
+impl Hash for Rules {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name().hash(state)
+    }
+}
+
+impl fmt::Debug for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rules::Custom(variant) => write!(f, "Custom({:?})", variant.name()),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for &rules in &BUILT_IN {
+            assert_eq!(Rules::from_name(rules.name()), Some(rules));
+        }
+        assert_eq!(Rules::KingOfTheHill.name(), "King of the Hill");
+        assert_eq!(Rules::from_name("three-check"), Some(Rules::ThreeCheck));
+        assert_eq!(Rules::from_name("KOTH"), Some(Rules::KingOfTheHill));
+        assert_eq!(Rules::from_name("Suicide chess"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use moves::Move;
+use position::Position;
+use rand::Rng;
+use std::time::Instant;
+
+// The move choice of the front-ends not on `Search`: the shortest
+// forced mate within the depth and the deadline, and a random legal
+// move when there is none.
+
+// moves to look ahead for a mate when not told otherwise
+pub const MATE_DEPTH: u32 = 3;
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Choice {
+    pub mv: Move,
+    // the mate line starting with `mv`: the attacker's moves and the
+    // most stubborn defenses in turn
+    pub mate: Option<Vec<Move>>,
+}
+
+impl Choice {
+    // in moves
+    pub fn mate_length(&self) -> Option<u32> {
+        self.mate.as_ref().map(|line| (line.len() as u32 + 1) / 2)
+    }
+}
+
+// None when the game is over
+pub fn choose_move<R: Rng>(position: &Position,
+                           depth: u32,
+                           deadline: Option<Instant>,
+                           rng: &mut R)
+                           -> Option<Choice> {
+    if position.outcome().is_some() {
+        return None;
+    }
+    if let Some(line) = position.solve_mate_until(depth, deadline) {
+        return Some(Choice {
+            mv: line[0],
+            mate: Some(line),
+        });
+    }
+    rng.choose(&position.legal_moves()).map(|&mv| {
+        Choice {
+            mv: mv,
+            mate: None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use position::parse_position;
+    use rand::{SeedableRng, XorShiftRng};
+    use rules::Rules;
+
+    fn position(fen: &str) -> Position {
+        parse_position(fen.as_bytes()).unwrap().1
+    }
+
+    #[test]
+    fn mate() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let p = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let choice = choose_move(&p, MATE_DEPTH, None, &mut rng).unwrap();
+        assert_eq!(p.uci(choice.mv), "a1a8");
+        assert_eq!(choice.mate_length(), Some(1));
+        // too shallow
+        let choice = choose_move(&p, 0, None, &mut rng).unwrap();
+        assert_eq!(choice.mate, None);
+    }
+
+    #[test]
+    fn random_move() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let p = Position::start(Rules::Standard);
+        for _ in 0..10 {
+            let choice = choose_move(&p, MATE_DEPTH, None, &mut rng).unwrap();
+            assert_eq!(choice.mate, None);
+            assert!(p.legal_moves().contains(&choice.mv));
+        }
+    }
+
+    #[test]
+    fn deadline() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let p = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let choice = choose_move(&p, MATE_DEPTH, Some(Instant::now()), &mut rng).unwrap();
+        assert_eq!(choice.mate, None);
+        assert!(p.legal_moves().contains(&choice.mv));
+    }
+
+    #[test]
+    fn game_over() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let p = position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(choose_move(&p, MATE_DEPTH, None, &mut rng), None);
+    }
+}
@@ -0,0 +1,85 @@
+use color::Color;
+use kind::*;
+use position::Position;
+
+// Material and a little about where it stands, in centipawns, for the
+// side to move. It knows standard chess only, the variants get played
+// by the same numbers.
+
+// by kind: pawn, knight, bishop, rook, queen, king
+pub static VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+// per step closer to the center
+static CENTRALITY: [i32; 6] = [0, 10, 5, 2, 2, 0];
+// per rank a pawn has advanced
+const ADVANCE: i32 = 6;
+// the kings come out once the queens are off
+const KING_CENTRALITY: i32 = 10;
+
+pub fn evaluate(position: &Position) -> i32 {
+    let score = side(position, Color::White) - side(position, Color::Black);
+    if position.active == Color::White {
+        score
+    } else {
+        -score
+    }
+}
+
+fn side(position: &Position, color: Color) -> i32 {
+    let board = &position.board;
+    let endgame = (board.queens_of(Color::White) | board.queens_of(Color::Black)).bits() == 0;
+    let mut result = 0;
+    for kind in ALL_KINDS {
+        let value = VALUES[kind.bits() as usize];
+        let centrality = if kind == KING && endgame {
+            KING_CENTRALITY
+        } else {
+            CENTRALITY[kind.bits() as usize]
+        };
+        for square in board.pieces(kind.of(color)).single_bit_indices() {
+            result += value + centrality * center(square);
+            if kind == PAWN {
+                result += ADVANCE * advance(square, color);
+            }
+        }
+    }
+    result
+}
+
+// 0 on the edge .. 3 in the middle four squares
+fn center(square: u32) -> i32 {
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    3 - ((2 * file - 7).abs() / 2).max((2 * rank - 7).abs() / 2)
+}
+
+// ranks ahead of the starting one, square 0 is a8
+fn advance(square: u32, color: Color) -> i32 {
+    let row = (square / 8) as i32;
+    if color == Color::White {
+        6 - row
+    } else {
+        row - 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symmetric() {
+        let p = Position::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(evaluate(&p), 0);
+        let p = Position::parse("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        let q = Position::parse("3qk3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(evaluate(&p), evaluate(&q));
+        assert!(evaluate(&p) > 800);
+    }
+
+    #[test]
+    fn placement() {
+        assert_eq!((center(0), center(27), center(9)), (0, 3, 1));
+        // e4 is two ranks up for white, e5 three for black
+        assert_eq!((advance(36, Color::White), advance(28, Color::Black)), (2, 2));
+    }
+}
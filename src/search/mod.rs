@@ -0,0 +1,509 @@
+// Alpha-beta over the legal moves: iterative deepening, a quiescence
+// search of the captures and a transposition table.
+//
+// Lazy SMP: with more than one thread the helpers run the same
+// iterative deepening on the same position, every other one a ply
+// deeper, and share nothing but the table. What they store makes the
+// main thread faster; its answer is the one reported. A single thread
+// with a depth limit and no deadline searches the same way every time.
+
+mod eval;
+mod table;
+mod choice;
+
+use std::cmp::{self, Reverse};
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Instant;
+use kind::*;
+use moves::Move;
+use position::{Outcome, Position};
+use self::table::{Bound, Entry, Table};
+
+pub use self::eval::evaluate;
+pub use self::choice::{MATE_DEPTH, Choice, choose_move};
+
+// mate on the board, mates further away score a point less per ply
+pub const MATE: i32 = 30000;
+const INFINITY: i32 = 32000;
+// the longest line searched, quiescence included
+const MAX_PLY: u32 = 128;
+// nodes between looks at the clock
+const CHECK_EVERY: u64 = 1024;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    // in moves, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    fn new(value: i32) -> Self {
+        if value >= MATE - MAX_PLY as i32 {
+            Score::Mate((MATE - value + 1) / 2)
+        } else if value <= -MATE + MAX_PLY as i32 {
+            Score::Mate(-(MATE + value) / 2)
+        } else {
+            Score::Centipawns(value)
+        }
+    }
+}
+
+// as UCI writes it: "cp 35", "mate -2"
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Score::Centipawns(cp) => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub deadline: Option<Instant>,
+    // set from another thread to stop early, as UCI `stop` does
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+// A finished iteration
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Report {
+    pub depth: u32,
+    pub score: Score,
+    // of all the threads
+    pub nodes: u64,
+    // starts with the best move
+    pub pv: Vec<Move>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Search {
+    threads: usize,
+    table: Arc<Table>,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search::new(1, DEFAULT_HASH_MB)
+    }
+}
+
+impl Search {
+    pub fn new(threads: usize, hash_mb: usize) -> Self {
+        Search {
+            threads: cmp::max(threads, 1),
+            table: Arc::new(Table::new(hash_mb)),
+        }
+    }
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = cmp::max(threads, 1);
+    }
+    // Drops what the table holds
+    pub fn set_hash(&mut self, hash_mb: usize) {
+        self.table = Arc::new(Table::new(hash_mb));
+    }
+    // A new game: nothing learned before is to be trusted
+    pub fn clear(&self) {
+        self.table.clear();
+    }
+
+    // Deepens until a limit is hit or a mate is certain, `report` hears
+    // of every depth the main thread finishes. None when the game is
+    // over, otherwise there is a move even if no depth got finished.
+    pub fn run<F>(&self, position: &Position, limits: &Limits, mut report: F) -> Option<Report>
+        where F: FnMut(&Report)
+    {
+        if position.legal_moves().is_empty() {
+            return None;
+        }
+        let shared = Arc::new(Shared {
+            table: self.table.clone(),
+            done: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+        });
+        let helpers: Vec<_> = (1..self.threads)
+            .map(|i| {
+                let shared = shared.clone();
+                let position = *position;
+                let limits = Limits { depth: None, ..limits.clone() };
+                thread::spawn(move || {
+                    Worker::new(&shared, &limits).deepen(&position, None, i as u32 % 2, |_| {})
+                })
+            })
+            .collect();
+        let result = Worker::new(&shared, limits).deepen(position, limits.depth, 0, &mut report);
+        shared.done.store(true, Ordering::Relaxed);
+        for helper in helpers {
+            let _ = helper.join();
+        }
+        result.map(|r| Report { nodes: shared.nodes.load(Ordering::Relaxed), ..r })
+    }
+}
+
+struct Shared {
+    table: Arc<Table>,
+    // the main thread is through, the helpers should stop
+    done: AtomicBool,
+    nodes: AtomicU64,
+}
+
+struct Worker<'a> {
+    shared: &'a Shared,
+    limits: &'a Limits,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl<'a> Worker<'a> {
+    fn new(shared: &'a Shared, limits: &'a Limits) -> Self {
+        Worker {
+            shared: shared,
+            limits: limits,
+            nodes: 0,
+            aborted: false,
+        }
+    }
+
+    fn deepen<F>(&mut self, position: &Position, depth: Option<u32>, skip: u32, mut report: F)
+                 -> Option<Report>
+        where F: FnMut(&Report)
+    {
+        let moves = position.legal_moves();
+        let mut result = None;
+        for d in (1 + skip)..(depth.unwrap_or(MAX_PLY) + 1) {
+            if self.expired() {
+                break;
+            }
+            let score = self.negamax(position, d, -INFINITY, INFINITY, 0);
+            if self.aborted {
+                break;
+            }
+            let r = Report {
+                depth: d,
+                score: Score::new(score),
+                nodes: self.shared.nodes.load(Ordering::Relaxed) + self.nodes % CHECK_EVERY,
+                pv: self.pv(position, d),
+            };
+            report(&r);
+            result = Some(r);
+            // no deeper search finds a shorter one
+            if score.abs() >= MATE - d as i32 {
+                break;
+            }
+        }
+        self.shared.nodes.fetch_add(self.nodes % CHECK_EVERY, Ordering::Relaxed);
+        // out of time before the first depth was through
+        result.or_else(|| {
+            moves.first().map(|&mv| {
+                Report {
+                    depth: 0,
+                    score: Score::new(evaluate(position)),
+                    nodes: self.shared.nodes.load(Ordering::Relaxed),
+                    pv: vec![mv],
+                }
+            })
+        })
+    }
+
+    fn expired(&self) -> bool {
+        self.limits.stop.as_ref().map_or(false, |s| s.load(Ordering::Relaxed)) ||
+        self.shared.done.load(Ordering::Relaxed) ||
+        self.limits.deadline.map_or(false, |d| Instant::now() >= d)
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes % CHECK_EVERY == 0 {
+            self.shared.nodes.fetch_add(CHECK_EVERY, Ordering::Relaxed);
+            self.aborted = self.expired();
+        }
+        self.aborted
+    }
+
+    fn negamax(&mut self, position: &Position, depth: u32, mut alpha: i32, beta: i32, ply: u32)
+               -> i32 {
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiesce(position, alpha, beta, ply);
+        }
+        if self.out_of_time() {
+            return 0;
+        }
+        let moves = position.legal_moves();
+        if let Some(score) = game_over(position, &moves, ply) {
+            return score;
+        }
+        let key = table::key(position);
+        let mut first = None;
+        if let Some(entry) = self.shared.table.get(key) {
+            first = entry.best;
+            let score = from_table(entry.score, ply);
+            if ply > 0 && entry.depth >= depth &&
+               match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            } {
+                return score;
+            }
+        }
+        let original_alpha = alpha;
+        let mut best = (-INFINITY, None);
+        for i in order(position, &moves, first) {
+            let score = -self.negamax(&position.make_move(moves[i]), depth - 1, -beta, -alpha, ply + 1);
+            if self.aborted {
+                return 0;
+            }
+            if score > best.0 {
+                best = (score, Some(i));
+            }
+            alpha = cmp::max(alpha, score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        let bound = if best.0 >= beta {
+            Bound::Lower
+        } else if best.0 > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.shared.table.put(key,
+                              Entry {
+                                  best: best.1,
+                                  score: to_table(best.0, ply),
+                                  depth: depth,
+                                  bound: bound,
+                              });
+        best.0
+    }
+
+    // Captures and promotions until the position is quiet, all the
+    // moves when in check
+    fn quiesce(&mut self, position: &Position, mut alpha: i32, beta: i32, ply: u32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        let moves = position.legal_moves();
+        if let Some(score) = game_over(position, &moves, ply) {
+            return score;
+        }
+        let check = position.is_check();
+        if !check {
+            let standing = evaluate(position);
+            if standing >= beta || ply >= MAX_PLY {
+                return standing;
+            }
+            alpha = cmp::max(alpha, standing);
+        }
+        let mut tried: Vec<(i32, Move)> = moves.iter()
+            .filter_map(|&mv| gain(position, mv).or(if check { Some(0) } else { None }).map(|g| (g, mv)))
+            .collect();
+        tried.sort_by_key(|&(g, _)| Reverse(g));
+        for (_, mv) in tried {
+            let score = -self.quiesce(&position.make_move(mv), -beta, -alpha, ply + 1);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = cmp::max(alpha, score);
+        }
+        alpha
+    }
+
+    // The best moves the table remembers, as far as they are legal
+    fn pv(&self, position: &Position, depth: u32) -> Vec<Move> {
+        let mut result = vec![];
+        let mut position = *position;
+        while result.len() < depth as usize {
+            let moves = position.legal_moves();
+            let best = self.shared.table.get(table::key(&position)).and_then(|e| e.best);
+            match best.and_then(|i| moves.get(i)) {
+                Some(&mv) => {
+                    result.push(mv);
+                    position = position.make_move(mv);
+                }
+                None => break,
+            }
+        }
+        result
+    }
+}
+
+// The score for the side to move when the game is over
+fn game_over(position: &Position, moves: &[Move], ply: u32) -> Option<i32> {
+    let outcome = if moves.is_empty() {
+        position.outcome()
+    } else {
+        position.rules.variant().outcome(position)
+    };
+    outcome.map(|outcome| match outcome {
+        Outcome::Draw => 0,
+        Outcome::Win(color) if color == position.active => MATE - ply as i32,
+        Outcome::Win(_) => -MATE + ply as i32,
+    })
+}
+
+// Mates are stored as seen from the node, not from the root
+fn to_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+// The move the table suggests first, then captures of the most
+// valuable pieces by the least valuable ones, then the rest
+fn order(position: &Position, moves: &[Move], first: Option<usize>) -> Vec<usize> {
+    let mut result: Vec<usize> = (0..moves.len()).collect();
+    result.sort_by_key(|&i| {
+        if Some(i) == first {
+            Reverse(INFINITY)
+        } else {
+            Reverse(gain(position, moves[i]).unwrap_or(-INFINITY))
+        }
+    });
+    result
+}
+
+// For captures and promotions: what is taken or made, less a little
+// for what takes it. None for the quiet moves.
+fn gain(position: &Position, mv: Move) -> Option<i32> {
+    let made = if mv.promote != UNKNOWN {
+        eval::VALUES[mv.promote.bits() as usize] - eval::VALUES[0]
+    } else {
+        0
+    };
+    let taken = position.captured_square(mv).map(|square| {
+        let victim = position.board.get_piece(square).kind();
+        let attacker = position.board.get_piece(mv.from.mask()).kind();
+        10 * eval::VALUES[victim.bits() as usize] - eval::VALUES[attacker.bits() as usize] / 10
+    });
+    match taken {
+        Some(t) => Some(t + made),
+        None if made > 0 => Some(made),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn search(fen: &str, depth: u32, threads: usize) -> Report {
+        let limits = Limits { depth: Some(depth), ..Limits::default() };
+        Search::new(threads, 1).run(&Position::parse(fen), &limits, |_| {}).unwrap()
+    }
+
+    fn best(fen: &str, depth: u32) -> String {
+        format!("{}", search(fen, depth, 1).pv[0])
+    }
+
+    #[test]
+    fn mates() {
+        let r = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4, 1);
+        assert_eq!(r.score, Score::Mate(1));
+        assert_eq!(format!("{}", r.pv[0]), "a1-a8");
+        // the side to move gets mated
+        let r = search("k7/8/1K6/8/8/8/7R/8 b - - 0 1", 4, 1);
+        assert_eq!(r.score, Score::Mate(-1));
+        let r = search("k7/8/2K5/8/8/8/8/1R6 w - - 0 1", 5, 1);
+        assert_eq!(r.score, Score::Mate(2));
+    }
+
+    #[test]
+    fn takes_what_hangs() {
+        assert_eq!(best("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 3), "d1-d5");
+        // but not when it is defended by a pawn
+        let r = search("4k3/2p5/3n4/8/8/8/8/3RK3 w - - 0 1", 3, 1);
+        assert!(format!("{}", r.pv[0]) != "d1-d6");
+        // promotes
+        assert_eq!(best("8/P6k/8/8/8/8/8/K7 w - - 0 1", 2), "a7-a8=Q");
+        // drops are quiet moves
+        assert_eq!(best("6k1/5ppp/8/8/8/8/8/6K1[R] w - - 0 1", 2), "R@a8");
+    }
+
+    #[test]
+    fn game_over() {
+        let limits = Limits { depth: Some(3), ..Limits::default() };
+        let mated = Position::parse("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        assert_eq!(Search::default().run(&mated, &limits, |_| {}), None);
+        let stalemate = Position::parse("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        assert_eq!(Search::default().run(&stalemate, &limits, |_| {}), None);
+        // the variant's own ends: taking next to the king blows it up
+        let atomic = Position {
+            rules: Rules::Atomic,
+            ..Position::parse("k7/1p6/8/8/8/8/8/KR6 w - - 0 1")
+        };
+        let r = Search::default().run(&atomic, &limits, |_| {}).unwrap();
+        assert_eq!((format!("{}", r.pv[0]), r.score), ("b1-b7".to_string(), Score::Mate(1)));
+    }
+
+    #[test]
+    fn deterministic() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1";
+        let first = search(fen, 3, 1);
+        assert_eq!(search(fen, 3, 1), first);
+        let mut depths = vec![];
+        let limits = Limits { depth: Some(2), ..Limits::default() };
+        Search::default().run(&Position::parse(fen), &limits, |r| depths.push(r.depth));
+        assert_eq!(depths, vec![1, 2]);
+    }
+
+    #[test]
+    fn threads() {
+        let r = search("k7/8/2K5/8/8/8/8/1R6 w - - 0 1", 5, 4);
+        assert_eq!(r.score, Score::Mate(2));
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1";
+        let p = Position::parse(fen);
+        assert!(p.legal_moves().contains(&search(fen, 3, 3).pv[0]));
+    }
+
+    #[test]
+    fn stops() {
+        let p = Position::parse("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+        let stop = Arc::new(AtomicBool::new(true));
+        let limits = Limits { stop: Some(stop), ..Limits::default() };
+        let r = Search::new(2, 1).run(&p, &limits, |_| {}).unwrap();
+        assert_eq!(r.depth, 0);
+        assert!(p.legal_moves().contains(&r.pv[0]));
+        let limits = Limits { deadline: Some(Instant::now()), ..Limits::default() };
+        assert_eq!(Search::default().run(&p, &limits, |_| {}).unwrap().depth, 0);
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(Score::new(MATE - 1), Score::Mate(1));
+        assert_eq!(Score::new(MATE - 3), Score::Mate(2));
+        assert_eq!(Score::new(-MATE + 2), Score::Mate(-1));
+        assert_eq!(Score::new(-35), Score::Centipawns(-35));
+        assert_eq!(format!("{} {}", Score::Mate(-1), Score::Centipawns(12)), "mate -1 cp 12");
+        assert_eq!(from_table(to_table(MATE - 5, 3), 3), MATE - 5);
+    }
+}
@@ -0,0 +1,139 @@
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use position::Position;
+
+// The transposition table the threads share. It takes no locks: an
+// entry is written as the key xor the data, then the data, so one torn
+// by two threads writing at once fails the key check and reads as empty.
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    // at least the score, the search failed high
+    Lower,
+    // at most the score, it failed low
+    Upper,
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub struct Entry {
+    // the index of the best move in `legal_moves`
+    pub best: Option<usize>,
+    pub score: i32,
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+impl Entry {
+    // 12 bits of move, 16 of score, 8 of depth, 2 of bound
+    fn pack(&self) -> u64 {
+        let best = self.best.map_or(0, |i| i as u64 + 1) & 0xFFF;
+        let score = self.score as i16 as u16 as u64;
+        let depth = cmp::min(self.depth, 0xFF) as u64;
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        best | score << 12 | depth << 28 | bound << 36
+    }
+    fn unpack(data: u64) -> Self {
+        let best = (data & 0xFFF) as usize;
+        Entry {
+            best: if best == 0 { None } else { Some(best - 1) },
+            score: (data >> 12) as u16 as i16 as i32,
+            depth: (data >> 28 & 0xFF) as u32,
+            bound: match data >> 36 & 3 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
+    }
+}
+
+pub fn key(position: &Position) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    position.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug)]
+pub struct Table {
+    // two words an entry: the key xor the data, the data
+    words: Vec<AtomicU64>,
+}
+
+impl Table {
+    // The largest power of two number of entries that fits
+    pub fn new(megabytes: usize) -> Self {
+        let mut entries = 1;
+        while entries * 2 * 16 <= megabytes << 20 {
+            entries *= 2;
+        }
+        Table { words: (0..entries * 2).map(|_| AtomicU64::new(0)).collect() }
+    }
+    pub fn clear(&self) {
+        for word in &self.words {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+    fn slot(&self, key: u64) -> usize {
+        (key as usize & (self.words.len() / 2 - 1)) * 2
+    }
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        let slot = self.slot(key);
+        let check = self.words[slot].load(Ordering::Relaxed);
+        let data = self.words[slot + 1].load(Ordering::Relaxed);
+        if data != 0 && check ^ data == key {
+            Some(Entry::unpack(data))
+        } else {
+            None
+        }
+    }
+    // Always replaces, the latest search knows best
+    pub fn put(&self, key: u64, entry: Entry) {
+        let slot = self.slot(key);
+        let data = entry.pack();
+        self.words[slot].store(key ^ data, Ordering::Relaxed);
+        self.words[slot + 1].store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let table = Table::new(1);
+        let entry = Entry {
+            best: Some(17),
+            score: -29990,
+            depth: 7,
+            bound: Bound::Upper,
+        };
+        table.put(12345, entry);
+        assert_eq!(table.get(12345), Some(entry));
+        // another key on the same slot
+        assert_eq!(table.get(12345 + (1 << 40)), None);
+        let entry = Entry {
+            best: None,
+            score: 15,
+            depth: 0,
+            bound: Bound::Exact,
+        };
+        table.put(12345, entry);
+        assert_eq!(table.get(12345), Some(entry));
+        table.clear();
+        assert_eq!(table.get(12345), None);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(Table::new(1).words.len(), 2 << 16);
+        assert_eq!(Table::new(0).words.len(), 2);
+    }
+}
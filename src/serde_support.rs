@@ -0,0 +1,491 @@
+// `serde` support, behind the feature of the same name. Human-readable
+// formats get the notation: "e4", "N", "KQkq", "e7e8q", FEN; the others
+// get the bits.
+use std::fmt;
+use nom::IResult;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor, MapAccess, Unexpected};
+use serde::ser::SerializeStruct;
+
+use bit_board::BitBoard;
+use bit_board::fen::parse_bit_board;
+use castle::{self, Castle, parse_castle};
+use color::{Color, parse_color};
+use file::{File, parse_file};
+use kind::*;
+use mask::Mask;
+use moves::{Move, parse_move};
+use piece::*;
+use pocket::Pockets;
+use position::Position;
+use position::parse_position;
+use rank::{Rank, parse_rank};
+use rules::Rules;
+use square::{Square, parse_square, UNDEFINED_SQUARE};
+
+// The whole input or nothing
+fn complete<T, E>(result: IResult<&[u8], T, E>) -> Option<T> {
+    match result {
+        IResult::Done(rest, value) if rest.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+fn expected<E: de::Error>(what: &str, input: &str) -> E {
+    E::custom(format!("expected {}, got {:?}", what, input))
+}
+
+// Types shown as a string and packed into an integer
+macro_rules! string_or_bits {
+    ($t:ty, $bits:ty, $what:expr,
+     |$s:ident| $to_string:expr, |$input:ident| $parse:expr,
+     |$v:ident| $to_bits:expr, |$b:ident| $from_bits:expr) => {
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let $s = *self;
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&$to_string)
+                } else {
+                    let $v = $s;
+                    let bits: $bits = $to_bits;
+                    bits.serialize(serializer)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    let $input = String::deserialize(deserializer)?;
+                    let parsed: Option<$t> = $parse;
+                    parsed.ok_or_else(|| expected($what, &$input))
+                } else {
+                    let $b = <$bits>::deserialize(deserializer)?;
+                    let parsed: Option<$t> = $from_bits;
+                    parsed.ok_or_else(|| de::Error::custom(format!("bad {} {}", $what, $b)))
+                }
+            }
+        }
+    }
+}
+
+string_or_bits!(Square, u8, "a square",
+                |s| format!("{}", s), |input| complete(parse_square(input.as_bytes())),
+                |v| v.bits(), |b| if b < 64 { Some(Square::from_bits(b)) } else { None });
+
+string_or_bits!(File, u8, "a file",
+                |s| format!("{}", s), |input| complete(parse_file(input.as_bytes())),
+                |v| v.bits(), |b| if b < 8 { Some(File::from_bits(b)) } else { None });
+
+string_or_bits!(Rank, u8, "a rank",
+                |s| format!("{}", s), |input| complete(parse_rank(input.as_bytes())),
+                |v| v.bits(), |b| if b < 8 { Some(Rank::from_bits(b)) } else { None });
+
+string_or_bits!(Color, u8, "a color",
+                |s| format!("{}", s), |input| complete(parse_color(input.as_bytes())),
+                |v| v as u8, |b| match b {
+                    0 => Some(Color::Black),
+                    1 => Some(Color::White),
+                    _ => None,
+                });
+
+string_or_bits!(Kind, u8, "a kind",
+                |s| format!("{}", s), |input| single(&input, b"PNBRQK").map(Kind::new),
+                |v| v.bits(), |b| if b < KINDS_COUNT { Some(Kind::new(b)) } else { None });
+
+string_or_bits!(Piece, u8, "a piece",
+                |s| format!("{}", s), |input| single(&input, b"PNBRQKpnbrqk").map(Piece::new),
+                |v| v.bits(), |b| if (b as usize) < PIECES_COUNT {
+                    Some(Piece::new(b))
+                } else {
+                    None
+                });
+
+string_or_bits!(Castle, u8, "castling rights",
+                |s| format!("{}", s), |input| complete(parse_castle(input.as_bytes())),
+                |v| v.bits(), |b| Castle::from_bits(b));
+
+string_or_bits!(Move, u64, "a move",
+                |s| uci(s), |input| parse_uci(&input),
+                |v| pack_move(v), |b| unpack_move(b));
+
+// the index of the only character in `symbols`
+fn single(input: &str, symbols: &[u8]) -> Option<u8> {
+    let bytes = input.as_bytes();
+    if bytes.len() != 1 {
+        return None;
+    }
+    symbols.iter().position(|&c| c == bytes[0]).map(|i| i as u8)
+}
+
+// "e2e4", "e7e8q", "N@f3", and "O-O"/"O-O-O" as a move
+// knows nothing about the king's squares
+fn uci(mv: Move) -> String {
+    if mv.castle != castle::NONE || mv.is_drop() {
+        return format!("{}", mv);
+    }
+    let mut result = format!("{}{}", mv.from, mv.to);
+    if mv.promote != UNKNOWN {
+        result.push(mv.promote.char().to_ascii_lowercase());
+    }
+    result
+}
+
+fn parse_uci(input: &str) -> Option<Move> {
+    let mv = complete(parse_move(input.as_bytes()));
+    if mv.is_some() || input.len() != 5 || !input.is_char_boundary(4) {
+        return mv;
+    }
+    let promote = single(&input[4..].to_uppercase(), b"PNBRQK").map(Kind::new)?;
+    if promote == PAWN {
+        return None;
+    }
+    let mv = complete(parse_move(input[..4].as_bytes()))?;
+    Some(Move::promote(mv.from, mv.to, promote))
+}
+
+fn pack_move(mv: Move) -> u64 {
+    [mv.from.bits(), mv.to.bits(), mv.promote.bits(), mv.castle.bits(), mv.drop.bits()]
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, &b)| acc | (b as u64) << (8 * i))
+}
+
+fn unpack_move(bits: u64) -> Option<Move> {
+    let byte = |i: u64| (bits >> (8 * i)) as u8;
+    let square = |b: u8| if b < 64 {
+        Some(Square::from_bits(b))
+    } else if b == UNDEFINED_SQUARE.bits() {
+        Some(UNDEFINED_SQUARE)
+    } else {
+        None
+    };
+    let kind = |b: u8| if b < KINDS_COUNT || Kind::new(b) == UNKNOWN {
+        Some(Kind::new(b))
+    } else {
+        None
+    };
+    if bits >> 40 != 0 {
+        return None;
+    }
+    Some(Move {
+        from: square(byte(0))?,
+        to: square(byte(1))?,
+        promote: kind(byte(2))?,
+        castle: Castle::from_bits(byte(3))?,
+        drop: kind(byte(4))?,
+    })
+}
+
+// A list of squares, or the bits
+impl Serialize for Mask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let squares = self.single_bits()
+                .map(|b| Square::from_bits(b.index_of_least_significant_bit() as u8))
+                .collect::<Vec<_>>();
+            squares.serialize(serializer)
+        } else {
+            self.bits().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Mask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let squares = Vec::<Square>::deserialize(deserializer)?;
+            Ok(squares.into_iter().fold(Mask::new(0), |acc, s| acc | s.mask()))
+        } else {
+            u64::deserialize(deserializer).map(Mask::new)
+        }
+    }
+}
+
+// The board part of FEN, or a mask a piece
+impl Serialize for BitBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&format!("{}", self))
+        } else {
+            board_bits(self).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BitBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let input = String::deserialize(deserializer)?;
+            complete(parse_bit_board(input.as_bytes()))
+                .ok_or_else(|| expected("a FEN board", &input))
+        } else {
+            let bits = <[u64; PIECES_COUNT]>::deserialize(deserializer)?;
+            board_from_bits(&bits)
+        }
+    }
+}
+
+fn board_bits(board: &BitBoard) -> [u64; PIECES_COUNT] {
+    let mut result = [0; PIECES_COUNT];
+    for (i, bits) in result.iter_mut().enumerate() {
+        *bits = board.pieces(Piece::new(i as u8)).bits();
+    }
+    result
+}
+
+fn board_from_bits<E: de::Error>(bits: &[u64; PIECES_COUNT]) -> Result<BitBoard, E> {
+    let mut board = BitBoard::new();
+    let mut occupied = 0;
+    for (i, &b) in bits.iter().enumerate() {
+        if occupied & b != 0 {
+            return Err(E::custom("two pieces on one square"));
+        }
+        occupied |= b;
+        board.set_piece(Mask::new(b), Piece::new(i as u8));
+    }
+    Ok(board)
+}
+
+// FEN, with the variant next to it when FEN does not tell it. The
+// compact form keeps every field. Custom rules can be written but
+// not read back.
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fen = format!("{}", self);
+        if serializer.is_human_readable() {
+            if self.rules == rules_of_fen(self) {
+                return serializer.serialize_str(&fen);
+            }
+            let mut s = serializer.serialize_struct("Position", 2)?;
+            s.serialize_field("fen", &fen)?;
+            s.serialize_field("variant", self.rules.name())?;
+            return s.end();
+        }
+        let mut pockets = [0u8; PIECES_COUNT];
+        for (i, count) in pockets.iter_mut().enumerate() {
+            *count = self.pockets.count(Piece::new(i as u8));
+        }
+        (board_bits(&self.board),
+         self.active,
+         self.available,
+         self.en_passant,
+         self.rules.name(),
+         pockets,
+         self.promoted,
+         self.remaining_checks)
+            .serialize(serializer)
+    }
+}
+
+fn rules_of_fen(p: &Position) -> Rules {
+    match p.rules {
+        Rules::Crazyhouse | Rules::ThreeCheck => p.rules,
+        _ => Rules::Standard,
+    }
+}
+
+fn parse_fen<E: de::Error>(fen: &str, rules: Option<&str>) -> Result<Position, E> {
+    let p = match parse_position(fen.as_bytes()) {
+        IResult::Done(rest, p) if rest.iter().all(|&c| c == b' ' || c.is_ascii_digit()) => p,
+        _ => return Err(expected("a FEN", fen)),
+    };
+    match rules {
+        None => Ok(p),
+        Some(name) => {
+            let rules = Rules::from_name(name).ok_or_else(|| expected("a variant", name))?;
+            Ok(Position { rules: rules, ..p })
+        }
+    }
+}
+
+struct PositionVisitor;
+
+impl<'de> Visitor<'de> for PositionVisitor {
+    type Value = Position;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a FEN or a map with `fen` and `variant`")
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Position, E> {
+        parse_fen(v, None)
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Position, A::Error> {
+        let (mut fen, mut variant) = (None::<String>, None::<String>);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "fen" => fen = Some(map.next_value()?),
+                "variant" => variant = Some(map.next_value()?),
+                _ => return Err(de::Error::unknown_field(&key, &["fen", "variant"])),
+            }
+        }
+        let fen = fen.ok_or_else(|| de::Error::missing_field("fen"))?;
+        parse_fen(&fen, variant.as_ref().map(|v| v.as_str()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserializer.deserialize_any(PositionVisitor);
+        }
+        let (board, active, available, en_passant, rules, pockets, promoted, checks):
+            ([u64; PIECES_COUNT], Color, Castle, Option<File>, String, [u8; PIECES_COUNT],
+             Mask, [u8; 2]) = Deserialize::deserialize(deserializer)?;
+        let rules = Rules::from_name(&rules).ok_or_else(|| expected("a variant", &rules))?;
+        if let Some(&n) = checks.iter().find(|&&n| n > 3) {
+            return Err(de::Error::invalid_value(Unexpected::Unsigned(n as u64),
+                                                &"at most 3 checks to go"));
+        }
+        let board = board_from_bits(&board)?;
+        let promotable = [KNIGHT, BISHOP, ROOK, QUEEN]
+            .iter()
+            .fold(Mask::new(0), |m, &k| {
+                m | board.pieces(k.of(Color::White)) | board.pieces(k.of(Color::Black))
+            });
+        if !promotable.contains(promoted) {
+            return Err(de::Error::invalid_value(Unexpected::Unsigned(promoted.bits()),
+                                                &"promoted pieces on the board"));
+        }
+        let mut p = Position {
+            available: available,
+            en_passant: en_passant,
+            rules: rules,
+            promoted: promoted,
+            remaining_checks: checks,
+            ..Position::new(board, active)
+        };
+        let mut pocket = Pockets::new();
+        for (i, &count) in pockets.iter().enumerate() {
+            let piece = Piece::new(i as u8);
+            if count > 0 && piece.kind() == KING {
+                return Err(de::Error::custom("a king in the pocket"));
+            }
+            for _ in 0..count {
+                pocket.add(piece).map_err(|_| de::Error::custom("a full pocket"))?;
+            }
+        }
+        p.pockets = pocket;
+        Ok(p)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_test::{Token, Configure, Readable, Compact, assert_tokens, assert_de_tokens_error};
+    use square::*;
+
+    #[test]
+    fn readable() {
+        assert_tokens(&E4.readable(), &[Token::Str("e4")]);
+        assert_tokens(&File::parse('c').readable(), &[Token::Str("c")]);
+        assert_tokens(&Rank::parse('7').readable(), &[Token::Str("7")]);
+        assert_tokens(&Color::White.readable(), &[Token::Str("w")]);
+        assert_tokens(&KNIGHT.readable(), &[Token::Str("N")]);
+        assert_tokens(&BLACK_QUEEN.readable(), &[Token::Str("q")]);
+        assert_tokens(&Castle::parse("Kq").readable(), &[Token::Str("Kq")]);
+        assert_tokens(&castle::NONE.readable(), &[Token::Str("-")]);
+        assert_tokens(&(E2.mask() | E4.mask()).readable(),
+                      &[Token::Seq { len: Some(2) },
+                        Token::Str("e4"),
+                        Token::Str("e2"),
+                        Token::SeqEnd]);
+    }
+
+    #[test]
+    fn moves() {
+        assert_tokens(&Move::new(E2, E4).readable(), &[Token::Str("e2e4")]);
+        assert_tokens(&Move::promote(E7, E8, QUEEN).readable(), &[Token::Str("e7e8q")]);
+        assert_tokens(&Move::parse("O-O-O").readable(), &[Token::Str("O-O-O")]);
+        assert_tokens(&Move::drop(KNIGHT, F3).readable(), &[Token::Str("N@f3")]);
+        for &mv in &[Move::new(E2, E4), Move::parse("O-O"), Move::drop(PAWN, A3)] {
+            assert_tokens(&mv.compact(), &[Token::U64(pack_move(mv))]);
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        let p = Position::parse(fen);
+        assert_tokens(&p.readable(), &[Token::Str(fen)]);
+        assert_tokens(&p.board.readable(),
+                      &[Token::Str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR")]);
+        let p = Position::start(Rules::Atomic);
+        assert_tokens(&p.readable(),
+                      &[Token::Struct {
+                            name: "Position",
+                            len: 2,
+                        },
+                        Token::Str("fen"),
+                        Token::Str(fen),
+                        Token::Str("variant"),
+                        Token::Str("Atomic"),
+                        Token::StructEnd]);
+    }
+
+    // a black to move crazyhouse position without castling
+    fn compact_tokens(board: &[u64], pockets: &[u8]) -> Vec<Token> {
+        compact_tokens_with(board, pockets, 0, 3)
+    }
+
+    fn compact_tokens_with(board: &[u64], pockets: &[u8], promoted: u64, checks: u8)
+                           -> Vec<Token> {
+        let mut tokens = vec![Token::Tuple { len: 8 }, Token::Tuple { len: PIECES_COUNT }];
+        tokens.extend(board.iter().map(|&b| Token::U64(b)));
+        tokens.extend_from_slice(&[Token::TupleEnd,
+                                   Token::U8(0),
+                                   Token::U8(0),
+                                   Token::None,
+                                   Token::Str("Crazyhouse"),
+                                   Token::Tuple { len: PIECES_COUNT }]);
+        tokens.extend(pockets.iter().map(|&n| Token::U8(n)));
+        tokens.extend_from_slice(&[Token::TupleEnd,
+                                   Token::U64(promoted),
+                                   Token::Tuple { len: 2 },
+                                   Token::U8(checks),
+                                   Token::U8(3),
+                                   Token::TupleEnd,
+                                   Token::TupleEnd]);
+        tokens
+    }
+
+    #[test]
+    fn compact_position() {
+        let p = Position::parse("4k3/8/8/8/8/8/8/4K3[Qn] b - - 0 1");
+        let board = board_bits(&p.board);
+        let pockets: Vec<u8> = (0..PIECES_COUNT)
+            .map(|i| p.pockets.count(Piece::new(i as u8)))
+            .collect();
+        assert_tokens(&p.compact(), &compact_tokens(&board, &pockets));
+
+        let mut kings = pockets.clone();
+        kings[WHITE_KING.bits() as usize] = 1;
+        assert_de_tokens_error::<Compact<Position>>(&compact_tokens(&board, &kings),
+                                                    "a king in the pocket");
+        let mut overlapping = board;
+        overlapping[WHITE_QUEEN.bits() as usize] = board[WHITE_KING.bits() as usize];
+        assert_de_tokens_error::<Compact<Position>>(&compact_tokens(&overlapping, &pockets),
+                                                    "two pieces on one square");
+        let error = "invalid value: integer `4`, expected at most 3 checks to go";
+        assert_de_tokens_error::<Compact<Position>>(&compact_tokens_with(&board, &pockets, 0, 4),
+                                                    error);
+        // the e1 king
+        let king = board[WHITE_KING.bits() as usize];
+        let error = format!("invalid value: integer `{}`, expected promoted pieces on the board",
+                            king);
+        let promoted = compact_tokens_with(&board, &pockets, king, 3);
+        assert_de_tokens_error::<Compact<Position>>(&promoted, &error);
+    }
+
+    #[test]
+    fn errors() {
+        assert_de_tokens_error::<Readable<Square>>(&[Token::Str("i9")],
+                                                   "expected a square, got \"i9\"");
+        assert_de_tokens_error::<Compact<Square>>(&[Token::U8(64)], "bad a square 64");
+        assert_de_tokens_error::<Readable<Move>>(&[Token::Str("e7e8x")],
+                                                 "expected a move, got \"e7e8x\"");
+        assert_de_tokens_error::<Readable<Position>>(&[Token::Str("8/8 w")],
+                                                     "expected a FEN, got \"8/8 w\"");
+    }
+}
@@ -18,6 +18,7 @@ pub trait Side {
     const RANGE : Range<usize>;
     const EN_PASSANT_RANK : Rank;
     const DOUBLE_PUSH_RANK_MASK : Mask;
+    const PROMOTION_RANK_MASK : Mask;
 }
 
 #[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
@@ -39,6 +40,7 @@ impl Side for White {
     const RANGE : Range<usize> = 0..6;
     const EN_PASSANT_RANK : Rank = _6;
     const DOUBLE_PUSH_RANK_MASK : Mask = masks::_4;
+    const PROMOTION_RANK_MASK : Mask = masks::_8;
 }
 impl Side for Black {
     type Mask = BlackMask;
@@ -54,4 +56,5 @@ impl Side for Black {
     const RANGE : Range<usize> = 6..12;
     const EN_PASSANT_RANK : Rank = _3;
     const DOUBLE_PUSH_RANK_MASK : Mask = masks::_5;
+    const PROMOTION_RANK_MASK : Mask = masks::_1;
 }
@@ -0,0 +1,358 @@
+use castle;
+use color::Color;
+use mask::Mask;
+use mask::masks::EMPTY;
+use moves::Move;
+use piece::*;
+use position::Position;
+use position::castling::castling;
+use square::Square;
+use std::fmt::Write;
+
+// Geometry in user units, the whole picture is scaled to `size` pixels
+const SQUARE: u32 = 45;
+const MARGIN: u32 = 15;
+
+const LIGHT: &'static str = "#f0d9b5";
+const DARK: &'static str = "#b58863";
+const LAST_MOVE: &'static str = "#cdd26a";
+
+// A line between two square centers, or a circle when they coincide
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    pub color: String,
+}
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct SvgStyle {
+    // width and height in pixels
+    pub size: u32,
+    // black at the bottom
+    pub flipped: bool,
+    pub coordinates: bool,
+    // the move that led to the position, castling included
+    pub last_move: Option<Move>,
+    // a red glow under the king of the side in check
+    pub check: bool,
+    // squares painted with a CSS color, drawn in order
+    pub squares: Vec<(Mask, String)>,
+    pub arrows: Vec<Arrow>,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle {
+            size: 400,
+            flipped: false,
+            coordinates: true,
+            last_move: None,
+            check: true,
+            squares: vec![],
+            arrows: vec![],
+        }
+    }
+}
+
+// Glyphs in a 45x45 box, in kind order: PNBRQK
+static GLYPHS: [&'static str; 6] =
+    ["M22.5 9a4 4 0 0 0-3.2 6.4 6 6 0 0 0-2.3 10.6c-3 1-7.5 5.6-7.5 13.5h26c0-7.9-4.5-12.5\
+      -7.5-13.5a6 6 0 0 0-2.3-10.6A4 4 0 0 0 22.5 9z",
+     "M14 39h21c0-11-2-18-7-24l1-6-4 3-2-3-1 4c-5 2-9 7-11 13l3 2 4-3 3-1c1 4-3 8-7 15z",
+     "M9 39h27v-3H9zM15 36c0-5 2-9 4-11-5-3-5-10 3.5-15 8.5 5 8.5 12 3.5 15 2 2 4 6 4 11z\
+      M22.5 5a2.5 2.5 0 1 1 0 5 2.5 2.5 0 1 1 0-5z",
+     "M9 39h27v-3H9zM12 36v-4h21v4zM14 32V17h17v15zM11 17l3-3h17l3 3zM11 14V9h4v2h5V9h5v2h5V9h4v5z",
+     "M9 39h27v-3H9zM11 36L8 15l7 10 2-13 5.5 12 5.5-12 2 13 7-10-3 21zM8 11a2 2 0 1 1 0 4 2 2 0 1 \
+      1 0-4zM17 8a2 2 0 1 1 0 4 2 2 0 1 1 0-4zM28 8a2 2 0 1 1 0 4 2 2 0 1 1 0-4zM37 11a2 2 0 1 1 0 4 \
+      2 2 0 1 1 0-4z",
+     "M9 39h27v-3H9zM11 36c-4-7-2-14 4-15 4-1 6 2 7.5 5 1.5-3 3.5-6 7.5-5 6 1 8 8 4 15z\
+      M21 6h3v4h3v3h-3v6h-3v-6h-3v-3h3z"];
+
+// A standalone SVG document of the position. Layers, bottom up: board,
+// annotated squares, last move, check, coordinates, pieces, arrows.
+pub fn svg(p: &Position, style: &SvgStyle) -> String {
+    let margin = if style.coordinates { MARGIN } else { 0 };
+    let side = 8 * SQUARE + 2 * margin;
+    let corner = |square: Mask| {
+        let (column, row) = column_row(square, style.flipped);
+        (margin + column * SQUARE, margin + row * SQUARE)
+    };
+    let mut out = String::new();
+    let _ = write!(out,
+                   "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                    xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                    version=\"1.1\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {1} {1}\">\n",
+                   style.size,
+                   side);
+
+    out.push_str("<defs>\n");
+    for i in 0..PIECES_COUNT as u8 {
+        let piece = Piece::new(i);
+        if p.board.pieces(piece) == EMPTY {
+            continue;
+        }
+        let (fill, stroke) = if piece.color() == Color::White {
+            ("#fff", "#000")
+        } else {
+            ("#000", "#fff")
+        };
+        let _ = write!(out,
+                       "<path id=\"{}\" d=\"{}\" fill=\"{}\" stroke=\"{}\" \
+                        stroke-width=\"1.5\" stroke-linejoin=\"round\"/>\n",
+                       glyph_id(piece),
+                       GLYPHS[piece.kind().bits() as usize],
+                       fill,
+                       stroke);
+    }
+    out.push_str("<radialGradient id=\"check\">\
+                  <stop offset=\"0%\" stop-color=\"#f00\"/>\
+                  <stop offset=\"50%\" stop-color=\"#e70\" stop-opacity=\"0.7\"/>\
+                  <stop offset=\"100%\" stop-color=\"#a00\" stop-opacity=\"0\"/>\
+                  </radialGradient>\n");
+    out.push_str("</defs>\n");
+
+    if margin > 0 {
+        let _ = write!(out,
+                       "<rect width=\"{0}\" height=\"{0}\" fill=\"#212121\"/>\n",
+                       side);
+    }
+    for square in Mask::new(!0).single_bits() {
+        let (x, y) = corner(square);
+        let index = square.index_of_least_significant_bit();
+        let color = if (index % 8 + index / 8) % 2 == 0 {
+            LIGHT
+        } else {
+            DARK
+        };
+        rect(&mut out, x, y, color);
+    }
+    for &(ref mask, ref color) in &style.squares {
+        for square in mask.single_bits() {
+            let (x, y) = corner(square);
+            rect(&mut out, x, y, color);
+        }
+    }
+    if let Some(mv) = style.last_move {
+        for square in moved_squares(p, mv).single_bits() {
+            let (x, y) = corner(square);
+            rect(&mut out, x, y, LAST_MOVE);
+        }
+    }
+    if style.check && p.is_check() {
+        for king in p.board.kings_of(p.active).single_bits() {
+            let (x, y) = corner(king);
+            rect(&mut out, x, y, "url(#check)");
+        }
+    }
+    if margin > 0 {
+        for i in 0..8 {
+            let (column, row) = if style.flipped { (7 - i, i) } else { (i, 7 - i) };
+            let _ = write!(out,
+                           "<text x=\"{}\" y=\"{}\" {}>{}</text>\n",
+                           margin + column * SQUARE + SQUARE / 2,
+                           side - 4,
+                           TEXT,
+                           (b'a' + i as u8) as char);
+            let _ = write!(out,
+                           "<text x=\"{}\" y=\"{}\" {}>{}</text>\n",
+                           margin / 2,
+                           margin + row * SQUARE + SQUARE / 2 + 4,
+                           TEXT,
+                           i + 1);
+        }
+    }
+    for square in p.board.occupation().single_bits() {
+        let (x, y) = corner(square);
+        let _ = write!(out,
+                       "<use xlink:href=\"#{}\" x=\"{}\" y=\"{}\"/>\n",
+                       glyph_id(p.board.get_piece(square)),
+                       x,
+                       y);
+    }
+    for arrow in &style.arrows {
+        let center = |square: Square| {
+            let (x, y) = corner(square.mask());
+            (x as f64 + SQUARE as f64 / 2.0, y as f64 + SQUARE as f64 / 2.0)
+        };
+        let (x1, y1) = center(arrow.from);
+        let (x2, y2) = center(arrow.to);
+        if arrow.from == arrow.to {
+            let _ = write!(out,
+                           "<circle cx=\"{}\" cy=\"{}\" r=\"20\" fill=\"none\" stroke=\"{}\" \
+                            stroke-width=\"3\" opacity=\"0.8\"/>\n",
+                           x1,
+                           y1,
+                           escape(&arrow.color));
+            continue;
+        }
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        let (ux, uy) = ((x2 - x1) / length, (y2 - y1) / length);
+        // the head ends a bit short of the center, the shaft under it
+        let (tip_x, tip_y) = (x2 - ux * 4.0, y2 - uy * 4.0);
+        let (base_x, base_y) = (tip_x - ux * 15.0, tip_y - uy * 15.0);
+        let (wide_x, wide_y) = (-uy * 9.0, ux * 9.0);
+        let _ = write!(out,
+                       "<g opacity=\"0.8\" fill=\"{0}\" stroke=\"{0}\">\
+                        <line x1=\"{1:.1}\" y1=\"{2:.1}\" x2=\"{3:.1}\" y2=\"{4:.1}\" \
+                        stroke-width=\"8\" stroke-linecap=\"round\"/>\
+                        <polygon points=\"{5:.1},{6:.1} {7:.1},{8:.1} {9:.1},{10:.1}\" \
+                        stroke=\"none\"/></g>\n",
+                       escape(&arrow.color),
+                       x1,
+                       y1,
+                       base_x,
+                       base_y,
+                       tip_x,
+                       tip_y,
+                       base_x + wide_x,
+                       base_y + wide_y,
+                       base_x - wide_x,
+                       base_y - wide_y);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+const TEXT: &'static str = "fill=\"#e0e0e0\" font-family=\"sans-serif\" font-size=\"10\" \
+                            text-anchor=\"middle\"";
+
+fn column_row(square: Mask, flipped: bool) -> (u32, u32) {
+    let index = square.index_of_least_significant_bit();
+    if flipped {
+        (7 - index % 8, 7 - index / 8)
+    } else {
+        (index % 8, index / 8)
+    }
+}
+
+fn rect(out: &mut String, x: u32, y: u32, fill: &str) {
+    let _ = write!(out,
+                   "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>\n",
+                   x,
+                   y,
+                   SQUARE,
+                   escape(fill));
+}
+
+// The colors come from the caller: as attribute values they must not
+// close the quotes or open a tag
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn glyph_id(piece: Piece) -> String {
+    let kind = piece.kind().char().to_ascii_lowercase();
+    format!("{}{}", piece.color().char(), kind)
+}
+
+// Castling has no squares of its own, it is shown by the king's path
+fn moved_squares(p: &Position, mv: Move) -> Mask {
+    if mv.castle != castle::NONE {
+        let c = castling(p.active.invert(), mv.castle);
+        c.king_from | c.king_to
+    } else if mv.is_drop() {
+        mv.to.mask()
+    } else {
+        mv.from.mask() | mv.to.mask()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use square::*;
+
+    fn count(svg: &str, needle: &str) -> usize {
+        svg.matches(needle).count()
+    }
+
+    #[test]
+    fn start_position() {
+        let out = svg(&Position::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+                      &SvgStyle::default());
+        assert!(out.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(out.ends_with("</svg>\n"));
+        assert_eq!(count(&out, "<path id="), 12);
+        assert_eq!(count(&out, "<use "), 32);
+        assert_eq!(count(&out, "<text "), 16);
+        // a8 is light and in the top left corner, the rook on it too
+        assert!(out.contains("<rect x=\"15\" y=\"15\" width=\"45\" height=\"45\" fill=\"#f0d9b5\"/>"));
+        assert!(out.contains("<use xlink:href=\"#br\" x=\"15\" y=\"15\"/>"));
+    }
+
+    #[test]
+    fn flipped_without_coordinates() {
+        let style = SvgStyle {
+            flipped: true,
+            coordinates: false,
+            ..SvgStyle::default()
+        };
+        let out = svg(&Position::parse("8/8/8/8/8/8/8/k6K w - - 0 1"), &style);
+        assert!(out.contains("viewBox=\"0 0 360 360\""));
+        assert_eq!(count(&out, "<text "), 0);
+        assert_eq!(count(&out, "<path id="), 2);
+        assert!(out.contains("<use xlink:href=\"#wk\" x=\"0\" y=\"0\"/>"));
+        assert!(out.contains("<use xlink:href=\"#bk\" x=\"315\" y=\"0\"/>"));
+    }
+
+    #[test]
+    fn highlights() {
+        let p = Position::parse("r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4");
+        let style = SvgStyle {
+            last_move: Some(Move::new(H5, F7)),
+            squares: vec![(E8.mask() | D8.mask(), String::from("red"))],
+            ..SvgStyle::default()
+        };
+        let out = svg(&p, &style);
+        assert_eq!(count(&out, "fill=\"#cdd26a\""), 2);
+        assert_eq!(count(&out, "fill=\"red\""), 2);
+        assert_eq!(count(&out, "fill=\"url(#check)\""), 1);
+        let quiet = svg(&p, &SvgStyle { check: false, ..SvgStyle::default() });
+        assert_eq!(count(&quiet, "fill=\"url(#check)\""), 0);
+    }
+
+    #[test]
+    fn castling_and_arrows() {
+        let p = Position::parse("r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 0 4");
+        let style = SvgStyle {
+            last_move: Some(Move::parse("O-O")),
+            arrows: vec![Arrow { from: A7, to: A6, color: String::from("green") },
+                         Arrow { from: E5, to: E5, color: String::from("blue") }],
+            ..SvgStyle::default()
+        };
+        let out = svg(&p, &style);
+        // e1 and g1
+        assert!(out.contains("<rect x=\"195\" y=\"330\" width=\"45\" height=\"45\" fill=\"#cdd26a\"/>"));
+        assert!(out.contains("<rect x=\"285\" y=\"330\" width=\"45\" height=\"45\" fill=\"#cdd26a\"/>"));
+        assert_eq!(count(&out, "<polygon "), 1);
+        assert_eq!(count(&out, "<circle "), 1);
+        assert!(out.contains("<line x1=\"37.5\" y1=\"82.5\" x2=\"37.5\" y2=\"108.5\""));
+    }
+
+    #[test]
+    fn colors_are_escaped() {
+        let p = Position::parse("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let evil = String::from("red\"/><script>alert(1)</script><g x=\"");
+        let style = SvgStyle {
+            squares: vec![(A1.mask(), evil.clone())],
+            arrows: vec![Arrow { from: A1, to: A2, color: evil.clone() },
+                         Arrow { from: B1, to: B1, color: evil }],
+            ..SvgStyle::default()
+        };
+        let out = svg(&p, &style);
+        assert_eq!(count(&out, "<script>"), 0);
+        assert_eq!(count(&out, "red&quot;/&gt;&lt;script&gt;"), 4);
+    }
+}
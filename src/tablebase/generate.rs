@@ -0,0 +1,195 @@
+use std::cmp;
+use kind::*;
+use mask::Mask;
+use mask::masks::*;
+use position::Position;
+use color::Color;
+use super::*;
+use super::index::Indexer;
+
+// Entries of the work lists whose value is not decided yet: a win
+// through a capture or a promotion, unless a shorter one turns up.
+const OUTER: u32 = 1 << 31;
+// No capture or promotion from the position
+const NONE: u8 = UNRESOLVED;
+
+fn is_illegal(p: &Position) -> bool {
+    let mut other = *p;
+    other.active = p.active.invert();
+    other.is_check() || !p.validate().is_empty()
+}
+
+// Retrograde analysis: every position first counts its moves that stay
+// in the table and notes the best of those that leave it. Then the
+// values spread back ply by ply through the unmoves of the positions
+// decided on the ply before: a loss makes every predecessor a win, a
+// win takes one move off each predecessor's count, and a predecessor
+// left with no moves is lost. Unresolved positions are draws.
+//
+// The index has no room for an en passant square. A double push that
+// can be taken en passant (KPKP) is valued as if it could not.
+pub fn generate(material: &Material, tablebase: &Tablebase) -> Table {
+    let indexer = Indexer::new(material);
+    let size = indexer.size();
+    let mut data = vec![UNRESOLVED; size];
+    // moves staying in the table, not known to lose yet
+    let mut count = vec![0u8; size];
+    // the best value of the moves leaving it
+    let mut outer = vec![NONE; size];
+    // what gets decided on every ply
+    let mut pending = vec![Vec::new(); MAX_PLIES as usize + 1];
+
+    for index in 0..size {
+        let position = match indexer.position(index) {
+            Some(ref p) if !is_illegal(p) => *p,
+            _ => {
+                data[index] = ILLEGAL;
+                continue;
+            }
+        };
+        let moves = position.legal_moves();
+        if moves.is_empty() {
+            if position.is_check() {
+                decide(&mut data, &mut pending, index, Value::Loss(0));
+            } else {
+                data[index] = DRAW;
+            }
+            continue;
+        }
+        let mut best = None;
+        for mv in moves {
+            if mv.promote == UNKNOWN && !position.is_capture(mv) {
+                count[index] += 1;
+                continue;
+            }
+            let next = position.make_move(mv);
+            let value = tablebase.probe_dtm(&next)
+                .unwrap_or_else(|| panic!("{} is missing", Material::of(&next.board)));
+            let value = match value {
+                Value::Loss(d) => Value::Win(d + 1),
+                Value::Win(d) => Value::Loss(d + 1),
+                Value::Draw => Value::Draw,
+            };
+            if best.map_or(true, |b| rank(value) > rank(b)) {
+                best = Some(value);
+            }
+        }
+        outer[index] = best.map_or(NONE, Value::encode);
+        match best {
+            None => {}
+            Some(Value::Draw) if count[index] == 0 => data[index] = DRAW,
+            Some(value @ Value::Loss(_)) if count[index] == 0 => {
+                decide(&mut data, &mut pending, index, value)
+            }
+            Some(Value::Win(d)) if d < MAX_PLIES => pending[d as usize].push(index as u32 | OUTER),
+            Some(_) => {}
+        }
+    }
+
+    for ply in 0..pending.len() {
+        let decided = ::std::mem::replace(&mut pending[ply], Vec::new());
+        for entry in decided {
+            let index = (entry & !OUTER) as usize;
+            if entry & OUTER != 0 {
+                if data[index] != UNRESOLVED {
+                    continue;
+                }
+                data[index] = Value::Win(ply as u8).encode();
+            }
+            let position = indexer.position(index).unwrap();
+            let lost = Value::decode(data[index]) == Some(Value::Loss(ply as u8));
+            for prev in predecessors(&position) {
+                let before = indexer.index(&prev);
+                if data[before] != UNRESOLVED {
+                    continue;
+                }
+                if lost {
+                    decide(&mut data, &mut pending, before, Value::Win(ply as u8 + 1));
+                    continue;
+                }
+                count[before] -= 1;
+                if count[before] > 0 {
+                    continue;
+                }
+                match Value::decode(outer[before]) {
+                    None => decide(&mut data, &mut pending, before, Value::Loss(ply as u8 + 1)),
+                    Some(Value::Loss(d)) => {
+                        let longest = cmp::max(ply as u8 + 1, d);
+                        decide(&mut data, &mut pending, before, Value::Loss(longest))
+                    }
+                    Some(Value::Draw) => data[before] = DRAW,
+                    // decided when its ply comes
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    for value in &mut data {
+        if *value == UNRESOLVED {
+            *value = DRAW;
+        }
+    }
+    Table {
+        material: material.clone(),
+        data: data,
+    }
+}
+
+fn decide(data: &mut [u8], pending: &mut [Vec<u32>], index: usize, value: Value) {
+    let plies = value.plies() as usize;
+    if plies < pending.len() {
+        data[index] = value.encode();
+        pending[plies].push(index as u32);
+    }
+}
+
+// Wins first, the sooner the better, then draws, then the longest losses
+pub fn rank(value: Value) -> (u8, i32) {
+    match value {
+        Value::Win(d) => (2, -(d as i32)),
+        Value::Draw => (1, 0),
+        Value::Loss(d) => (0, d as i32),
+    }
+}
+
+// The positions of the same material one move before: every piece of
+// the side that moved goes back to a square it could have come from,
+// pawns one or two squares, nothing is uncaptured or unpromoted.
+// Whether they are legal is up to the caller.
+fn predecessors(position: &Position) -> Vec<Position> {
+    let mover = position.active.invert();
+    let occupation = position.board.occupation();
+    let empty = !occupation;
+    let (back, double_push_rank): (fn(Mask) -> Mask, Mask) = if mover == Color::White {
+        (Mask::shift_south, _4)
+    } else {
+        (Mask::shift_north, _5)
+    };
+    let mut result = Vec::new();
+    for to in position.board.occupation_of(mover).single_bits() {
+        let piece = position.board.get_piece(to);
+        let origins = match piece.kind() {
+            PAWN => {
+                let single = back(to) & empty;
+                if to.intersects(double_push_rank) {
+                    single | back(single) & empty
+                } else {
+                    single
+                }
+            }
+            KNIGHT => to.knight_attacks(),
+            BISHOP => to.bishop_attacks(occupation),
+            ROOK => to.rook_attacks(occupation),
+            QUEEN => to.queen_attacks(occupation),
+            _ => to.king_attacks(),
+        } & empty;
+        for from in origins.single_bits() {
+            let mut prev = *position;
+            prev.active = mover;
+            prev.board.remove_piece(to);
+            prev.board.set_piece(from, piece);
+            result.push(prev);
+        }
+    }
+    result
+}
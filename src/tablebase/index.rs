@@ -0,0 +1,140 @@
+use bit_board::BitBoard;
+use color::Color;
+use mask::Mask;
+use mask::masks::*;
+use position::Position;
+use super::material::Material;
+
+// Maps positions of a given material to dense indexes:
+// side to move, the white king square within its region,
+// then a square per every other piece, in material order.
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Indexer {
+    material: Material,
+    king_region: Mask,
+}
+
+impl Indexer {
+    pub fn new(material: &Material) -> Self {
+        // Where the white king is kept by the symmetry reduction:
+        // with pawns on the board only the left-right mirror is allowed,
+        // without them the board can also be turned upside down.
+        let left = A | B | C | D;
+        Indexer {
+            material: material.clone(),
+            king_region: if material.has_pawns() {
+                left
+            } else {
+                left & (_1 | _2 | _3 | _4)
+            },
+        }
+    }
+    pub fn size(&self) -> usize {
+        2 * self.king_region.count() as usize * 64usize.pow(self.material.len() as u32 - 1)
+    }
+    pub fn canonical(&self, board: &BitBoard) -> BitBoard {
+        let mut board = *board;
+        let king = board.kings_of(Color::White);
+        if king.intersects(E | F | G | H) {
+            board = board.flip_horizontally();
+        }
+        if !self.material.has_pawns() && king.intersects(_5 | _6 | _7 | _8) {
+            board = board.flip_vertically();
+        }
+        board
+    }
+    // The position has to be of the indexer's material
+    pub fn index(&self, position: &Position) -> usize {
+        let board = self.canonical(&position.board);
+        let pieces = self.material.pieces();
+        let mut index = 0;
+        let mut i = pieces.len();
+        while i > 1 {
+            let piece = pieces[i - 1];
+            // same pieces go in a row, n-th of them gets n-th square
+            let mut first = i - 1;
+            while first > 1 && pieces[first - 1] == piece {
+                first -= 1;
+            }
+            let squares = board.pieces(piece).single_bit_indices().collect::<Vec<_>>();
+            for j in (first..i).rev() {
+                index = index * 64 + squares[j - first] as usize;
+            }
+            i = first;
+        }
+        let king = board.kings_of(Color::White);
+        let below = Mask::new(king.bits() - 1);
+        index = index * self.king_region.count() as usize +
+                (self.king_region & below).count() as usize;
+        index * 2 + if position.active == Color::White { 0 } else { 1 }
+    }
+    // None if pieces would share a square
+    pub fn position(&self, mut index: usize) -> Option<Position> {
+        let active = if index % 2 == 0 { Color::White } else { Color::Black };
+        index /= 2;
+        let region = self.king_region.count() as usize;
+        let king = self.king_region.single_bits().nth(index % region).unwrap();
+        index /= region;
+
+        let mut board = BitBoard::new();
+        let mut occupied = king;
+        let pieces = self.material.pieces();
+        board.set_piece(king, pieces[0]);
+        for &piece in &pieces[1..] {
+            let square = Mask::new(1 << (index % 64));
+            index /= 64;
+            if occupied.intersects(square) {
+                return None;
+            }
+            occupied |= square;
+            board.set_piece(square, piece);
+        }
+        Some(Position::new(board, active))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(material: &str, fen: &str) {
+        let indexer = Indexer::new(&Material::parse(material).unwrap());
+        let p = Position::parse(fen);
+        let index = indexer.index(&p);
+        assert!(index < indexer.size());
+        let back = indexer.position(index).unwrap();
+        assert_eq!(indexer.index(&back), index);
+        assert_eq!(back.board, indexer.canonical(&p.board));
+        assert_eq!(back.active, p.active);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(Indexer::new(&Material::parse("KQK").unwrap()).size(), 2 * 16 * 64 * 64);
+        assert_eq!(Indexer::new(&Material::parse("KPK").unwrap()).size(), 2 * 32 * 64 * 64);
+    }
+
+    #[test]
+    fn round_trip() {
+        check("KQK", "8/8/8/8/8/8/1Q6/K6k w - - 0 1");
+        check("KQK", "K7/8/8/8/8/8/1Q6/7k b - - 0 1");
+        check("KRKP", "7K/8/8/8/3p4/8/1R6/7k b - - 0 1");
+        check("KNNK", "7K/8/8/8/8/3N4/1N6/7k b - - 0 1");
+    }
+
+    #[test]
+    fn symmetric_positions_share_index() {
+        let indexer = Indexer::new(&Material::parse("KQK").unwrap());
+        let a = Position::parse("8/8/8/8/8/8/1Q6/K6k w - - 0 1");
+        let b = Position::parse("K6k/1Q6/8/8/8/8/8/8 w - - 0 1");
+        let c = Position::parse("8/8/8/8/8/8/6Q1/k6K w - - 0 1");
+        assert_eq!(indexer.index(&a), indexer.index(&b));
+        assert_eq!(indexer.index(&a), indexer.index(&c));
+    }
+
+    #[test]
+    fn collision() {
+        let indexer = Indexer::new(&Material::parse("KQK").unwrap());
+        assert_eq!(indexer.position(0), None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::{Display, Formatter, Result};
+use bit_board::BitBoard;
+use color::Color;
+use kind::*;
+use piece::*;
+
+// Pieces on the board, white first, kings first, then
+// from the most valuable to the least: "KRKP", "KBNK".
+#[derive(Eq, Clone, Debug, PartialEq, Hash)]
+pub struct Material(Vec<Piece>);
+
+fn order(p: &Piece) -> (u8, u8) {
+    let color = if p.color() == Color::White { 0 } else { 1 };
+    (color, KING.bits() - p.kind().bits())
+}
+
+impl Material {
+    pub fn new(mut pieces: Vec<Piece>) -> Self {
+        pieces.sort_by_key(order);
+        Material(pieces)
+    }
+    pub fn parse(input: &str) -> Option<Self> {
+        if !input.starts_with('K') {
+            return None;
+        }
+        match input[1..].find('K').map(|i| i + 1) {
+            Some(i) => {
+                let mut pieces = Vec::with_capacity(input.len());
+                for (n, c) in input.chars().enumerate() {
+                    let kind = match c {
+                        'K' | 'Q' | 'R' | 'B' | 'N' | 'P' => Kind::parse(c),
+                        _ => return None,
+                    };
+                    if kind == KING && n != 0 && n != i {
+                        return None;
+                    }
+                    let color = if n < i { Color::White } else { Color::Black };
+                    pieces.push(kind.of(color));
+                }
+                Some(Material::new(pieces))
+            }
+            _ => None,
+        }
+    }
+    pub fn of(board: &BitBoard) -> Self {
+        let mut pieces = Vec::new();
+        for piece in ALL_PIECES {
+            for _ in 0..board.pieces(piece).count() {
+                pieces.push(piece);
+            }
+        }
+        Material::new(pieces)
+    }
+    pub fn pieces(&self) -> &[Piece] {
+        &self.0
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn has_pawns(&self) -> bool {
+        self.0.iter().any(|p| p.kind() == PAWN)
+    }
+    pub fn is_kings_only(&self) -> bool {
+        self.0.iter().all(|p| p.kind() == KING)
+    }
+    pub fn swap(&self) -> Self {
+        Material::new(self.0
+            .iter()
+            .map(|p| p.kind().of(p.color().invert()))
+            .collect())
+    }
+    // Materials reachable with one capture and/or promotion
+    pub fn successors(&self) -> Vec<Material> {
+        let mut result = Vec::new();
+        for (i, &p) in self.0.iter().enumerate() {
+            if p.kind() == KING {
+                continue;
+            }
+            // p is captured
+            result.push(self.without(i));
+            if p.kind() != PAWN {
+                continue;
+            }
+            for kind in &[QUEEN, ROOK, BISHOP, KNIGHT] {
+                let promoted = self.replaced(i, kind.of(p.color()));
+                // promotion with and without capture
+                for (j, &q) in promoted.0.iter().enumerate() {
+                    if q.color() != p.color() && q.kind() != KING {
+                        result.push(promoted.without(j));
+                    }
+                }
+                result.push(promoted);
+            }
+        }
+        result.sort_by_key(|m| m.to_string());
+        result.dedup();
+        result
+    }
+    fn without(&self, i: usize) -> Self {
+        let mut pieces = self.0.clone();
+        pieces.remove(i);
+        Material::new(pieces)
+    }
+    fn replaced(&self, i: usize, piece: Piece) -> Self {
+        let mut pieces = self.0.clone();
+        pieces[i] = piece;
+        Material::new(pieces)
+    }
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        for p in &self.0 {
+            write!(f, "{}", p.kind())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::*;
+
+    #[test]
+    fn parse_display() {
+        for s in &["KQK", "KRKP", "KBNK", "KK", "KPKP"] {
+            assert_eq!(Material::parse(s).unwrap().to_string(), *s);
+        }
+        assert_eq!(Material::parse("KNBK").unwrap().to_string(), "KBNK");
+        assert_eq!(Material::parse("QKK"), None);
+        assert_eq!(Material::parse("KQ"), None);
+        assert_eq!(Material::parse("KXK"), None);
+        assert_eq!(Material::parse(""), None);
+    }
+
+    #[test]
+    fn swap() {
+        assert_eq!(Material::parse("KRKP").unwrap().swap().to_string(), "KPKR");
+    }
+
+    #[test]
+    fn of_board() {
+        let b = BitBoard::parse("8/8/8/8/8/8/1r6/K1Bk4");
+        assert_eq!(Material::of(&b).to_string(), "KBKR");
+    }
+
+    #[test]
+    fn successors() {
+        let m = Material::parse("KPKR").unwrap();
+        assert_eq!(m.successors().iter().map(|m| m.to_string()).join(" "),
+                   "KBK KBKR KKR KNK KNKR KPK KQK KQKR KRK KRKR");
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use castle;
+use position::Position;
+
+pub use self::material::Material;
+use self::index::Indexer;
+
+pub mod material;
+pub mod index;
+mod generate;
+pub mod syzygy;
+
+// Every position takes one byte: 0 is a draw, 255 an illegal
+// position, everything else is distance to mate in plies plus one.
+// Odd distances are wins for the side to move, even are losses.
+const DRAW: u8 = 0;
+const ILLEGAL: u8 = 255;
+const UNRESOLVED: u8 = 254;
+const MAX_PLIES: u8 = 253;
+
+static MAGIC: &'static [u8; 4] = b"CTB1";
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq, Hash)]
+pub enum Wdl {
+    Loss,
+    Draw,
+    Win,
+}
+
+// From the point of view of the side to move,
+// distances are in plies.
+#[derive(Eq, Copy, Clone, Debug, PartialEq, Hash)]
+pub enum Value {
+    Loss(u8),
+    Draw,
+    Win(u8),
+}
+
+impl Value {
+    pub fn wdl(self) -> Wdl {
+        match self {
+            Value::Loss(_) => Wdl::Loss,
+            Value::Draw => Wdl::Draw,
+            Value::Win(_) => Wdl::Win,
+        }
+    }
+    pub fn plies(self) -> u8 {
+        match self {
+            Value::Loss(d) | Value::Win(d) => d,
+            Value::Draw => 0,
+        }
+    }
+    fn encode(self) -> u8 {
+        match self {
+            Value::Draw => DRAW,
+            Value::Loss(d) | Value::Win(d) => d + 1,
+        }
+    }
+    fn decode(bits: u8) -> Option<Value> {
+        match bits {
+            DRAW => Some(Value::Draw),
+            ILLEGAL | UNRESOLVED => None,
+            _ if bits % 2 == 0 => Some(Value::Win(bits - 1)),
+            _ => Some(Value::Loss(bits - 1)),
+        }
+    }
+}
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Table {
+    material: Material,
+    data: Vec<u8>,
+}
+
+impl Table {
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+    // The position has to be of the table's material
+    pub fn probe(&self, position: &Position) -> Option<Value> {
+        let index = Indexer::new(&self.material).index(position);
+        Value::decode(self.data[index])
+    }
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let name = self.material.to_string();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[name.len() as u8])?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&self.data)
+    }
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() < 5 || &bytes[0..4] != MAGIC {
+            return Err(invalid("not a tablebase file"));
+        }
+        let end = 5 + bytes[4] as usize;
+        if bytes.len() < end {
+            return Err(invalid("truncated file"));
+        }
+        let material = ::std::str::from_utf8(&bytes[5..end])
+            .ok()
+            .and_then(Material::parse)
+            .ok_or_else(|| invalid("bad material"))?;
+        if bytes.len().checked_sub(end) != Some(Indexer::new(&material).size()) {
+            return Err(invalid("wrong table size"));
+        }
+        Ok(Table {
+            material: material,
+            data: bytes[end..].to_vec(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Tablebase {
+    tables: HashMap<Material, Table>,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Tablebase { tables: HashMap::new() }
+    }
+    pub fn add(&mut self, table: Table) {
+        self.tables.insert(table.material.clone(), table);
+    }
+    pub fn contains(&self, material: &Material) -> bool {
+        material.is_kings_only() || self.tables.contains_key(material) ||
+        self.tables.contains_key(&material.swap())
+    }
+    // Generates the table along with every table it converts into
+    pub fn generate(&mut self, material: &Material) {
+        if self.contains(material) {
+            return;
+        }
+        for successor in material.successors() {
+            self.generate(&successor);
+        }
+        let table = generate::generate(material, self);
+        self.add(table);
+    }
+    pub fn probe_dtm(&self, position: &Position) -> Option<Value> {
+        if position.available != castle::NONE {
+            return None;
+        }
+        let material = Material::of(&position.board);
+        if material.is_kings_only() {
+            return Some(Value::Draw);
+        }
+        if let Some(table) = self.tables.get(&material) {
+            return table.probe(position);
+        }
+        self.tables.get(&material.swap()).and_then(|table| {
+            let mut swapped = *position;
+            swapped.board = position.board.swap_colors();
+            swapped.active = position.active.invert();
+            table.probe(&swapped)
+        })
+    }
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        self.probe_dtm(position).map(Value::wdl)
+    }
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut result = Tablebase::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |e| e == "ctb") {
+                result.add(Table::read(&mut fs::File::open(path)?)?);
+            }
+        }
+        Ok(result)
+    }
+    pub fn save_dir<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        for (material, table) in &self.tables {
+            let path = dir.as_ref().join(format!("{}.ctb", material));
+            table.write(&mut fs::File::create(path)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use moves::Move;
+
+    fn generated(materials: &[&str]) -> Tablebase {
+        let mut tb = Tablebase::new();
+        for m in materials {
+            tb.generate(&Material::parse(m).unwrap());
+        }
+        tb
+    }
+
+    fn dtm(tb: &Tablebase, fen: &str) -> Option<Value> {
+        tb.probe_dtm(&Position::parse(fen))
+    }
+
+    #[test]
+    fn value_encoding() {
+        for v in &[Value::Draw, Value::Loss(0), Value::Win(1), Value::Loss(20), Value::Win(19)] {
+            assert_eq!(Value::decode(v.encode()), Some(*v));
+        }
+        assert_eq!(Value::decode(ILLEGAL), None);
+    }
+
+    // generating takes a while, so the tables are shared:
+    // KPK needs KQK for its promotions
+    #[test]
+    fn three_pieces() {
+        let tb = generated(&["KPK"]);
+        probe(&tb);
+        castling_is_not_probed(&tb);
+        read_write_round_trip(&tb);
+        pawns(&tb);
+        consistent(&tb, "KPK", 1);
+    }
+
+    // Minutes without optimizations: cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn four_pieces() {
+        let tb = generated(&["KBNK"]);
+        // the longest mate with bishop and knight is in 33
+        let longest = tb.tables[&Material::parse("KBNK").unwrap()]
+            .data
+            .iter()
+            .filter_map(|&v| Value::decode(v))
+            .map(Value::plies)
+            .max();
+        assert_eq!(longest, Some(66));
+        assert_eq!(dtm(&tb, "7k/7B/6K1/4N3/8/8/8/8 w - - 0 1"), Some(Value::Win(1)));
+        // the knight hangs
+        assert_eq!(dtm(&tb, "8/8/8/8/8/8/1kN5/B6K b - - 0 1"), Some(Value::Draw));
+        consistent(&tb, "KBNK", 101);
+    }
+
+    fn probe(tb: &Tablebase) {
+        // mated
+        assert_eq!(dtm(tb, "k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(Value::Loss(0)));
+        // mate in one
+        assert_eq!(dtm(tb, "k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Some(Value::Win(1)));
+        // stalemate
+        assert_eq!(dtm(tb, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Value::Draw));
+        // the queen hangs
+        assert_eq!(dtm(tb, "8/8/8/3kQ3/8/8/8/K7 b - - 0 1"), Some(Value::Draw));
+        // the side not to move is in check
+        assert_eq!(dtm(tb, "k7/1Q6/1K6/8/8/8/8/8 w - - 0 1"), None);
+        // the colors swapped
+        assert_eq!(dtm(tb, "8/7q/8/8/8/1k6/8/K7 b - - 0 1"), Some(Value::Win(1)));
+        assert_eq!(tb.probe_wdl(&Position::parse("8/8/8/3k4/8/8/8/KQ6 w - - 0 1")),
+                   Some(Wdl::Win));
+        // the longest win is mate in 10
+        let longest = tb.tables[&Material::parse("KQK").unwrap()]
+            .data
+            .iter()
+            .filter_map(|&v| Value::decode(v))
+            .map(Value::plies)
+            .max();
+        assert_eq!(longest, Some(20));
+    }
+
+    fn pawns(tb: &Tablebase) {
+        // the king in front of its pawn on the sixth rank wins
+        assert_eq!(tb.probe_wdl(&Position::parse("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")),
+                   Some(Wdl::Win));
+        assert_eq!(tb.probe_wdl(&Position::parse("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")),
+                   Some(Wdl::Loss));
+        // not with a rook pawn and the king in the corner
+        assert_eq!(dtm(tb, "k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(Value::Draw));
+        assert_eq!(dtm(tb, "k7/P7/1K6/8/8/8/8/8 b - - 0 1"), Some(Value::Draw));
+        // promoting converts into KQK
+        let p = Position::parse("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+        let promoted = p.make_move(Move::parse("e7e8=Q"));
+        match (tb.probe_dtm(&p), tb.probe_dtm(&promoted)) {
+            (Some(Value::Win(d)), Some(Value::Loss(l))) => assert_eq!(d, l + 1),
+            other => panic!("{:?}", other),
+        }
+        // the colors swapped
+        assert_eq!(tb.probe_wdl(&Position::parse("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1")),
+                   Some(Wdl::Win));
+    }
+
+    // Every n-th position has the value its best move gives
+    fn consistent(tb: &Tablebase, material: &str, n: usize) {
+        let indexer = Indexer::new(&Material::parse(material).unwrap());
+        for index in (0..indexer.size()).filter(|i| i % n == 0) {
+            let p = match indexer.position(index) {
+                Some(p) => p,
+                None => continue,
+            };
+            let value = match tb.probe_dtm(&p) {
+                Some(v) => v,
+                None => continue,
+            };
+            let moves = p.legal_moves();
+            let best = moves.iter()
+                .map(|&mv| match tb.probe_dtm(&p.make_move(mv)).unwrap() {
+                    Value::Loss(d) => Value::Win(d + 1),
+                    Value::Win(d) => Value::Loss(d + 1),
+                    Value::Draw => Value::Draw,
+                })
+                .max_by_key(|&v| generate::rank(v));
+            let expected = match best {
+                Some(v) => v,
+                None if p.is_check() => Value::Loss(0),
+                None => Value::Draw,
+            };
+            assert_eq!(value, expected, "{}", p);
+        }
+    }
+
+    fn castling_is_not_probed(tb: &Tablebase) {
+        assert_eq!(dtm(tb, "8/8/8/3k4/8/8/8/4K2Q w K - 0 1"), None);
+    }
+
+    fn read_write_round_trip(tb: &Tablebase) {
+        let table = &tb.tables[&Material::parse("KQK").unwrap()];
+        let mut bytes = Vec::new();
+        table.write(&mut bytes).unwrap();
+        assert_eq!(&Table::read(&mut bytes.as_slice()).unwrap(), table);
+        assert!(Table::read(&mut &bytes[1..]).is_err());
+        // cut in the name and in the data
+        assert!(Table::read(&mut &bytes[..8]).is_err());
+        assert!(Table::read(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(200);
+        bytes.extend_from_slice(b"KQK");
+        let error = Table::read(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,172 @@
+// The tables that turn piece squares into table indexes the way the
+// Syzygy generator does. Squares here run from a1 = 0 to h8 = 63.
+
+pub fn rank(square: usize) -> usize {
+    square >> 3
+}
+
+pub fn file(square: usize) -> usize {
+    square & 7
+}
+
+// negative below the a1-h8 diagonal, positive above
+pub fn off_diagonal(square: usize) -> i32 {
+    rank(square) as i32 - file(square) as i32
+}
+
+pub fn flip_diagonal(square: usize) -> usize {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+#[derive(Clone, Debug)]
+pub struct Encoding {
+    // [k][n]: the ways to choose k of n
+    pub binomial: Vec<[u64; 64]>,
+    // a2-h7 to 0..47, nearer to the edge and lower is more: the
+    // pawn with the most leads
+    pub map_pawns: [usize; 64],
+    // [leading pawns][square of the first of them]
+    pub lead_pawn_idx: Vec<[u64; 64]>,
+    // [leading pawns][file a..d]
+    pub lead_pawns_size: Vec<[u64; 4]>,
+    // the squares below the a1-h8 diagonal to 0..27
+    pub map_b1h1h7: [usize; 64],
+    // the a1-d1-d4 triangle to 0..9, the diagonal last
+    pub map_a1d1d4: [usize; 64],
+    // [map_a1d1d4 of the first king][square of the other]: the 462
+    // placements of two kings
+    pub map_kk: Vec<[usize; 64]>,
+}
+
+impl Encoding {
+    pub fn new() -> Self {
+        let mut binomial = vec![[0; 64]; 6];
+        binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6 {
+                if k > n {
+                    break;
+                }
+                binomial[k][n] = if k > 0 { binomial[k - 1][n - 1] } else { 0 } +
+                                 if k < n { binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut map_b1h1h7 = [0; 64];
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut map_a1d1d4 = [0; 64];
+        let mut diagonal = vec![];
+        let mut code = 0;
+        for square in 0..28 {
+            if off_diagonal(square) < 0 && file(square) <= 3 {
+                map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && file(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // with the first king on the diagonal the other one is never
+        // above it, and placements with both on it come last
+        let mut map_kk = vec![[0; 64]; 10];
+        let mut both_on_diagonal = vec![];
+        let mut code = 0;
+        for i in 0..10 {
+            // b1 is the only square of the triangle mapped to 0
+            for first in (0..28).filter(|&s| map_a1d1d4[s] == i && (i > 0 || s == 1)) {
+                for second in 0..64 {
+                    let near = (rank(first) as i32 - rank(second) as i32).abs() <= 1 &&
+                               (file(first) as i32 - file(second) as i32).abs() <= 1;
+                    if near || off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((i, second));
+                    } else {
+                        map_kk[i][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (i, second) in both_on_diagonal {
+            map_kk[i][second] = code;
+            code += 1;
+        }
+
+        let mut map_pawns = [0; 64];
+        let mut lead_pawn_idx = vec![[0; 64]; 6];
+        let mut lead_pawns_size = vec![[0; 4]; 6];
+        let mut next = 48;
+        for leading in 1..6 {
+            for f in 0..4 {
+                let mut index = 0;
+                for r in 1..7 {
+                    let square = r * 8 + f;
+                    if leading == 1 {
+                        next -= 1;
+                        map_pawns[square] = next;
+                        next -= 1;
+                        map_pawns[square ^ 7] = next;
+                    }
+                    lead_pawn_idx[leading][square] = index;
+                    index += binomial[leading - 1][map_pawns[square]];
+                }
+                lead_pawns_size[leading][f] = index;
+            }
+        }
+
+        Encoding {
+            binomial: binomial,
+            map_pawns: map_pawns,
+            lead_pawn_idx: lead_pawn_idx,
+            lead_pawns_size: lead_pawns_size,
+            map_b1h1h7: map_b1h1h7,
+            map_a1d1d4: map_a1d1d4,
+            map_kk: map_kk,
+        }
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tables() {
+        let e = Encoding::new();
+        assert_eq!(e.binomial[2][5], 10);
+        assert_eq!(e.binomial[5][63], 7028847);
+        // b1 c1 d1 c2 d2 d3, then a1 b2 c3 d4
+        let triangle = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+        for (code, &square) in triangle.iter().enumerate() {
+            assert_eq!(e.map_a1d1d4[square], code);
+        }
+        assert_eq!(e.map_b1h1h7[1], 0);
+        assert_eq!(e.map_b1h1h7[55], 27);
+        let kk: Vec<usize> = e.map_kk.iter().flat_map(|row| row.iter().cloned()).collect();
+        assert_eq!(kk.iter().max(), Some(&461));
+        // a2 leads, then h2, a3, h3 ...
+        assert_eq!((e.map_pawns[8], e.map_pawns[15], e.map_pawns[16]), (47, 46, 45));
+        assert_eq!((e.map_pawns[51], e.map_pawns[52]), (1, 0));
+        assert_eq!(e.lead_pawns_size[1], [6, 6, 6, 6]);
+        assert_eq!(e.lead_pawn_idx[2][16], 47);
+    }
+}
@@ -0,0 +1,710 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use bit_board::BitBoard;
+use castle;
+use color::Color;
+use kind::*;
+use moves::Move;
+use position::Position;
+use rules::Rules;
+use self::encoding::Encoding;
+use self::table::{Info, Table};
+
+mod encoding;
+mod table;
+#[cfg(test)]
+mod write;
+
+// Probing the Syzygy tables, `.rtbw` for win/draw/loss and `.rtbz` for
+// the distance to the next capture or pawn move. The tables leave out
+// positions where the side to move can capture, and often store only
+// one side to move for DTZ, so a probe searches captures (and pawn
+// moves) on top of the table, which also takes care of en passant.
+// Standard chess only, without castling rights.
+
+// The fifty-move rule turns some wins into cursed ones, won only
+// without it, and some losses into blessed ones.
+#[derive(Eq, Copy, Clone, Debug, PartialEq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+    // -2 for a loss to 2 for a win
+    pub fn value(self) -> i32 {
+        self as i32 - 2
+    }
+    pub fn invert(self) -> Self {
+        Wdl::from_value(-self.value()).unwrap()
+    }
+}
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub enum SyzygyError {
+    // castling rights, variants and broken positions are not in the tables
+    Unsupported,
+    // the file the probe needs, "KRvKP.rtbw"
+    Missing(String),
+    Io(io::ErrorKind),
+    Corrupt(String),
+}
+
+impl From<io::Error> for SyzygyError {
+    fn from(e: io::Error) -> Self {
+        SyzygyError::Io(e.kind())
+    }
+}
+
+#[derive(Debug)]
+pub struct Syzygy {
+    dir: PathBuf,
+    files: HashSet<String>,
+    encoding: Encoding,
+    // read on first use, a full set runs into gigabytes
+    tables: RefCell<HashMap<String, Rc<Table>>>,
+}
+
+// "KRP", kings first, then from the most valuable to the least
+fn side_name(board: &BitBoard, color: Color) -> String {
+    let mut name = String::new();
+    for &kind in &[KING, QUEEN, ROOK, BISHOP, KNIGHT, PAWN] {
+        for _ in 0..board.pieces(kind.of(color)).count() {
+            name.push(kind.char());
+        }
+    }
+    name
+}
+
+fn is_pawn_move(position: &Position, mv: Move) -> bool {
+    mv.castle == castle::NONE && position.board.pawns_of(position.active).intersects(mv.from.mask())
+}
+
+fn is_zeroing(position: &Position, mv: Move) -> bool {
+    position.is_capture(mv) || is_pawn_move(position, mv)
+}
+
+// What a capture or pawn move leaves for the side making it
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+impl Syzygy {
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut files = HashSet::new();
+        for entry in fs::read_dir(&dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".rtbw") || name.ends_with(".rtbz") {
+                files.insert(name);
+            }
+        }
+        Ok(Syzygy {
+            dir: dir.as_ref().to_path_buf(),
+            files: files,
+            encoding: Encoding::new(),
+            tables: RefCell::new(HashMap::new()),
+        })
+    }
+    // For the side to move
+    pub fn probe_wdl(&self, position: &Position) -> Result<Wdl, SyzygyError> {
+        check(position)?;
+        Ok(self.search(position, false)?.0)
+    }
+    // Plies to the next capture or pawn move that keeps the result,
+    // for the side to move: positive when winning, negative when
+    // losing, 0 for a draw; beyond 100 either way for cursed wins and
+    // blessed losses. Tables storing moves rather than plies can make
+    // it one ply longer than it is.
+    pub fn probe_dtz(&self, position: &Position) -> Result<i32, SyzygyError> {
+        check(position)?;
+        self.dtz(position)
+    }
+    // The result under the fifty-move rule `halfmove_clock` plies after
+    // the last capture or pawn move: wins and losses that do not get to
+    // the next one in time are draws. Right at the limit a DTZ one ply
+    // too long can make a win or a loss come out as a draw.
+    pub fn probe_outcome(&self,
+                         position: &Position,
+                         halfmove_clock: u32)
+                         -> Result<super::Wdl, SyzygyError> {
+        let dtz = self.probe_dtz(position)?;
+        Ok(if dtz == 0 || dtz.abs() as u32 + halfmove_clock > 100 {
+            super::Wdl::Draw
+        } else if dtz > 0 {
+            super::Wdl::Win
+        } else {
+            super::Wdl::Loss
+        })
+    }
+
+    // The value and whether a capture or, with `pawn_moves`, a pawn move
+    // gets it. The tables do not care what they hold for positions
+    // with a winning capture, so captures go first.
+    fn search(&self, position: &Position, pawn_moves: bool) -> Result<(Wdl, bool), SyzygyError> {
+        let moves = position.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in &moves {
+            if !(position.is_capture(mv) || pawn_moves && is_pawn_move(position, mv)) {
+                continue;
+            }
+            searched += 1;
+            let value = self.search(&position.make_move(mv), false)?.0.invert();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Ok((value, true));
+                }
+            }
+        }
+        let all = searched > 0 && searched == moves.len();
+        let value = if all { best } else { self.wdl_table(position)? };
+        if best >= value {
+            Ok((best, best > Wdl::Draw || all))
+        } else {
+            Ok((value, false))
+        }
+    }
+    fn dtz(&self, position: &Position) -> Result<i32, SyzygyError> {
+        let (wdl, zeroing) = self.search(position, true)?;
+        if wdl == Wdl::Draw {
+            return Ok(0);
+        }
+        if zeroing {
+            return Ok(dtz_before_zeroing(wdl));
+        }
+        if let Some(dtz) = self.dtz_table(position, wdl)? {
+            let cursed = wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss;
+            return Ok((dtz + if cursed { 100 } else { 0 }) * wdl.value().signum());
+        }
+
+        // the other side to move is stored: one ply further
+        let mut best = None;
+        for mv in position.legal_moves() {
+            let zeroing = is_zeroing(position, mv);
+            let next = position.make_move(mv);
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&next, false)?.0)
+            } else {
+                -self.dtz(&next)?
+            };
+            if dtz == 1 && next.is_checkmate() {
+                best = Some(1);
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz.signum() == wdl.value().signum() && best.map_or(true, |b| dtz < b) {
+                best = Some(dtz);
+            }
+        }
+        // mated
+        Ok(best.unwrap_or(-1))
+    }
+    fn wdl_table(&self, position: &Position) -> Result<Wdl, SyzygyError> {
+        let (name, table, flip) = match self.find(position, false)? {
+            Some(found) => found,
+            None => return Ok(Wdl::Draw),
+        };
+        let placement = table.place(&self.encoding, position, flip);
+        table.index(&self.encoding, &placement)
+            .and_then(|idx| table.decompress(table.pairs(&placement), idx))
+            .and_then(|value| Wdl::from_value(value as i32 - 2))
+            .ok_or(SyzygyError::Corrupt(name))
+    }
+    // None when the table has the other side to move
+    fn dtz_table(&self, position: &Position, wdl: Wdl) -> Result<Option<i32>, SyzygyError> {
+        let (name, table, flip) = match self.find(position, true)? {
+            Some(found) => found,
+            None => return Ok(Some(0)),
+        };
+        let placement = table.place(&self.encoding, position, flip);
+        if !table.has_side(&placement) {
+            return Ok(None);
+        }
+        table.index(&self.encoding, &placement)
+            .and_then(|idx| table.decompress(table.pairs(&placement), idx))
+            .and_then(|value| table.map_score(&placement, value, wdl.value()))
+            .map(Some)
+            .ok_or(SyzygyError::Corrupt(name))
+    }
+    // The table of the position's material, with whether it has the
+    // colors the other way; None for the bare kings
+    fn find(&self,
+            position: &Position,
+            dtz: bool)
+            -> Result<Option<(String, Rc<Table>, bool)>, SyzygyError> {
+        let white = side_name(&position.board, Color::White);
+        let black = side_name(&position.board, Color::Black);
+        if white == "K" && black == "K" {
+            return Ok(None);
+        }
+        let extension = if dtz { "rtbz" } else { "rtbw" };
+        let name = format!("{}v{}.{}", white, black, extension);
+        if self.files.contains(&name) {
+            return self.table(&name, dtz).map(|t| Some((name, t, false)));
+        }
+        let swapped = format!("{}v{}.{}", black, white, extension);
+        if self.files.contains(&swapped) {
+            return self.table(&swapped, dtz).map(|t| Some((swapped, t, true)));
+        }
+        Err(SyzygyError::Missing(name))
+    }
+    fn table(&self, name: &str, dtz: bool) -> Result<Rc<Table>, SyzygyError> {
+        if let Some(table) = self.tables.borrow().get(name) {
+            return Ok(table.clone());
+        }
+        let corrupt = || SyzygyError::Corrupt(name.to_string());
+        let info = Info::parse(&name[..name.len() - 5]).ok_or_else(&corrupt)?;
+        let bytes = fs::read(self.dir.join(name))?;
+        let table = Rc::new(Table::parse(info, dtz, bytes, &self.encoding).ok_or_else(&corrupt)?);
+        self.tables.borrow_mut().insert(name.to_string(), table.clone());
+        Ok(table)
+    }
+}
+
+fn check(position: &Position) -> Result<(), SyzygyError> {
+    let waiting = Position { active: position.active.invert(), ..*position };
+    match position.rules {
+        Rules::Standard if position.available == castle::NONE &&
+                           position.validate().is_empty() &&
+                           !waiting.is_check() => Ok(()),
+        _ => Err(SyzygyError::Unsupported),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::process;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use mask::Mask;
+    use piece::*;
+    use std::cmp;
+    use tablebase::{self, Material, Tablebase, Value};
+    use tablebase::index::Indexer;
+    use super::table::MAPPED;
+    use super::write::{self, Side};
+
+    // The fixtures are written by the tests, with the values of our own
+    // generator where it has them; `real_tables` checks the reader
+    // against the real files when it is given some.
+
+    // A directory of tables, removed when dropped. Tables are read on
+    // first use, so it has to outlive the probes.
+    struct Fixture(PathBuf);
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn dir(name: &str, tables: &[(&str, Vec<u8>)]) -> Fixture {
+        let dir = env::temp_dir().join(format!("syzygy-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for &(file, ref bytes) in tables {
+            fs::write(dir.join(file), bytes).unwrap();
+        }
+        Fixture(dir)
+    }
+
+    // every board with the pieces on different squares
+    fn boards(pieces: &[Piece]) -> Vec<BitBoard> {
+        let mut result = vec![BitBoard::new()];
+        for &piece in pieces {
+            result = result.into_iter()
+                .flat_map(|board| {
+                    (0..64).map(|i| Mask::new(1 << i)).filter_map(move |m| {
+                        if board.occupation().intersects(m) {
+                            return None;
+                        }
+                        let mut board = board;
+                        board.set_piece(m, piece);
+                        Some(board)
+                    })
+                })
+                .collect();
+        }
+        result
+    }
+
+    fn positions(pieces: &[Piece]) -> Vec<Position> {
+        let mut result = vec![];
+        for board in boards(pieces) {
+            for &color in &[Color::White, Color::Black] {
+                let p = Position::new(board, color);
+                if check(&p).is_ok() {
+                    result.push(p);
+                }
+            }
+        }
+        result
+    }
+
+    // The stored values by file and side to move, positions that share
+    // an index have to share the value
+    fn fill<F>(table: &Table,
+               e: &Encoding,
+               positions: &[Position],
+               value: F)
+               -> HashMap<(usize, usize), Vec<u16>>
+        where F: Fn(&Position) -> Option<u16>
+    {
+        let mut stored: HashMap<(usize, usize), Vec<Option<u16>>> = HashMap::new();
+        for p in positions {
+            let v = match value(p) {
+                Some(v) => v,
+                None => continue,
+            };
+            let placement = table.place(e, p, false);
+            let size = table.pairs(&placement).size() as usize;
+            let idx = table.index(e, &placement).unwrap() as usize;
+            let values = stored.entry((placement.file, placement.stm))
+                .or_insert_with(|| vec![None; size]);
+            assert!(values[idx].map_or(true, |w| w == v), "{}", p);
+            values[idx] = Some(v);
+        }
+        stored.into_iter()
+            .map(|(k, values)| (k, values.into_iter().map(|v| v.unwrap_or(0)).collect()))
+            .collect()
+    }
+
+    fn side(flags: u8, values: Vec<u16>) -> Side {
+        Side {
+            flags: flags,
+            values: values,
+            map: Default::default(),
+        }
+    }
+
+    fn header(name: &str, dtz: bool, e: &Encoding) -> (Info, Table) {
+        let info = Info::parse(name).unwrap();
+        let (table, _) = Table::header(info.clone(), dtz, write::header(&info, dtz), e).unwrap();
+        (info, table)
+    }
+
+    fn position(fen: &str) -> Position {
+        Position::parse(fen)
+    }
+
+    #[test]
+    fn wdl_values() {
+        assert_eq!(Wdl::CursedWin.invert(), Wdl::BlessedLoss);
+        assert_eq!(Wdl::Loss.value(), -2);
+        assert!(Wdl::BlessedLoss > Wdl::Loss && Wdl::CursedWin < Wdl::Win);
+        assert_eq!(Wdl::from_value(3), None);
+    }
+
+    // one generation for all the checks, it takes a while
+    #[test]
+    fn king_and_queen() {
+        let mut dtm = Tablebase::new();
+        dtm.generate(&Material::parse("KQK").unwrap());
+        let e = Encoding::new();
+        let all = positions(&[WHITE_KING, WHITE_QUEEN, BLACK_KING]);
+
+        let (info, wdl) = header("KQvK", false, &e);
+        let values = fill(&wdl, &e, &all, |p| {
+            dtm.probe_dtm(p).map(|v| match v.wdl() {
+                tablebase::Wdl::Win => 4,
+                // with the queen hanging the table may hold anything
+                tablebase::Wdl::Draw if p.legal_moves().iter().any(|&mv| p.is_capture(mv)) => 0,
+                tablebase::Wdl::Draw => 2,
+                tablebase::Wdl::Loss => 0,
+            })
+        });
+        let sides = [side(0, values[&(0, 0)].clone()), side(0, values[&(0, 1)].clone())];
+        let rtbw = write::write(&info, false, &sides);
+
+        // white to move only, in moves, through the map
+        let (_, dtz) = header("KQvK", true, &e);
+        let moves = |p: &Position| if p.active == Color::White {
+            dtm.probe_dtm(p).map(|v| (v.plies() as u16 - 1) / 2)
+        } else {
+            None
+        };
+        let values = fill(&dtz, &e, &all, moves);
+        let mut map: Vec<u16> = values[&(0, 0)].clone();
+        map.sort();
+        map.dedup();
+        let symbols = values[&(0, 0)].iter().map(|v| map.binary_search(v).unwrap() as u16);
+        let mut white = side(MAPPED, symbols.collect());
+        white.map[0] = map.iter().map(|&m| m as u8).collect();
+        let rtbz = write::write(&info, true, &[white]);
+
+        let fixture = dir("kqvk", &[("KQvK.rtbw", rtbw), ("KQvK.rtbz", rtbz)]);
+        let tb = Syzygy::open(&fixture.0).unwrap();
+        for (n, p) in all.iter().enumerate().filter(|&(n, _)| n % 13 == 0) {
+            let v = dtm.probe_dtm(p).unwrap();
+            let (wdl, dtz) = match v {
+                Value::Win(d) => (Wdl::Win, d as i32),
+                Value::Draw => (Wdl::Draw, 0),
+                Value::Loss(0) => (Wdl::Loss, -1),
+                Value::Loss(d) => (Wdl::Loss, -(d as i32)),
+            };
+            assert_eq!(tb.probe_wdl(p), Ok(wdl), "{}", p);
+            if n % 130 == 0 {
+                assert_eq!(tb.probe_dtz(p), Ok(dtz), "{}", p);
+            }
+        }
+        probe(&tb);
+        let longest = |value| all.iter().find(|p| dtm.probe_dtm(p) == Some(value)).unwrap();
+        fifty_moves(&tb, longest(Value::Win(19)), longest(Value::Loss(20)));
+    }
+
+    fn probe(tb: &Syzygy) {
+        let wdl = |fen| tb.probe_wdl(&position(fen));
+        let dtz = |fen| tb.probe_dtz(&position(fen));
+        // mated
+        assert_eq!(wdl("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Ok(Wdl::Loss));
+        assert_eq!(dtz("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Ok(-1));
+        // mate in one
+        assert_eq!(dtz("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Ok(1));
+        // stalemate
+        assert_eq!(dtz("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Ok(0));
+        // the queen hangs, whatever the table says
+        assert_eq!(wdl("8/8/8/3kQ3/8/8/8/K7 b - - 0 1"), Ok(Wdl::Draw));
+        assert_eq!(dtz("8/8/8/3kQ3/8/8/8/K7 b - - 0 1"), Ok(0));
+        // the colors swapped
+        assert_eq!(dtz("8/7q/8/8/8/1k6/8/K7 b - - 0 1"), Ok(1));
+        assert_eq!(dtz("8/8/8/8/8/1k6/1q6/K7 w - - 0 1"), Ok(-1));
+        assert_eq!(wdl("8/8/8/8/8/8/8/K1k5 w - - 0 1"), Ok(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/4K2Q w K - 0 1"), Err(SyzygyError::Unsupported));
+        // the side not to move is in check
+        assert_eq!(wdl("k7/1Q6/1K6/8/8/8/8/8 w - - 0 1"), Err(SyzygyError::Unsupported));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/KR6 w - - 0 1"),
+                   Err(SyzygyError::Missing("KRvK.rtbw".to_string())));
+    }
+
+    // mate in 10 takes all of 19 plies
+    fn fifty_moves(tb: &Syzygy, win: &Position, loss: &Position) {
+        assert_eq!(tb.probe_dtz(win), Ok(19));
+        assert_eq!(tb.probe_outcome(win, 81), Ok(tablebase::Wdl::Win));
+        assert_eq!(tb.probe_outcome(win, 82), Ok(tablebase::Wdl::Draw));
+        assert_eq!(tb.probe_dtz(loss), Ok(-20));
+        assert_eq!(tb.probe_outcome(loss, 80), Ok(tablebase::Wdl::Loss));
+        assert_eq!(tb.probe_outcome(loss, 81), Ok(tablebase::Wdl::Draw));
+    }
+
+    // The real KQvK, KRvK and KPvK files against our own generator:
+    //
+    //     SYZYGY_PATH=/path/to/tables cargo test real_tables
+    //
+    // Nothing to check without them.
+    #[test]
+    fn real_tables() {
+        let path = match env::var_os("SYZYGY_PATH") {
+            Some(path) => PathBuf::from(path),
+            None => return,
+        };
+        let tb = Syzygy::open(path).unwrap();
+        let mut dtm = Tablebase::new();
+        for name in &["KQK", "KRK", "KPK"] {
+            let material = Material::parse(name).unwrap();
+            dtm.generate(&material);
+            let indexer = Indexer::new(&material);
+            for index in (0..indexer.size()).filter(|i| i % 7 == 0) {
+                let p = match indexer.position(index) {
+                    Some(ref p) if check(p).is_ok() => *p,
+                    _ => continue,
+                };
+                let (wdl, plies) = match dtm.probe_dtm(&p).unwrap() {
+                    Value::Win(d) => (Wdl::Win, d as i32),
+                    Value::Draw => (Wdl::Draw, 0),
+                    Value::Loss(d) => (Wdl::Loss, -cmp::max(d as i32, 1)),
+                };
+                assert_eq!(tb.probe_wdl(&p), Ok(wdl), "{}", p);
+                // only captures zero the count without pawns; the
+                // tables may store moves rather than plies
+                if !material.has_pawns() {
+                    let dtz = tb.probe_dtz(&p).unwrap();
+                    assert!(dtz.signum() == plies.signum() && (dtz - plies).abs() <= 1,
+                            "{} {} {}",
+                            p,
+                            dtz,
+                            plies);
+                }
+            }
+        }
+        // mate in 16 with the rook
+        assert_eq!(tb.probe_dtz(&position("8/8/8/8/3k4/8/8/R6K w - - 0 1")).map(|d| d > 0),
+                   Ok(true));
+        let wdl = |fen| tb.probe_wdl(&position(fen));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Ok(Wdl::Loss));
+        assert_eq!(wdl("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Ok(Wdl::Draw));
+    }
+
+    #[test]
+    fn en_passant() {
+        // the pawn wins whoever moves, pawns against pawns are drawn
+        let mut kpvk = vec![];
+        for _ in 0..4 {
+            kpvk.push(side(0, vec![4]));
+            kpvk.push(side(0, vec![0]));
+        }
+        let kpvkp = vec![side(0, vec![2]); 4];
+        let tables = [("KPvK.rtbw", write::write(&Info::parse("KPvK").unwrap(), false, &kpvk)),
+                      ("KPvKP.rtbw", write::write(&Info::parse("KPvKP").unwrap(), false, &kpvkp))];
+        let fixture = dir("en-passant", &tables);
+        let tb = Syzygy::open(&fixture.0).unwrap();
+
+        // only taking en passant leaves black the pawn
+        let p = position("8/8/8/8/3Pp3/8/k7/6K1 b - d3 0 1");
+        assert_eq!(tb.probe_wdl(&p), Ok(Wdl::Win));
+        assert_eq!(tb.probe_dtz(&p), Ok(1));
+        assert_eq!(tb.probe_outcome(&p, 99), Ok(tablebase::Wdl::Win));
+        let p = position("8/8/8/8/3Pp3/8/k7/6K1 b - - 0 1");
+        assert_eq!(tb.probe_wdl(&p), Ok(Wdl::Draw));
+        assert_eq!(tb.probe_dtz(&p), Ok(0));
+        // the colors swapped
+        assert_eq!(tb.probe_wdl(&position("8/8/8/8/8/8/K3p3/6k1 b - - 0 1")), Ok(Wdl::Win));
+        assert_eq!(tb.probe_wdl(&position("8/8/8/8/8/8/K3p3/6k1 w - - 0 1")), Ok(Wdl::Loss));
+        // no DTZ table
+        assert_eq!(tb.probe_dtz(&position("8/8/8/8/8/8/K3p3/6k1 w - - 0 1")),
+                   Err(SyzygyError::Missing("KvKP.rtbz".to_string())));
+    }
+
+    fn rank(board: &BitBoard, piece: Piece) -> u16 {
+        board.pieces(piece).index_of_least_significant_bit() as u16 / 8
+    }
+
+    #[test]
+    fn pawn_files() {
+        let e = Encoding::new();
+        // anything that does not change with the board mirrored
+        let value = |p: &Position| {
+            let b = &p.board;
+            let stm = if p.active == Color::White { 0 } else { 1 };
+            (rank(b, WHITE_PAWN) + 2 * rank(b, WHITE_KING) + rank(b, BLACK_KING) + stm) % 5
+        };
+        let all = positions(&[WHITE_KING, WHITE_PAWN, BLACK_KING]);
+        let (info, table) = header("KPvK", false, &e);
+        let values = fill(&table, &e, &all, |p| Some(value(p)));
+        let mut sides = vec![];
+        for file in 0..4 {
+            for stm in 0..2 {
+                sides.push(side(0, values[&(file, stm)].clone()));
+            }
+        }
+        let bytes = write::write(&info, false, &sides);
+        let table = Table::parse(info, false, bytes, &e).unwrap();
+        for p in all.iter().step_by(3) {
+            let swapped = Position::new(p.board.swap_colors(), p.active.invert());
+            for &(q, flip) in &[(p, false), (&swapped, true)] {
+                let placement = table.place(&e, q, flip);
+                let idx = table.index(&e, &placement).unwrap();
+                assert_eq!(table.decompress(table.pairs(&placement), idx),
+                           Some(value(p) as u32),
+                           "{}",
+                           q);
+            }
+        }
+    }
+
+    // What a position has in common with its mirror images
+    fn symmetric(p: &Position, pawns: bool) -> Vec<(u8, usize, usize)> {
+        let mut result: Vec<(u8, usize, usize)> = p.board
+            .occupation()
+            .single_bits()
+            .map(|m| {
+                let piece = p.board.get_piece(m);
+                let i = m.index_of_least_significant_bit() as usize;
+                let (file, rank) = (::std::cmp::min(i % 8, 7 - i % 8), i / 8);
+                if pawns {
+                    (piece.bits(), file, rank)
+                } else {
+                    let rank = ::std::cmp::min(rank, 7 - rank);
+                    (piece.bits(), ::std::cmp::min(file, rank), ::std::cmp::max(file, rank))
+                }
+            })
+            .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn more_pieces() {
+        let e = Encoding::new();
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let tables: &[(&str, &[Piece])] =
+            &[("KRvKN", &[WHITE_KING, WHITE_ROOK, BLACK_KING, BLACK_KNIGHT]),
+              ("KNNvK", &[WHITE_KING, WHITE_KNIGHT, WHITE_KNIGHT, BLACK_KING]),
+              ("KBNvK", &[WHITE_KING, WHITE_BISHOP, WHITE_KNIGHT, BLACK_KING]),
+              ("KPvKP", &[WHITE_KING, WHITE_PAWN, BLACK_KING, BLACK_PAWN]),
+              ("KPPvK", &[WHITE_KING, WHITE_PAWN, WHITE_PAWN, BLACK_KING]),
+              ("KRvKP", &[WHITE_KING, WHITE_ROOK, BLACK_KING, BLACK_PAWN]),
+              ("KRPvKP", &[WHITE_KING, WHITE_ROOK, WHITE_PAWN, BLACK_KING, BLACK_PAWN])];
+        for &(name, pieces) in tables {
+            let (info, table) = header(name, false, &e);
+            let mut seen = HashMap::new();
+            let mut n = 0;
+            while n < 30000 {
+                let mut board = BitBoard::new();
+                for &piece in pieces {
+                    let m = Mask::new(1 << rng.gen_range(0, 64));
+                    if !board.occupation().intersects(m) {
+                        board.set_piece(m, piece);
+                    }
+                }
+                let p = Position::new(board, Color::White);
+                if board.occupation().count() as usize != pieces.len() || check(&p).is_err() {
+                    continue;
+                }
+                n += 1;
+                let placement = table.place(&e, &p, false);
+                let idx = table.index(&e, &placement).unwrap();
+                let key = (placement.file, idx);
+                let common = symmetric(&p, info.has_pawns);
+                assert_eq!(seen.entry(key).or_insert_with(|| common.clone()),
+                           &common,
+                           "{} {}",
+                           name,
+                           p);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_files() {
+        let e = Encoding::new();
+        let (info, table) = header("KNvK", false, &e);
+        let placement = table.place(&e, &position("8/8/8/8/8/8/8/KNk5 w - - 0 1"), false);
+        let size = table.pairs(&placement).size();
+        let values: Vec<u16> = (0..size).map(|i| (i % 3) as u16).collect();
+        let mut bytes = write::write(&info, false, &[side(0, values.clone()), side(0, values)]);
+        let length = bytes.len();
+        bytes.truncate(length - 10);
+        let tables = [("KNvK.rtbw", bytes), ("KBvK.rtbw", b"KBvK".to_vec())];
+        let fixture = dir("corrupt", &tables);
+        let tb = Syzygy::open(&fixture.0).unwrap();
+        assert_eq!(tb.probe_wdl(&position("8/8/8/3k4/8/8/8/KN6 w - - 0 1")),
+                   Err(SyzygyError::Corrupt("KNvK.rtbw".to_string())));
+        assert_eq!(tb.probe_wdl(&position("8/8/8/3k4/8/8/8/KB6 w - - 0 1")),
+                   Err(SyzygyError::Corrupt("KBvK.rtbw".to_string())));
+    }
+}
@@ -0,0 +1,681 @@
+use color::Color;
+use position::Position;
+use super::encoding::*;
+
+// One `.rtbw` or `.rtbz` file. After a small header telling how the
+// pieces are grouped into the index, every file (a to d with pawns,
+// one without them) and side to move has its values Huffman coded in
+// blocks, with a sparse index saying where each stretch of indexes
+// starts.
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// the header
+pub const SPLIT: u8 = 1;
+pub const HAS_PAWNS: u8 = 2;
+
+// a side of a file
+pub const STM: u8 = 1;
+pub const MAPPED: u8 = 2;
+pub const WIN_PLIES: u8 = 4;
+pub const LOSS_PLIES: u8 = 8;
+pub const WIDE: u8 = 16;
+pub const SINGLE_VALUE: u8 = 128;
+
+// pieces are numbered 1..6 for white pawn..king, 9..14 for black
+pub const BLACK: u8 = 8;
+pub const PAWN: u8 = 1;
+
+static LETTERS: &'static [u8; 6] = b"PNBRQK";
+
+// What the name of a table tells: "KRPvKR" is white king, rook and
+// pawn against black king and rook.
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Info {
+    pub pieces: Vec<u8>,
+    pub has_pawns: bool,
+    // some piece other than a king is alone of its kind and color,
+    // three of those lead the index instead of the two kings
+    pub has_unique_pieces: bool,
+    // pawns of the leading color, then of the other one
+    pub pawn_count: [usize; 2],
+    // the same pieces on both sides: only white to move is stored
+    pub symmetric: bool,
+}
+
+impl Info {
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut sides = name.split('v');
+        let (white, black) = match (sides.next(), sides.next(), sides.next()) {
+            (Some(w), Some(b), None) => (w, b),
+            _ => return None,
+        };
+        let mut pieces = vec![];
+        let mut counts = [[0; 6]; 2];
+        for (color, side) in [white, black].iter().enumerate() {
+            if !side.starts_with('K') || side[1..].contains('K') {
+                return None;
+            }
+            for c in side.bytes() {
+                let kind = LETTERS.iter().position(|&l| l == c)?;
+                counts[color][kind] += 1;
+                pieces.push(kind as u8 + 1 + if color == 1 { BLACK } else { 0 });
+            }
+        }
+        if pieces.len() > 7 {
+            return None;
+        }
+        let (white_pawns, black_pawns) = (counts[0][0], counts[1][0]);
+        let white_leads = black_pawns == 0 || white_pawns > 0 && black_pawns >= white_pawns;
+        Some(Info {
+            pieces: pieces,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: counts.iter().any(|c| c[..5].contains(&1)),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            symmetric: white == black,
+        })
+    }
+    pub fn files(&self) -> usize {
+        if self.has_pawns { 4 } else { 1 }
+    }
+    fn both_have_pawns(&self) -> bool {
+        self.has_pawns && self.pawn_count[1] > 0
+    }
+}
+
+// How one side of one file is coded
+#[derive(Clone, Debug, Default)]
+pub struct Pairs {
+    pub flags: u8,
+    // the order of the pieces in the index
+    pub pieces: Vec<u8>,
+    // pieces are indexed in groups of the same ones, the first group
+    // the leading pawns or the kings with their company; the lengths
+    // end with a 0 and the last multiplier is the number of indexes
+    pub group_len: [usize; 8],
+    pub group_idx: [u64; 8],
+    // the single value, if so flagged
+    min_sym_len: u8,
+    max_sym_len: u8,
+    block_size: usize,
+    span: u64,
+    blocks: usize,
+    block_length_size: usize,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u32>,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    data: usize,
+    // DTZ: where the lists of values for win, loss, cursed win and
+    // blessed loss start in the map
+    map_idx: [usize; 4],
+}
+
+impl Pairs {
+    pub fn size(&self) -> u64 {
+        let n = self.group_len.iter().position(|&l| l == 0).unwrap_or(7);
+        self.group_idx[n]
+    }
+    fn set_groups(&mut self, info: &Info, order: [u8; 2], file: usize, e: &Encoding) {
+        let mut first_len: i32 = if info.has_pawns {
+            0
+        } else if info.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut n = 0;
+        self.group_len[0] = 1;
+        for i in 1..self.pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            } else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        // the leading group, the other side's pawns and the rest
+        // come in the order the file gives
+        let pp = info.both_have_pawns();
+        let mut next = if pp { 2 } else { 1 };
+        let mut free = 64 - self.group_len[0] - if pp { self.group_len[1] } else { 0 };
+        let mut idx = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                self.group_idx[0] = idx;
+                idx *= if info.has_pawns {
+                    e.lead_pawns_size[self.group_len[0]][file]
+                } else if info.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                self.group_idx[1] = idx;
+                idx *= e.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else {
+                self.group_idx[next] = idx;
+                idx *= e.binomial[self.group_len[next]][free];
+                free -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+    }
+}
+
+// A position seen from the table: colors swapped when the table has
+// them the other way, squares from a1 = 0
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub stm: usize,
+    pub file: usize,
+    squares: Vec<usize>,
+    pieces: Vec<u8>,
+    // the pawns the index starts with
+    leading: usize,
+}
+
+#[derive(Debug)]
+pub struct Table {
+    pub info: Info,
+    pub dtz: bool,
+    bytes: Vec<u8>,
+    sides: usize,
+    // [file * sides + side]
+    pairs: Vec<Pairs>,
+    map: usize,
+}
+
+fn byte(bytes: &[u8], at: usize) -> Option<u8> {
+    bytes.get(at).cloned()
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(byte(bytes, at)? as u16 | (byte(bytes, at + 1)? as u16) << 8)
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16_le(bytes, at)? as u32 | (u16_le(bytes, at + 2)? as u32) << 16)
+}
+
+// The decoder reads ahead of the last symbol of a block, past the
+// end of the file that reads as zeros
+fn be(bytes: &[u8], at: usize, len: usize) -> u64 {
+    (at..at + len).fold(0, |acc, i| acc << 8 | byte(bytes, i).unwrap_or(0) as u64)
+}
+
+impl Table {
+    pub fn parse(info: Info, dtz: bool, bytes: Vec<u8>, e: &Encoding) -> Option<Self> {
+        let (mut table, at) = Table::header(info, dtz, bytes, e)?;
+        table.sizes(at)?;
+        Some(table)
+    }
+    // Up to the piece order, what it takes to find positions
+    pub fn header(info: Info, dtz: bool, bytes: Vec<u8>, e: &Encoding) -> Option<(Self, usize)> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if bytes.len() < 5 || bytes[0..4] != magic {
+            return None;
+        }
+        let flags = bytes[4];
+        if (flags & HAS_PAWNS != 0) != info.has_pawns || (flags & SPLIT != 0) == info.symmetric {
+            return None;
+        }
+        let sides = if !dtz && !info.symmetric { 2 } else { 1 };
+        let pp = info.both_have_pawns();
+        let mut sorted = info.pieces.clone();
+        sorted.sort();
+        let mut pairs = vec![];
+        let mut at = 5;
+        for file in 0..info.files() {
+            let first = byte(&bytes, at)?;
+            let second = if pp { byte(&bytes, at + 1)? } else { 0xFF };
+            let orders = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            at += if pp { 2 } else { 1 };
+            for (side, &order) in orders.iter().enumerate().take(sides) {
+                let mut p = Pairs::default();
+                for k in 0..info.pieces.len() {
+                    let b = byte(&bytes, at + k)?;
+                    p.pieces.push(if side == 1 { b >> 4 } else { b & 0xF });
+                }
+                let mut check = p.pieces.clone();
+                check.sort();
+                if check != sorted {
+                    return None;
+                }
+                p.set_groups(&info, order, file, e);
+                if info.has_pawns &&
+                   (p.pieces[0] & !BLACK != PAWN || p.group_len[0] != info.pawn_count[0]) {
+                    return None;
+                }
+                pairs.push(p);
+            }
+            at += info.pieces.len();
+        }
+        at += at & 1;
+        Some((Table {
+                  info: info,
+                  dtz: dtz,
+                  bytes: bytes,
+                  sides: sides,
+                  pairs: pairs,
+                  map: 0,
+              },
+              at))
+    }
+    fn sizes(&mut self, mut at: usize) -> Option<()> {
+        for i in 0..self.pairs.len() {
+            at = self.set_sizes(i, at)?;
+        }
+        if self.dtz {
+            at = self.set_map(at)?;
+        }
+        for p in &mut self.pairs {
+            p.sparse_index = at;
+            at += p.sparse_index_size * 6;
+        }
+        for p in &mut self.pairs {
+            p.block_length = at;
+            at += p.block_length_size * 2;
+        }
+        for p in &mut self.pairs {
+            at = (at + 63) & !63;
+            p.data = at;
+            at += p.blocks * p.block_size;
+        }
+        if at > self.bytes.len() {
+            return None;
+        }
+        Some(())
+    }
+    fn set_sizes(&mut self, i: usize, mut at: usize) -> Option<usize> {
+        let bytes = &self.bytes;
+        let p = &mut self.pairs[i];
+        p.flags = byte(bytes, at)?;
+        if p.flags & SINGLE_VALUE != 0 {
+            p.min_sym_len = byte(bytes, at + 1)?;
+            return Some(at + 2);
+        }
+        let (block_size, span) = (byte(bytes, at + 1)?, byte(bytes, at + 2)?);
+        if block_size >= 32 || span >= 32 {
+            return None;
+        }
+        p.block_size = 1 << block_size;
+        p.span = 1 << span;
+        p.sparse_index_size = ((p.size() + p.span - 1) / p.span) as usize;
+        let padding = byte(bytes, at + 3)? as usize;
+        p.blocks = u32_le(bytes, at + 4)? as usize;
+        p.block_length_size = p.blocks + padding;
+        p.max_sym_len = byte(bytes, at + 8)?;
+        p.min_sym_len = byte(bytes, at + 9)?;
+        if p.min_sym_len == 0 || p.min_sym_len > p.max_sym_len || p.max_sym_len > 32 {
+            return None;
+        }
+        at += 10;
+        p.lowest_sym = at;
+        let lengths = (p.max_sym_len - p.min_sym_len + 1) as usize;
+        p.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let (lower, upper) = (u16_le(bytes, at + 2 * i)?, u16_le(bytes, at + 2 * i + 2)?);
+            p.base64[i] = p.base64[i + 1]
+                .wrapping_add(lower as u64)
+                .wrapping_sub(upper as u64) / 2;
+        }
+        for (i, base) in p.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - p.min_sym_len as usize;
+        }
+        at += lengths * 2;
+        let symbols = u16_le(bytes, at)? as usize;
+        at += 2;
+        p.btree = at;
+        at += symbols * 3 + (symbols & 1);
+        if at > bytes.len() {
+            return None;
+        }
+        p.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for s in 0..symbols {
+            if !visited[s] {
+                set_symlen(bytes, p, s, &mut visited)?;
+            }
+        }
+        Some(at)
+    }
+    fn set_map(&mut self, mut at: usize) -> Option<usize> {
+        self.map = at;
+        for file in 0..self.info.files() {
+            let p = &mut self.pairs[file * self.sides];
+            if p.flags & MAPPED == 0 {
+                continue;
+            }
+            if p.flags & WIDE != 0 {
+                at += at & 1;
+                for i in 0..4 {
+                    p.map_idx[i] = (at - self.map) / 2 + 1;
+                    at += 2 * u16_le(&self.bytes, at)? as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    p.map_idx[i] = at - self.map + 1;
+                    at += byte(&self.bytes, at)? as usize + 1;
+                }
+            }
+        }
+        Some(at + (at & 1))
+    }
+    pub fn pairs(&self, placement: &Placement) -> &Pairs {
+        &self.pairs[placement.file * self.sides + placement.stm % self.sides]
+    }
+    // DTZ tables store one side to move, for the other one the values
+    // come from a search
+    pub fn has_side(&self, placement: &Placement) -> bool {
+        let flags = self.pairs[placement.file * self.sides].flags;
+        !self.dtz || (flags & STM) as usize == placement.stm ||
+        self.info.symmetric && !self.info.has_pawns
+    }
+    // The position has to be of the table's material, `flip` when
+    // its colors are swapped in the table
+    pub fn place(&self, e: &Encoding, position: &Position, flip: bool) -> Placement {
+        let black_to_move = position.active == Color::Black;
+        let flip = flip || self.info.symmetric && black_to_move;
+        let color_flip = if flip { BLACK } else { 0 };
+        // our squares run from a8 = 0
+        let square = |i: u32| i as usize ^ if flip { 0 } else { 56 };
+        let board = &position.board;
+        let mut squares = vec![];
+        let mut pieces = vec![];
+        let mut rest = board.occupation();
+        let mut file = 0;
+        if self.info.has_pawns {
+            let lead = self.pairs[0].pieces[0];
+            let color = if (lead ^ color_flip) & BLACK != 0 {
+                Color::Black
+            } else {
+                Color::White
+            };
+            let pawns = board.pawns_of(color);
+            for mask in pawns.single_bits() {
+                squares.push(square(mask.index_of_least_significant_bit()));
+                pieces.push(lead);
+            }
+            rest &= !pawns;
+            if let Some(first) = (0..squares.len()).max_by_key(|&i| e.map_pawns[squares[i]]) {
+                squares.swap(0, first);
+                file = ::std::cmp::min(super::encoding::file(squares[0]),
+                                       7 - super::encoding::file(squares[0]));
+            }
+        }
+        let leading = squares.len();
+        for mask in rest.single_bits() {
+            let piece = board.get_piece(mask);
+            let black = if piece.color() == Color::Black { BLACK } else { 0 };
+            squares.push(square(mask.index_of_least_significant_bit()));
+            pieces.push((piece.kind().bits() + 1 + black) ^ color_flip);
+        }
+        Placement {
+            stm: (flip != black_to_move) as usize,
+            file: file,
+            squares: squares,
+            pieces: pieces,
+            leading: leading,
+        }
+    }
+    // None when the placement does not fit the table
+    pub fn index(&self, e: &Encoding, placement: &Placement) -> Option<u64> {
+        let d = self.pairs(placement);
+        let mut squares = placement.squares.clone();
+        let mut pieces = placement.pieces.clone();
+        let (size, leading) = (squares.len(), placement.leading);
+        if size != d.pieces.len() || leading > 5 || self.info.has_pawns && leading == 0 {
+            return None;
+        }
+        for i in leading..size {
+            let j = (i..size).find(|&j| pieces[j] == d.pieces[i])?;
+            squares.swap(i, j);
+            pieces.swap(i, j);
+        }
+        if file(squares[0]) > 3 {
+            for s in &mut squares {
+                *s ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.info.has_pawns {
+            idx = e.lead_pawn_idx[leading][squares[0]];
+            squares[1..leading].sort_by_key(|&s| e.map_pawns[s]);
+            for i in 1..leading {
+                idx += e.binomial[i][e.map_pawns[squares[i]]];
+            }
+        } else {
+            if rank(squares[0]) > 3 {
+                for s in &mut squares {
+                    *s ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for s in &mut squares[i..] {
+                        *s = flip_diagonal(*s);
+                    }
+                }
+                break;
+            }
+            idx = if self.info.has_unique_pieces {
+                let s: Vec<u64> = squares[..3].iter().map(|&s| s as u64).collect();
+                let r: Vec<u64> = squares[..3].iter().map(|&s| rank(s) as u64).collect();
+                let adjust1 = (s[1] > s[0]) as u64;
+                let adjust2 = (s[2] > s[0]) as u64 + (s[2] > s[1]) as u64;
+                if off_diagonal(squares[0]) != 0 {
+                    (e.map_a1d1d4[squares[0]] as u64 * 63 + s[1] - adjust1) * 62 + s[2] - adjust2
+                } else if off_diagonal(squares[1]) != 0 {
+                    (6 * 63 + r[0] * 28 + e.map_b1h1h7[squares[1]] as u64) * 62 + s[2] - adjust2
+                } else if off_diagonal(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + r[0] * 7 * 28 + (r[1] - adjust1) * 28 +
+                    e.map_b1h1h7[squares[2]] as u64
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + r[0] * 7 * 6 + (r[1] - adjust1) * 6 +
+                    r[2] - adjust2
+                }
+            } else {
+                e.map_kk[e.map_a1d1d4[squares[0]]][squares[1]] as u64
+            };
+        }
+
+        // every other group by the squares it takes, not counting
+        // the ones taken by the groups before
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.info.both_have_pawns();
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let s = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&t| s > t).count();
+                let below = adjust + if remaining_pawns { 8 } else { 0 };
+                n += e.binomial[i + 1][s.checked_sub(below)?];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        if idx < d.size() { Some(idx) } else { None }
+    }
+    // The stored value, None if the file is broken
+    pub fn decompress(&self, d: &Pairs, idx: u64) -> Option<u32> {
+        if d.flags & SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as u32);
+        }
+        let bytes = &self.bytes;
+        let k = (idx / d.span) as usize;
+        if k >= d.sparse_index_size {
+            return None;
+        }
+        let mut block = u32_le(bytes, d.sparse_index + 6 * k)? as usize;
+        let mut offset = u16_le(bytes, d.sparse_index + 6 * k + 4)? as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+        let block_length = |block: usize| if block < d.block_length_size {
+            u16_le(bytes, d.block_length + 2 * block).map(|l| l as i64)
+        } else {
+            None
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+        if block >= d.blocks {
+            return None;
+        }
+
+        let min = d.min_sym_len as usize;
+        let lowest_sym = |len: usize| u16_le(bytes, d.lowest_sym + 2 * len).map(|l| l as usize);
+        let mut at = d.data + block * d.block_size;
+        let mut buf = be(bytes, at, 8);
+        at += 8;
+        let mut buf_size = 64;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < d.base64[len] {
+                len += 1;
+                if len == d.base64.len() {
+                    return None;
+                }
+            }
+            sym = ((buf - d.base64[len]) >> (64 - len - min)) as usize + lowest_sym(len)?;
+            let symlen = *d.symlen.get(sym)? as i64;
+            if offset < symlen + 1 {
+                break;
+            }
+            offset -= symlen + 1;
+            len += min;
+            buf <<= len;
+            buf_size -= len;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= be(bytes, at, 4) << (64 - buf_size);
+                at += 4;
+            }
+        }
+
+        // a symbol stands for a pair of shorter ones, down to the value
+        for _ in 0..d.symlen.len() {
+            if d.symlen[sym] == 0 {
+                return Some(left(bytes, d, sym)? as u32);
+            }
+            let l = left(bytes, d, sym)?;
+            let symlen = *d.symlen.get(l)? as i64;
+            if offset < symlen + 1 {
+                sym = l;
+            } else {
+                offset -= symlen + 1;
+                sym = right(bytes, d, sym)?;
+                if sym >= d.symlen.len() {
+                    return None;
+                }
+            }
+        }
+        None
+    }
+    // DTZ: the stored value in plies, to the zeroing move
+    pub fn map_score(&self, placement: &Placement, value: u32, wdl: i32) -> Option<i32> {
+        let d = &self.pairs[placement.file * self.sides];
+        let mut value = value as usize;
+        if d.flags & MAPPED != 0 {
+            // win, loss, cursed win, blessed loss
+            let list = d.map_idx[[1, 3, 0, 2, 0][(wdl + 2) as usize]];
+            value = if d.flags & WIDE != 0 {
+                u16_le(&self.bytes, self.map + 2 * (list + value))? as usize
+            } else {
+                byte(&self.bytes, self.map + list + value)? as usize
+            };
+        }
+        if wdl == 2 && d.flags & WIN_PLIES == 0 || wdl == -2 && d.flags & LOSS_PLIES == 0 ||
+           wdl == 1 || wdl == -1 {
+            value *= 2;
+        }
+        Some(value as i32 + 1)
+    }
+}
+
+fn left(bytes: &[u8], d: &Pairs, sym: usize) -> Option<usize> {
+    let at = d.btree + 3 * sym;
+    Some(((byte(bytes, at + 1)? as usize & 0xF) << 8) | byte(bytes, at)? as usize)
+}
+
+fn right(bytes: &[u8], d: &Pairs, sym: usize) -> Option<usize> {
+    let at = d.btree + 3 * sym;
+    Some((byte(bytes, at + 2)? as usize) << 4 | byte(bytes, at + 1)? as usize >> 4)
+}
+
+// How many values a symbol stands for, less one
+fn set_symlen(bytes: &[u8], d: &mut Pairs, s: usize, visited: &mut [bool]) -> Option<()> {
+    visited[s] = true;
+    let r = right(bytes, d, s)?;
+    if r == 0xFFF {
+        return Some(());
+    }
+    let l = left(bytes, d, s)?;
+    if l >= visited.len() || r >= visited.len() {
+        return None;
+    }
+    if !visited[l] {
+        set_symlen(bytes, d, l, visited)?;
+    }
+    if !visited[r] {
+        set_symlen(bytes, d, r, visited)?;
+    }
+    d.symlen[s] = d.symlen[l].saturating_add(d.symlen[r]).saturating_add(1);
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn info() {
+        let i = Info::parse("KRPvKR").unwrap();
+        assert_eq!(i.pieces, vec![6, 4, 1, 14, 12]);
+        assert!(i.has_pawns && i.has_unique_pieces && !i.symmetric);
+        assert_eq!(i.pawn_count, [1, 0]);
+        let i = Info::parse("KNNvKP").unwrap();
+        assert_eq!(i.pawn_count, [1, 0]);
+        assert!(i.has_unique_pieces);
+        let i = Info::parse("KPPvKP").unwrap();
+        // black has fewer pawns and leads
+        assert_eq!(i.pawn_count, [1, 2]);
+        let i = Info::parse("KRvKR").unwrap();
+        assert!(i.symmetric && i.has_unique_pieces);
+        assert!(!Info::parse("KNNvKBB").unwrap().has_unique_pieces);
+        for name in &["KQK", "KQvKvK", "QKvK", "KQvKK", "KXvK", "KQQQvKQQQ"] {
+            assert_eq!(Info::parse(name), None, "{}", name);
+        }
+    }
+}
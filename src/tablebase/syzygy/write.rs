@@ -0,0 +1,222 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use super::table::*;
+
+// Table files for the tests, laid out as the generator lays them out
+// but simpler: every value is a symbol of its own, no pairs, in blocks
+// of 32 bytes.
+
+const BLOCK_SIZE: usize = 1 << 5;
+const SPAN: usize = 1 << 6;
+
+// One side to move of one file: a value per index
+#[derive(Clone, Debug, Default)]
+pub struct Side {
+    pub flags: u8,
+    pub values: Vec<u16>,
+    // DTZ: the values of wins, losses, cursed wins and blessed losses,
+    // when MAPPED, on the first side of a file
+    pub map: [Vec<u8>; 4],
+}
+
+// The magic, the flags and the order of the pieces: leading pawns,
+// the other pawns, then pieces alone of their kind before the others.
+pub fn header(info: &Info, dtz: bool) -> Vec<u8> {
+    let mut bytes = if dtz { DTZ_MAGIC } else { WDL_MAGIC }.to_vec();
+    let split = if info.symmetric { 0 } else { SPLIT };
+    bytes.push(split | if info.has_pawns { HAS_PAWNS } else { 0 });
+    let count = |p: u8| info.pieces.iter().filter(|&&q| q == p).count();
+    let (white, black) = (count(PAWN), count(PAWN | BLACK));
+    let leading = if black == 0 || white > 0 && black >= white { PAWN } else { PAWN | BLACK };
+    let lead = |p: u8| if p == leading {
+        0
+    } else if p & !BLACK == PAWN {
+        1
+    } else {
+        2
+    };
+    let mut pieces = info.pieces.clone();
+    pieces.sort_by_key(|&p| (lead(p), count(p), p));
+    let pp = info.has_pawns && info.pawn_count[1] > 0;
+    for _ in 0..info.files() {
+        bytes.push(0x00);
+        if pp {
+            bytes.push(0x11);
+        }
+        bytes.extend(pieces.iter().map(|&p| p | p << 4));
+    }
+    if bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+struct Coded {
+    sizes: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_length: Vec<u8>,
+    data: Vec<u8>,
+}
+
+fn push_u16(bytes: &mut Vec<u8>, n: usize) {
+    bytes.push(n as u8);
+    bytes.push((n >> 8) as u8);
+}
+
+fn push_u32(bytes: &mut Vec<u8>, n: usize) {
+    push_u16(bytes, n & 0xFFFF);
+    push_u16(bytes, n >> 16);
+}
+
+// Huffman code lengths by value
+fn lengths(frequencies: &BTreeMap<u16, usize>) -> BTreeMap<u16, usize> {
+    let mut lengths: BTreeMap<u16, usize> = frequencies.keys().map(|&v| (v, 0)).collect();
+    let mut nodes: Vec<(usize, Vec<u16>)> =
+        frequencies.iter().map(|(&v, &f)| (f, vec![v])).collect();
+    while nodes.len() > 1 {
+        nodes.sort_by_key(|n| Reverse(n.0));
+        let (f1, mut v1) = nodes.pop().unwrap();
+        let (f2, v2) = nodes.pop().unwrap();
+        v1.extend(v2);
+        for v in &v1 {
+            *lengths.get_mut(v).unwrap() += 1;
+        }
+        nodes.push((f1 + f2, v1));
+    }
+    lengths
+}
+
+fn code(flags: u8, values: &[u16]) -> Coded {
+    let mut frequencies = BTreeMap::new();
+    for &v in values {
+        *frequencies.entry(v).or_insert(0) += 1;
+    }
+    if frequencies.len() <= 1 {
+        return Coded {
+            sizes: vec![flags | SINGLE_VALUE, values.first().cloned().unwrap_or(0) as u8],
+            sparse_index: vec![],
+            block_length: vec![],
+            data: vec![],
+        };
+    }
+    let lengths = lengths(&frequencies);
+    // longer codes get the lower symbols
+    let mut symbols: Vec<u16> = lengths.keys().cloned().collect();
+    symbols.sort_by_key(|v| (!lengths[v], *v));
+    let min = *lengths.values().min().unwrap();
+    let max = *lengths.values().max().unwrap();
+    let lowest: Vec<usize> = (min..max + 1)
+        .map(|l| lengths.values().filter(|&&m| m > l).count())
+        .collect();
+    let mut base = vec![0; max + 1];
+    for l in (min..max).rev() {
+        base[l] = (base[l + 1] + lowest[l - min] - lowest[l + 1 - min]) / 2;
+    }
+    let codes: BTreeMap<u16, (u64, usize)> = symbols.iter()
+        .enumerate()
+        .map(|(sym, v)| {
+            let l = lengths[v];
+            (*v, ((base[l] + sym - lowest[l - min]) as u64, l))
+        })
+        .collect();
+
+    let mut sizes = vec![flags, 5, 6, 0, 0, 0, 0, 0, max as u8, min as u8];
+    for &l in &lowest {
+        push_u16(&mut sizes, l);
+    }
+    push_u16(&mut sizes, symbols.len());
+    for &v in &symbols {
+        sizes.extend(&[v as u8, (v >> 8) as u8 | 0xF0, 0xFF]);
+    }
+    if symbols.len() % 2 == 1 {
+        sizes.push(0);
+    }
+
+    // blocks of whole symbols, numbered from the most significant bit
+    let mut data = vec![];
+    let mut counts = vec![0];
+    let mut bits = vec![];
+    for &v in values {
+        let (code, len) = codes[&v];
+        if bits.len() + len > BLOCK_SIZE * 8 {
+            close(&mut data, &mut bits);
+            counts.push(0);
+        }
+        bits.extend((0..len).rev().map(|i| code >> i & 1 == 1));
+        *counts.last_mut().unwrap() += 1;
+    }
+    close(&mut data, &mut bits);
+    let blocks = counts.len();
+    sizes[4..8].copy_from_slice(&[blocks as u8, (blocks >> 8) as u8, (blocks >> 16) as u8, 0]);
+
+    let mut block_length = vec![];
+    for &c in &counts {
+        push_u16(&mut block_length, c - 1);
+    }
+    // the block and the offset in it of the middle of every span
+    let mut sparse_index = vec![];
+    let starts: Vec<usize> = counts.iter()
+        .scan(0, |start, &c| {
+            *start += c;
+            Some(*start - c)
+        })
+        .collect();
+    for k in 0..(values.len() + SPAN - 1) / SPAN {
+        let middle = k * SPAN + SPAN / 2;
+        let block = starts.iter().rposition(|&s| s <= middle).unwrap();
+        push_u32(&mut sparse_index, block);
+        push_u16(&mut sparse_index, middle - starts[block]);
+    }
+    Coded {
+        sizes: sizes,
+        sparse_index: sparse_index,
+        block_length: block_length,
+        data: data,
+    }
+}
+
+fn close(data: &mut Vec<u8>, bits: &mut Vec<bool>) {
+    if bits.is_empty() {
+        return;
+    }
+    bits.resize(BLOCK_SIZE * 8, false);
+    for byte in bits.chunks(8) {
+        data.push(byte.iter().fold(0, |acc, &b| acc << 1 | b as u8));
+    }
+    bits.clear();
+}
+
+// The sides in the order of the table, [file * sides + side]
+pub fn write(info: &Info, dtz: bool, sides: &[Side]) -> Vec<u8> {
+    let mut bytes = header(info, dtz);
+    let coded: Vec<Coded> = sides.iter().map(|s| code(s.flags, &s.values)).collect();
+    for c in &coded {
+        bytes.extend(&c.sizes);
+    }
+    if dtz {
+        for side in sides {
+            if side.flags & MAPPED != 0 {
+                for list in &side.map {
+                    bytes.push(list.len() as u8);
+                    bytes.extend(list);
+                }
+            }
+        }
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+    }
+    for c in &coded {
+        bytes.extend(&c.sparse_index);
+    }
+    for c in &coded {
+        bytes.extend(&c.block_length);
+    }
+    for c in &coded {
+        while bytes.len() % 64 != 0 {
+            bytes.push(0);
+        }
+        bytes.extend(&c.data);
+    }
+    bytes
+}
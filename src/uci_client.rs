@@ -0,0 +1,600 @@
+use moves::Move;
+use position::Position;
+use rules::Rules;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Driving an external UCI engine: spawn it, shake hands, set options,
+// send positions and read back the search with every move checked
+// against the position it is played in.
+
+// for `uciok` and `readyok`
+const HANDSHAKE: u64 = 10000;
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub enum UciError {
+    Io(io::ErrorKind),
+    Timeout,
+    // the engine closed its output, most likely it is gone
+    Exited,
+    // a move in `bestmove`, `ponder` or a `pv` that is not legal there
+    IllegalMove(String),
+    // a line that does not follow the protocol
+    Protocol(String),
+}
+
+impl From<io::Error> for UciError {
+    fn from(e: io::Error) -> Self {
+        UciError::Io(e.kind())
+    }
+}
+
+// An `option` line of the handshake
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct EngineOption {
+    pub name: String,
+    // check, spin, combo, button or string
+    pub kind: String,
+    pub default: Option<String>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    // combo only
+    pub vars: Vec<String>,
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    // in moves, negative when the engine gets mated
+    Mate(i32),
+}
+
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+// What an `info` line tells, fields it lacks stay empty
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    // milliseconds
+    pub time: Option<u64>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+impl Default for Info {
+    fn default() -> Self {
+        Info {
+            depth: None,
+            seldepth: None,
+            multipv: None,
+            score: None,
+            bound: Bound::Exact,
+            nodes: None,
+            nps: None,
+            time: None,
+            pv: vec![],
+            string: None,
+        }
+    }
+}
+
+// The `go` parameters, None for the ones not sent; times in milliseconds
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub movetime: Option<u64>,
+}
+
+#[derive(Eq, Clone, Debug, PartialEq)]
+pub struct Search {
+    pub best: Move,
+    pub ponder: Option<Move>,
+    // every `info` line in the order they came
+    pub infos: Vec<Info>,
+}
+
+#[derive(Debug)]
+pub struct Engine {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub options: Vec<EngineOption>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    // what the last `position` command set up
+    position: Position,
+}
+
+impl Engine {
+    // Starts the engine and waits for `uciok`
+    pub fn spawn(program: &str, args: &[&str]) -> Result<Engine, UciError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        // lines arrive through a channel so that reading them can time out
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        let mut engine = Engine {
+            name: None,
+            author: None,
+            options: vec![],
+            child: child,
+            stdin: stdin,
+            lines: receiver,
+            position: Position::start(Rules::Standard),
+        };
+        engine.send("uci")?;
+        let deadline = Some(Duration::from_millis(HANDSHAKE));
+        loop {
+            let line = engine.read_line(deadline)?;
+            if line == "uciok" {
+                break;
+            } else if line.starts_with("id name ") {
+                engine.name = Some(line[8..].trim().to_string());
+            } else if line.starts_with("id author ") {
+                engine.author = Some(line[10..].trim().to_string());
+            } else if line.starts_with("option ") {
+                engine.options.push(parse_option(&line)?);
+            }
+        }
+        Ok(engine)
+    }
+
+    // A raw line to the engine
+    pub fn send(&mut self, line: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    // The next non-empty line, waits forever without a timeout
+    pub fn read_line(&mut self, timeout: Option<Duration>) -> Result<String, UciError> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let line = match deadline {
+                None => self.lines.recv().map_err(|_| UciError::Exited)?,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(UciError::Timeout);
+                    }
+                    match self.lines.recv_timeout(deadline - now) {
+                        Ok(line) => line,
+                        Err(RecvTimeoutError::Timeout) => return Err(UciError::Timeout),
+                        Err(RecvTimeoutError::Disconnected) => return Err(UciError::Exited),
+                    }
+                }
+            };
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(line.to_string());
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), UciError> {
+        match value {
+            Some(value) => self.send(&format!("setoption name {} value {}", name, value)),
+            None => self.send(&format!("setoption name {}", name)),
+        }
+    }
+
+    // Sends `isready` and skips everything up to `readyok`
+    pub fn is_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        let timeout = Some(Duration::from_millis(HANDSHAKE));
+        while self.read_line(timeout)? != "readyok" {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    // The start, as `startpos` when it is the standard one, and the moves
+    pub fn set_position(&mut self, start: &Position, moves: &[Move]) -> Result<(), UciError> {
+        let mut command = if *start == Position::start(Rules::Standard) {
+            "position startpos".to_string()
+        } else {
+            // there are no clocks in a position
+            format!("position fen {}", start.fen(0, 1))
+        };
+        let mut p = *start;
+        for (i, &mv) in moves.iter().enumerate() {
+            command.push_str(if i == 0 { " moves " } else { " " });
+            command.push_str(&p.uci(mv));
+            p = p.make_move(mv);
+        }
+        self.position = p;
+        self.send(&command)
+    }
+
+    // Searches the position set last and waits for `bestmove`
+    pub fn go(&mut self, limits: &Limits, timeout: Option<Duration>) -> Result<Search, UciError> {
+        let mut command = "go".to_string();
+        {
+            let mut add = |name: &str, value: Option<u64>| {
+                if let Some(value) = value {
+                    command.push_str(&format!(" {} {}", name, value));
+                }
+            };
+            add("wtime", limits.wtime);
+            add("btime", limits.btime);
+            add("winc", limits.winc);
+            add("binc", limits.binc);
+            add("movestogo", limits.movestogo.map(|n| n as u64));
+            add("depth", limits.depth.map(|n| n as u64));
+            add("nodes", limits.nodes);
+            add("mate", limits.mate.map(|n| n as u64));
+            add("movetime", limits.movetime);
+        }
+        self.send(&command)?;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut infos = vec![];
+        loop {
+            let left = deadline.map(|d| {
+                let now = Instant::now();
+                if d > now { d - now } else { Duration::from_millis(0) }
+            });
+            let line = match self.read_line(left) {
+                Err(UciError::Timeout) => {
+                    // or its `bestmove` would answer the next search
+                    self.stop();
+                    return Err(UciError::Timeout);
+                }
+                line => line?,
+            };
+            if line.starts_with("info") {
+                match parse_info(&self.position, &line) {
+                    Ok(info) => infos.push(info),
+                    Err(e) => {
+                        // the search goes on, and so would its `bestmove`
+                        self.stop();
+                        return Err(e);
+                    }
+                }
+            } else if line.starts_with("bestmove") {
+                let (best, ponder) = parse_bestmove(&self.position, &line)?;
+                return Ok(Search {
+                    best: best,
+                    ponder: ponder,
+                    infos: infos,
+                });
+            }
+        }
+    }
+
+    // Ends the search and drops everything up to its `bestmove`. The
+    // timeout is what gets reported, so an engine that does not stop
+    // either is left for the next `isready` to sort out.
+    fn stop(&mut self) {
+        if self.send("stop").is_err() {
+            return;
+        }
+        let timeout = Some(Duration::from_millis(HANDSHAKE));
+        while let Ok(line) = self.read_line(timeout) {
+            if line.starts_with("bestmove") {
+                break;
+            }
+        }
+    }
+
+    // Asks the engine to leave and makes sure it does
+    pub fn quit(mut self) {
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    pub fn has_exited(&mut self) -> bool {
+        self.child.try_wait().ok().map_or(false, |status| status.is_some())
+    }
+}
+
+// "option name Hash type spin default 16 min 1 max 1024", names and
+// values may have spaces in them
+pub fn parse_option(line: &str) -> Result<EngineOption, UciError> {
+    let keywords = ["name", "type", "default", "min", "max", "var"];
+    let mut result = EngineOption {
+        name: String::new(),
+        kind: String::new(),
+        default: None,
+        min: None,
+        max: None,
+        vars: vec![],
+    };
+    let mut words = line.split_whitespace().skip(1).peekable();
+    while let Some(keyword) = words.next() {
+        let mut value = vec![];
+        while let Some(&word) = words.peek() {
+            if keywords.contains(&word) {
+                break;
+            }
+            value.push(word);
+            words.next();
+        }
+        let value = value.join(" ");
+        let number = || value.parse().map_err(|_| UciError::Protocol(line.to_string()));
+        match keyword {
+            "name" => result.name = value.clone(),
+            "type" => result.kind = value.clone(),
+            "default" => result.default = Some(value.clone()),
+            "min" => result.min = Some(number()?),
+            "max" => result.max = Some(number()?),
+            "var" => result.vars.push(value.clone()),
+            _ => return Err(UciError::Protocol(line.to_string())),
+        }
+    }
+    if result.name.is_empty() {
+        return Err(UciError::Protocol(line.to_string()));
+    }
+    Ok(result)
+}
+
+// An `info` line about the search in `p`
+pub fn parse_info(p: &Position, line: &str) -> Result<Info, UciError> {
+    let error = || UciError::Protocol(line.to_string());
+    let mut info = Info::default();
+    let mut words = line.split_whitespace().skip(1);
+    while let Some(word) = words.next() {
+        match word {
+            "depth" => info.depth = Some(number(words.next(), line)?),
+            "seldepth" => info.seldepth = Some(number(words.next(), line)?),
+            "multipv" => info.multipv = Some(number(words.next(), line)?),
+            "nodes" => info.nodes = Some(number(words.next(), line)?),
+            "nps" => info.nps = Some(number(words.next(), line)?),
+            "time" => info.time = Some(number(words.next(), line)?),
+            "score" => {
+                info.score = Some(match words.next() {
+                    Some("cp") => Score::Centipawns(number(words.next(), line)?),
+                    Some("mate") => Score::Mate(number(words.next(), line)?),
+                    _ => return Err(error()),
+                });
+            }
+            "lowerbound" => info.bound = Bound::Lower,
+            "upperbound" => info.bound = Bound::Upper,
+            "pv" => {
+                let mut q = *p;
+                // the moves run to the end of the line
+                for uci in words.by_ref() {
+                    let mv = q.parse_uci(uci).ok_or_else(|| UciError::IllegalMove(uci.to_string()))?;
+                    info.pv.push(mv);
+                    q = q.make_move(mv);
+                }
+            }
+            "string" => {
+                info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+            }
+            // the rest of the line is about other lines than the best one
+            "refutation" | "currline" => break,
+            // single values we do not keep
+            "currmove" | "currmovenumber" | "hashfull" | "tbhits" | "sbhits" | "cpuload" => {
+                words.next().ok_or_else(&error)?;
+            }
+            // extensions like `wdl W D L` or `ebf 1.8`, with their values
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn number<T: ::std::str::FromStr>(word: Option<&str>, line: &str) -> Result<T, UciError> {
+    word.and_then(|w| w.parse().ok()).ok_or_else(|| UciError::Protocol(line.to_string()))
+}
+
+// "bestmove e2e4 ponder e7e5"
+fn parse_bestmove(p: &Position, line: &str) -> Result<(Move, Option<Move>), UciError> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let best = words.get(1).ok_or_else(|| UciError::Protocol(line.to_string()))?;
+    let mv = p.parse_uci(best).ok_or_else(|| UciError::IllegalMove(best.to_string()))?;
+    let ponder = match (words.get(2), words.get(3)) {
+        (Some(&"ponder"), Some(ponder)) => {
+            let next = p.make_move(mv);
+            Some(next.parse_uci(ponder).ok_or_else(|| UciError::IllegalMove(ponder.to_string()))?)
+        }
+        _ => None,
+    };
+    Ok((mv, ponder))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use square::*;
+
+    // Answers like an engine would, `$0` picks the bestmove; `wait`
+    // searches until told to stop, `show` echoes the last position
+    static FAKE_ENGINE: &'static str = "
+        while read command rest; do
+            case \"$command\" in
+                uci) echo 'id name Fake Engine 1.0'
+                     echo 'id author Tester'
+                     echo 'option name Hash type spin default 16 min 1 max 1024'
+                     echo 'option name Style type combo default Normal var Solid var Normal'
+                     echo uciok ;;
+                isready) echo readyok ;;
+                go) [ \"$0\" = wait ] && { searching=1; continue; }
+                    echo 'info depth 1 score cp 20 nodes 20 nps 2000 time 10 pv e2e4'
+                    echo 'info string thinking hard'
+                    echo 'info depth 2 multipv 1 score mate 2 lowerbound nodes 400 pv e2e4 e7e5'
+                    echo \"bestmove $0 ponder e7e5\" ;;
+                stop) [ -n \"$searching\" ] && echo 'bestmove e2e4'
+                      searching= ;;
+                position) last=$rest ;;
+                show) echo \"$last\" ;;
+                slow) sleep 1 ;;
+                quit) exit 0 ;;
+            esac
+        done";
+
+    fn fake(bestmove: &str) -> Engine {
+        Engine::spawn("sh", &["-c", FAKE_ENGINE, bestmove]).unwrap()
+    }
+
+    #[test]
+    fn info_lines() {
+        let p = Position::start(Rules::Standard);
+        let info = parse_info(&p,
+                              "info depth 12 seldepth 18 multipv 2 score cp -35 upperbound \
+                               nodes 123456 nps 987654 hashfull 12 tbhits 0 time 125 \
+                               pv g1f3 d7d5 g2g3")
+            .unwrap();
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(Score::Centipawns(-35)));
+        assert_eq!(info.bound, Bound::Upper);
+        assert_eq!((info.nodes, info.nps, info.time), (Some(123456), Some(987654), Some(125)));
+        assert_eq!(info.pv, vec![Move::new(G1, F3), Move::new(D7, D5), Move::new(G2, G3)]);
+
+        let info = parse_info(&p, "info score mate -3 currmove e2e4 currmovenumber 1").unwrap();
+        assert_eq!(info.score, Some(Score::Mate(-3)));
+        assert_eq!(info.pv, vec![]);
+        let info = parse_info(&p, "info string no pv e2e4 here").unwrap();
+        assert_eq!(info.string, Some("no pv e2e4 here".to_string()));
+    }
+
+    #[test]
+    fn bad_info_lines() {
+        let p = Position::start(Rules::Standard);
+        assert_eq!(parse_info(&p, "info depth 3 pv e2e4 e2e4"),
+                   Err(UciError::IllegalMove("e2e4".to_string())));
+        assert_eq!(parse_info(&p, "info depth x"),
+                   Err(UciError::Protocol("info depth x".to_string())));
+        assert_eq!(parse_info(&p, "info score 20"),
+                   Err(UciError::Protocol("info score 20".to_string())));
+        assert_eq!(parse_info(&p, "info currmove"),
+                   Err(UciError::Protocol("info currmove".to_string())));
+    }
+
+    #[test]
+    fn unknown_info() {
+        let p = Position::start(Rules::Standard);
+        let info = parse_info(&p, "info depth 5 wdl 500 400 100 ebf 1.8 score cp 10 pv e2e4")
+            .unwrap();
+        assert_eq!(info.depth, Some(5));
+        assert_eq!(info.score, Some(Score::Centipawns(10)));
+        assert_eq!(info.pv, vec![Move::new(E2, E4)]);
+        assert_eq!(parse_info(&p, "info frobnicate 1").unwrap(), Info::default());
+    }
+
+    #[test]
+    fn options() {
+        let option = parse_option("option name Skill Level type spin default 20 min 0 max 20")
+            .unwrap();
+        assert_eq!(option.name, "Skill Level");
+        assert_eq!((option.min, option.max), (Some(0), Some(20)));
+        let option = parse_option("option name Clear Hash type button").unwrap();
+        assert_eq!((option.kind.as_str(), option.default), ("button", None));
+        assert!(parse_option("option type check").is_err());
+    }
+
+    #[test]
+    fn fake_engine() {
+        let mut engine = fake("e2e4");
+        assert_eq!(engine.name, Some("Fake Engine 1.0".to_string()));
+        assert_eq!(engine.author, Some("Tester".to_string()));
+        assert_eq!(engine.options.len(), 2);
+        assert_eq!(engine.options[1].vars, vec!["Solid", "Normal"]);
+        engine.set_option("Hash", Some("64")).unwrap();
+        engine.new_game().unwrap();
+        engine.set_position(&Position::start(Rules::Standard), &[]).unwrap();
+        let limits = Limits { depth: Some(2), ..Limits::default() };
+        let search = engine.go(&limits, Some(Duration::from_secs(10))).unwrap();
+        assert_eq!(search.best, Move::new(E2, E4));
+        assert_eq!(search.ponder, Some(Move::new(E7, E5)));
+        assert_eq!(search.infos.len(), 3);
+        assert_eq!(search.infos[2].score, Some(Score::Mate(2)));
+        assert_eq!(search.infos[2].bound, Bound::Lower);
+        engine.quit();
+    }
+
+    #[test]
+    fn fake_engine_errors() {
+        let mut engine = fake("e2e5");
+        engine.set_position(&Position::start(Rules::Standard), &[]).unwrap();
+        assert_eq!(engine.go(&Limits::default(), None),
+                   Err(UciError::IllegalMove("e2e5".to_string())));
+        // after 1. e4 the pv is no longer legal
+        let p = Position::start(Rules::Standard);
+        engine.set_position(&p, &[p.parse_uci("e2e4").unwrap()]).unwrap();
+        assert_eq!(engine.go(&Limits::default(), None),
+                   Err(UciError::IllegalMove("e2e4".to_string())));
+        // the rest of the search is gone with its bestmove
+        engine.send("isready").unwrap();
+        assert_eq!(engine.read_line(Some(Duration::from_secs(10))), Ok("readyok".to_string()));
+        engine.send("slow").unwrap();
+        assert_eq!(engine.read_line(Some(Duration::from_millis(50))),
+                   Err(UciError::Timeout));
+        engine.send("quit").unwrap();
+        assert_eq!(engine.read_line(Some(Duration::from_secs(10))), Err(UciError::Exited));
+        assert!(Engine::spawn("/nonexistent/engine", &[]).is_err());
+    }
+
+    #[test]
+    fn en_passant_fen() {
+        let mut engine = fake("e2e4");
+        let p = Position::parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        engine.set_position(&p, &[Move::new(E5, D6)]).unwrap();
+        engine.send("show").unwrap();
+        assert_eq!(engine.read_line(Some(Duration::from_secs(10))),
+                   Ok("fen 4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1 moves e5d6".to_string()));
+        engine.quit();
+    }
+
+    #[test]
+    fn timeout() {
+        let mut engine = fake("wait");
+        engine.set_position(&Position::start(Rules::Standard), &[]).unwrap();
+        assert_eq!(engine.go(&Limits::default(), Some(Duration::from_millis(50))),
+                   Err(UciError::Timeout));
+        // the search is over and its bestmove gone
+        engine.send("stop").unwrap();
+        engine.send("isready").unwrap();
+        assert_eq!(engine.read_line(Some(Duration::from_secs(10))), Ok("readyok".to_string()));
+        engine.quit();
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+use kind::*;
+use position::validate_position::*;
+
+// Captures are compulsory, the king is an ordinary piece
+// and losing all the pieces wins
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+    // no castling, pawns may become kings
+    fn pseudo_legal_moves(&self, _p: &Position, _color: Color, moves: &mut Vec<Move>) {
+        moves.retain(|mv| mv.castle == ::castle::NONE);
+        let kings = moves.iter()
+            .filter(|mv| mv.promote == QUEEN)
+            .map(|mv| Move::promote(mv.from, mv.to, KING))
+            .collect::<Vec<_>>();
+        moves.extend(kings);
+    }
+    fn is_legal(&self, _next: &Position, _color: Color) -> bool {
+        true
+    }
+    // when there is a capture, only captures count
+    fn filter_legal(&self, p: &Position, moves: Vec<Move>) -> Vec<Move> {
+        if moves.iter().any(|&mv| p.is_capture(mv)) {
+            moves.into_iter().filter(|&mv| p.is_capture(mv)).collect()
+        } else {
+            moves
+        }
+    }
+    fn is_check(&self, _board: &BitBoard, _color: Color) -> bool {
+        false
+    }
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        if p.board.occupation_of(p.active).count() == 0 {
+            Some(Outcome::Win(p.active))
+        } else {
+            None
+        }
+    }
+    // being stalemated wins
+    fn no_moves(&self, p: &Position) -> Outcome {
+        Outcome::Win(p.active)
+    }
+    fn validate(&self, p: &Position) -> Assessment {
+        p.validate_standard() -
+        (HAS_NO_WHITE_KING | HAS_MORE_THAN_ONE_WHITE_KING | HAS_NO_BLACK_KING |
+         HAS_MORE_THAN_ONE_BLACK_KING)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn antichess(fen: &str) -> Position {
+        Position { rules: Rules::Antichess, ..Position::parse(fen) }
+    }
+
+    #[test]
+    fn start_position() {
+        let p = antichess("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        assert_eq!(p.perft(1), 20);
+        assert_eq!(p.perft(2), 400);
+        assert_eq!(p.perft(3), 8067);
+    }
+
+    #[test]
+    fn captures_are_compulsory() {
+        let p = antichess("8/8/8/3p4/4P3/8/8/R7 w - - 0 1");
+        assert_eq!(p.legal_moves(), vec![Move::parse("e4d5")]);
+    }
+
+    #[test]
+    fn king_is_an_ordinary_piece() {
+        // no castling, no check, the king can be captured
+        let p = antichess("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!p.is_check());
+        assert!(p.legal_moves().iter().all(|mv| mv.castle == ::castle::NONE));
+        let p = antichess("8/8/8/8/8/8/8/k6R w - - 0 1");
+        assert_eq!(p.legal_moves(), vec![Move::parse("h1a1")]);
+        // promotion to king
+        let p = antichess("8/P7/8/8/8/8/8/7k w - - 0 1");
+        assert_eq!(p.legal_moves().len(), 5);
+        assert!(p.legal_moves().contains(&Move::parse("a7a8=K")));
+        assert!(p.validate().is_empty());
+    }
+
+    #[test]
+    fn losing_everything_wins() {
+        let p = antichess("8/8/8/8/8/8/8/k6R w - - 0 1").make_move(Move::parse("h1a1"));
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::Black)));
+        assert!(p.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn being_stalemated_wins() {
+        let p = antichess("8/8/8/8/8/p7/P7/8 w - - 0 1");
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::White)));
+    }
+}
@@ -0,0 +1,111 @@
+use super::*;
+use position::castling::spoiled_castling;
+use sided_mask::*;
+
+// Captures explode everything but pawns around the target square
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+    // kings can not capture
+    fn pseudo_legal_moves(&self, p: &Position, color: Color, moves: &mut Vec<Move>) {
+        let kings = p.board.kings_of(color);
+        let occupation = p.board.occupation();
+        moves.retain(|mv| {
+            mv.is_drop() || mv.castle != ::castle::NONE ||
+            !(kings.intersects(mv.from.mask()) && occupation.intersects(mv.to.mask()))
+        });
+    }
+    // A move must keep the own king on the board. Blowing up
+    // the enemy king is fine even if the own one is in check.
+    fn is_legal(&self, next: &Position, color: Color) -> bool {
+        if next.board.kings_of(color).count() == 0 {
+            return false;
+        }
+        next.board.kings_of(color.invert()).count() == 0 || !self.is_check(&next.board, color)
+    }
+    // Kings that touch each other can not be checked:
+    // capturing one of them would explode the other.
+    fn is_check(&self, board: &BitBoard, color: Color) -> bool {
+        let king = board.kings_of(color);
+        let enemy = board.kings_of(color.invert());
+        !king.king_attacks().intersects(enemy) && is_check_to(board, color)
+    }
+    // The capturing piece and every piece but pawns
+    // around the target square go away
+    fn after_move(&self, before: &Position, mv: Move, next: &mut Position) {
+        if mv.is_drop() || mv.castle != ::castle::NONE || before.captured_square(mv).is_none() {
+            return;
+        }
+        let to = mv.to.mask();
+        let pawns = next.board.pawns::<White>().mask() | next.board.pawns::<Black>().mask();
+        let blast = to | (to.king_attacks() & !pawns);
+        for m in blast.single_bits() {
+            next.board.remove_piece(m);
+            next.available.remove(spoiled_castling(m));
+        }
+    }
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        for &color in &[Color::White, Color::Black] {
+            if p.board.kings_of(color).count() == 0 {
+                return Some(Outcome::Win(color.invert()));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn atomic(fen: &str) -> Position {
+        Position { rules: Rules::Atomic, ..Position::parse(fen) }
+    }
+
+    #[test]
+    fn explosion_spares_pawns() {
+        let p = atomic("k7/8/2nbr3/3q4/2P5/8/3Q4/K7 w - - 0 1").make_move(Move::parse("d2d5"));
+        assert_eq!(format!("{}", p), "k7/8/8/8/2P5/8/8/K7 b - -");
+    }
+
+    #[test]
+    fn explosion_spoils_castling() {
+        let p = atomic("r3k2r/1p4n1/8/8/8/8/8/R3K1R1 w Qkq - 0 1").make_move(Move::parse("g1g7"));
+        assert_eq!(format!("{}", p), "r3k3/1p6/8/8/8/8/8/R3K3 b Qq -");
+    }
+
+    #[test]
+    fn kings_do_not_capture() {
+        let p = atomic("k7/8/8/8/8/8/1q6/K7 w - - 0 1");
+        assert!(p.legal_moves().is_empty());
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::Black)));
+    }
+
+    #[test]
+    fn own_king_must_survive() {
+        let p = atomic("4k3/8/8/8/8/8/4r3/3QK3 w - - 0 1");
+        assert_eq!(p.legal_moves(), vec![Move::parse("e1f1")]);
+    }
+
+    #[test]
+    fn exploding_the_enemy_king_wins() {
+        let p = atomic("4k3/5n2/8/7Q/8/8/4r3/4K3 w - - 0 1");
+        assert!(p.is_check());
+        let mv = Move::parse("h5f7");
+        assert!(p.legal_moves().contains(&mv));
+        let next = p.make_move(mv);
+        assert_eq!(next.outcome(), Some(Outcome::Win(Color::White)));
+        assert!(next.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn touching_kings_are_not_in_check() {
+        assert!(!atomic("8/8/8/8/8/3k4/3K4/7r w - - 0 1").is_check());
+        assert!(Position::parse("8/8/8/8/8/3k4/3K4/7r w - - 0 1").is_check());
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::{Formatter, Result};
+use super::*;
+use kind::*;
+use mask::masks;
+
+// Captured pieces go to the capturer's pocket and can be dropped back
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+    // Any piece in the pocket onto any empty square,
+    // except pawns onto the first and the last ranks.
+    fn pseudo_legal_moves(&self, p: &Position, color: Color, moves: &mut Vec<Move>) {
+        let empty = !p.board.occupation();
+        for kind in p.pockets.kinds_of(color) {
+            let targets = if kind == PAWN {
+                empty & !(masks::_1 | masks::_8)
+            } else {
+                empty
+            };
+            for to in targets.single_bits() {
+                moves.push(Move::drop(kind, square(to)));
+            }
+        }
+    }
+    // The captured piece goes to the capturer's pocket,
+    // as a pawn if it had been promoted.
+    fn after_move(&self, before: &Position, mv: Move, next: &mut Position) {
+        if mv.is_drop() || mv.castle != ::castle::NONE {
+            return;
+        }
+        if let Some(taken) = before.captured_square(mv) {
+            let captured = before.board.get_piece(taken);
+            let kind = if before.promoted.intersects(taken) {
+                PAWN
+            } else {
+                captured.kind()
+            };
+            // a pocket already full from the FEN stays full
+            let _ = next.pockets.add(kind.of(captured.color().invert()));
+            next.promoted &= !taken;
+        }
+        let (from, to) = (mv.from.mask(), mv.to.mask());
+        if mv.promote != UNKNOWN || before.promoted.intersects(from) {
+            next.promoted = (next.promoted & !from) | to;
+        }
+    }
+    fn fmt_board_suffix(&self, p: &Position, f: &mut Formatter) -> Result {
+        write!(f, "{}", p.pockets)
+    }
+    fn parse_board_suffix<'a>(&self, input: &'a [u8], p: &mut Position) -> Parsed<'a> {
+        use nom::{Err, ErrorKind, IResult};
+        if !input.starts_with(b"[") {
+            return Ok(None);
+        }
+        match ::pocket::parse_pockets(input) {
+            IResult::Done(rest, pockets) => {
+                p.pockets = pockets;
+                Ok(Some(rest))
+            }
+            IResult::Error(Err::Position(ErrorKind::Custom(e), _)) => Err(PositionError::Pockets(e)),
+            _ => Err(PositionError::Pockets(::pocket::ParsingError::UnrecognizedToken)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn after(fen: &str, mv: &str) -> String {
+        format!("{}", Position::parse(fen).make_move(Move::parse(mv)))
+    }
+
+    #[test]
+    fn drops() {
+        assert_eq!(Position::parse("k7/8/8/8/8/8/8/K7[Nn] w - - 0 1").legal_moves().len(),
+                   3 + 62);
+        // no pawns on the first and the last ranks
+        assert_eq!(Position::parse("k7/8/8/8/8/8/8/K7[P] w - - 0 1").legal_moves().len(),
+                   3 + 48);
+        assert_eq!(Position::parse("k7/8/8/8/8/8/8/K7[Nn] w - - 0 1").legal_moves().len(),
+                   Position::parse("k7/8/8/8/8/8/8/K7[n] b - - 0 1").legal_moves().len());
+        assert_eq!(Position::parse("k7/8/8/8/8/8/8/K7 w - - 0 1").legal_moves().len(), 3);
+    }
+
+    #[test]
+    fn drop_blocks_check() {
+        let p = Position::parse("k7/8/8/8/8/8/8/K6r[B] w - - 0 1");
+        let moves = p.legal_moves();
+        assert!(moves.contains(&Move::parse("B@d1")));
+        assert!(!moves.contains(&Move::parse("B@d4")));
+    }
+
+    #[test]
+    fn captures_go_to_the_pocket() {
+        assert_eq!(after("k7/8/8/8/8/8/1q6/K7[] w - - 0 1", "a1b2"),
+                   "k7/8/8/8/8/8/1K6/8[Q] b - -");
+        assert_eq!(after("k7/8/8/8/8/8/8/K7[Nn] w - - 0 1", "N@c7"),
+                   "k7/2N5/8/8/8/8/8/K7[n] b - -");
+        assert_eq!(after("k7/8/8/3pP3/8/8/8/K7[] w - d 0 1", "e5d6"),
+                   "k7/8/3P4/8/8/8/8/K7[P] b - -");
+    }
+
+    #[test]
+    fn promoted_pieces_revert_to_pawns() {
+        let p = Position::parse("1r6/P1k5/8/8/8/8/8/K7[] w - - 0 1");
+        let p = p.make_move(Move::parse("a7b8=Q"));
+        assert_eq!(format!("{}", p), "1Q6/2k5/8/8/8/8/8/K7[R] b - -");
+        let p = p.make_move(Move::parse("c7b8"));
+        assert_eq!(format!("{}", p), "1k6/8/8/8/8/8/8/K7[Rp] w - -");
+        assert_eq!(p.promoted, masks::EMPTY);
+    }
+}
@@ -0,0 +1,91 @@
+use super::*;
+use mask::masks::*;
+use position::validate_position::*;
+
+// 36 white pawns and no white king against the usual black army.
+// Black wins by taking every white piece, white still has to mate.
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Horde;
+
+impl Variant for Horde {
+    fn name(&self) -> &'static str {
+        "Horde"
+    }
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+    }
+    // pawns on the first rank may double-push too
+    fn pseudo_legal_moves(&self, p: &Position, color: Color, moves: &mut Vec<Move>) {
+        let empty = !p.board.occupation();
+        let (first, forward): (Mask, fn(Mask) -> Mask) = if color == Color::White {
+            (_1, Mask::shift_north)
+        } else {
+            (_8, Mask::shift_south)
+        };
+        for from in (p.board.pawns_of(color) & first).single_bits() {
+            let double = forward(forward(from) & empty) & empty;
+            if double != EMPTY {
+                moves.push(Move::new(square(from), square(double)));
+            }
+        }
+    }
+    // double pushes from the first rank can not be taken en passant
+    fn after_move(&self, before: &Position, mv: Move, next: &mut Position) {
+        let first = if before.active == Color::White { ::rank::_1 } else { ::rank::_8 };
+        if next.en_passant.is_some() && mv.from.rank() == first {
+            next.en_passant = None;
+        }
+    }
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        if p.board.occupation_of(Color::White).count() == 0 {
+            Some(Outcome::Win(Color::Black))
+        } else {
+            None
+        }
+    }
+    fn validate(&self, p: &Position) -> Assessment {
+        p.validate_standard() - (HAS_NO_WHITE_KING | WHITE_PAWNS_ON_FIRST_RANK)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn horde(fen: &str) -> Position {
+        Position { rules: Rules::Horde, ..Position::parse(fen) }
+    }
+
+    #[test]
+    fn start_position() {
+        let p = Position::start(Rules::Horde);
+        assert_eq!(p.board.pawns_of(Color::White).count(), 36);
+        assert_eq!(p.perft(1), 8);
+        assert_eq!(p.perft(2), 128);
+        assert_eq!(p.perft(3), 1274);
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(Position::start(Rules::Horde).validate(), VALID);
+        assert_eq!(Position::parse(Rules::Horde.start_fen()).validate(),
+                   HAS_NO_WHITE_KING | WHITE_PAWNS_ON_FIRST_RANK);
+    }
+
+    #[test]
+    fn first_rank_double_push() {
+        let p = horde("4k3/8/8/8/8/8/8/P7 w - - 0 1");
+        assert_eq!(p.legal_moves(), vec![Move::parse("a1a2"), Move::parse("a1a3")]);
+        // no en passant after it
+        assert_eq!(p.make_move(Move::parse("a1a3")).en_passant, None);
+    }
+
+    #[test]
+    fn taking_everything_wins() {
+        let p = horde("4k3/8/8/8/8/8/8/3rP3 b - - 0 1");
+        assert_eq!(p.outcome(), None);
+        let p = p.make_move(Move::parse("d1e1"));
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::Black)));
+    }
+}
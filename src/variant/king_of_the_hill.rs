@@ -0,0 +1,36 @@
+use super::*;
+use mask::masks::CENTER;
+
+// Bringing the king to the center wins
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        [Color::White, Color::Black]
+            .iter()
+            .find(|&&color| p.board.kings_of(color).intersects(CENTER))
+            .map(|&color| Outcome::Win(color))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    #[test]
+    fn king_in_the_center_wins() {
+        let p = Position {
+            rules: Rules::KingOfTheHill,
+            ..Position::parse("7k/8/8/8/8/4K3/8/8 w - - 0 1")
+        };
+        assert_eq!(p.outcome(), None);
+        let p = p.make_move(Move::parse("e3d4"));
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::White)));
+        assert!(p.legal_moves().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::{Formatter, Result};
+use bit_board::BitBoard;
+use color::Color;
+use mask::Mask;
+use moves::Move;
+use position::{Position, PositionError, Outcome};
+use position::validate_position::Assessment;
+use side::*;
+use square::Square;
+
+pub use self::crazyhouse::Crazyhouse;
+pub use self::atomic::Atomic;
+pub use self::antichess::Antichess;
+pub use self::three_check::ThreeCheck;
+pub use self::king_of_the_hill::KingOfTheHill;
+pub use self::horde::Horde;
+pub use self::racing_kings::RacingKings;
+
+mod crazyhouse;
+mod atomic;
+mod antichess;
+mod three_check;
+mod king_of_the_hill;
+mod horde;
+mod racing_kings;
+
+pub static STANDARD_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// What is left of the FEN after a variant read its bits,
+// None when they are not there
+pub type Parsed<'a> = ::std::result::Result<Option<&'a [u8]>, PositionError>;
+
+// Everything a variant may change about the rules. The defaults
+// are standard chess, so house rules only override what differs.
+// Plug one in with `Rules::Custom(&MY_RULES)`.
+pub trait Variant: Sync {
+    // as in the PGN `[Variant "..."]` tag
+    fn name(&self) -> &'static str;
+    fn start_fen(&self) -> &'static str {
+        STANDARD_FEN
+    }
+
+    // Adds or removes moves of `color` after the standard ones are generated
+    fn pseudo_legal_moves(&self, _p: &Position, _color: Color, _moves: &mut Vec<Move>) {}
+    // Whether `color` may make the move that lead to `next`
+    fn is_legal(&self, next: &Position, color: Color) -> bool {
+        !is_check_to(&next.board, color)
+    }
+    // The last say on the legal moves, e.g. compulsory captures
+    fn filter_legal(&self, _p: &Position, moves: Vec<Move>) -> Vec<Move> {
+        moves
+    }
+    fn is_check(&self, board: &BitBoard, color: Color) -> bool {
+        is_check_to(board, color)
+    }
+
+    // Side effects on top of moving the pieces
+    fn after_move(&self, _before: &Position, _mv: Move, _next: &mut Position) {}
+
+    // The ends the variant decides on its own, no matter which moves are left
+    fn outcome(&self, _p: &Position) -> Option<Outcome> {
+        None
+    }
+    // The side to move has no legal moves
+    fn no_moves(&self, p: &Position) -> Outcome {
+        if p.is_check() {
+            Outcome::Win(p.active.invert())
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    fn validate(&self, p: &Position) -> Assessment {
+        p.validate_standard()
+    }
+
+    // Extra FEN bits: right after the board and after the en-passant field
+    fn fmt_board_suffix(&self, _p: &Position, _f: &mut Formatter) -> Result {
+        Ok(())
+    }
+    fn fmt_extension(&self, _p: &Position, _f: &mut Formatter) -> Result {
+        Ok(())
+    }
+    // Reading them back. The first built-in variant whose bits are
+    // there decides the rules of a parsed FEN.
+    fn parse_board_suffix<'a>(&self, _input: &'a [u8], _p: &mut Position) -> Parsed<'a> {
+        Ok(None)
+    }
+    fn parse_extension<'a>(&self, _input: &'a [u8], _p: &mut Position) -> Parsed<'a> {
+        Ok(None)
+    }
+}
+
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+pub fn is_check_to(board: &BitBoard, color: Color) -> bool {
+    if color == Color::White {
+        board.is_check_to::<White>()
+    } else {
+        board.is_check_to::<Black>()
+    }
+}
+
+fn square(m: Mask) -> Square {
+    Square::from_bits(m.index_of_least_significant_bit() as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    // No castling, ever
+    struct NoCastling;
+
+    impl Variant for NoCastling {
+        fn name(&self) -> &'static str {
+            "No castling"
+        }
+        fn pseudo_legal_moves(&self, _p: &Position, _color: Color, moves: &mut Vec<Move>) {
+            moves.retain(|mv| mv.castle == ::castle::NONE);
+        }
+    }
+
+    static NO_CASTLING: NoCastling = NoCastling;
+
+    #[test]
+    fn house_rules() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let p = Position::parse(fen);
+        assert_eq!(p.legal_moves().len(), 26);
+        let p = Position { rules: Rules::Custom(&NO_CASTLING), ..p };
+        assert_eq!(p.legal_moves().len(), 24);
+        assert_eq!(p.rules.name(), "No castling");
+        assert_eq!(p.outcome(), None);
+    }
+}
@@ -0,0 +1,82 @@
+use super::*;
+use kind::KING;
+use mask::masks::_8;
+
+// No checks, bringing the king to the eighth rank wins
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct RacingKings;
+
+impl Variant for RacingKings {
+    fn name(&self) -> &'static str {
+        "Racing Kings"
+    }
+    fn start_fen(&self) -> &'static str {
+        "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1"
+    }
+    // Neither the own king may be left in check, nor the enemy one be given it
+    fn is_legal(&self, next: &Position, _color: Color) -> bool {
+        !is_check_to(&next.board, Color::White) && !is_check_to(&next.board, Color::Black)
+    }
+    // The first king on the eighth rank wins. White moves first,
+    // so when it gets there black still has a move to draw.
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        let white = p.board.kings_of(Color::White).intersects(_8);
+        let black = p.board.kings_of(Color::Black).intersects(_8);
+        match (white, black) {
+            (true, true) => Some(Outcome::Draw),
+            (false, true) => Some(Outcome::Win(Color::Black)),
+            (true, false) if p.active == Color::Black && self.black_can_follow(p) => None,
+            (true, false) => Some(Outcome::Win(Color::White)),
+            (false, false) => None,
+        }
+    }
+}
+
+impl RacingKings {
+    fn black_can_follow(&self, p: &Position) -> bool {
+        p.pseudo_legal_moves_of::<Black>()
+            .into_iter()
+            .filter(|mv| mv.to.mask().intersects(_8))
+            .filter(|mv| p.board.get_piece(mv.from.mask()).kind() == KING)
+            .any(|mv| self.is_legal(&p.make_move(mv), Color::Black))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rules::Rules;
+
+    fn racing(fen: &str) -> Position {
+        Position { rules: Rules::RacingKings, ..Position::parse(fen) }
+    }
+
+    #[test]
+    fn start_position() {
+        let p = Position::start(Rules::RacingKings);
+        assert_eq!(p.perft(1), 21);
+        assert_eq!(p.perft(2), 421);
+        assert_eq!(p.perft(3), 11264);
+    }
+
+    #[test]
+    fn no_checks() {
+        let p = racing("8/8/8/8/8/k7/8/1R5K w - - 0 1");
+        assert!(!p.legal_moves().contains(&Move::parse("b1a1")));
+        assert!(!p.legal_moves().contains(&Move::parse("b1b3")));
+        assert!(p.legal_moves().contains(&Move::parse("b1b2")));
+    }
+
+    #[test]
+    fn eighth_rank() {
+        let p = racing("8/6K1/8/8/8/8/k7/8 w - - 0 1").make_move(Move::parse("g7g8"));
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::White)));
+        // black can still get there
+        let p = racing("8/k5K1/8/8/8/8/8/8 w - - 0 1").make_move(Move::parse("g7g8"));
+        assert_eq!(p.outcome(), None);
+        let p = p.make_move(Move::parse("a7a8"));
+        assert_eq!(p.outcome(), Some(Outcome::Draw));
+        let p = racing("k7/8/8/8/8/8/6K1/8 w - - 0 1");
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::Black)));
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::{Formatter, Result};
+use super::*;
+
+// Giving the third check wins
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Hash)]
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - +0+0 0 1"
+    }
+    fn after_move(&self, before: &Position, _mv: Move, next: &mut Position) {
+        if next.is_check() {
+            let remaining = &mut next.remaining_checks[before.active as usize];
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+    fn outcome(&self, p: &Position) -> Option<Outcome> {
+        [Color::White, Color::Black]
+            .iter()
+            .find(|&&color| p.remaining_checks(color) == 0)
+            .map(|&color| Outcome::Win(color))
+    }
+    // checks given so far
+    fn fmt_extension(&self, p: &Position, f: &mut Formatter) -> Result {
+        write!(f, " +{}+{}",
+               3 - p.remaining_checks(Color::White),
+               3 - p.remaining_checks(Color::Black))
+    }
+    // " +2+0": checks given by white and by black
+    fn parse_extension<'a>(&self, input: &'a [u8], p: &mut Position) -> Parsed<'a> {
+        if !input.starts_with(b" +") {
+            return Ok(None);
+        }
+        let given = |i: usize| match input.get(i) {
+            Some(&c) if b'0' <= c && c <= b'3' => Some(c - b'0'),
+            _ => None,
+        };
+        match (given(2), input.get(3), given(4)) {
+            (Some(white), Some(&b'+'), Some(black)) => {
+                p.remaining_checks[Color::White as usize] = 3 - white;
+                p.remaining_checks[Color::Black as usize] = 3 - black;
+                Ok(Some(&input[5..]))
+            }
+            _ => Err(PositionError::Checks),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checks_are_counted() {
+        let p = Position::parse("4k3/8/8/8/8/8/8/R3K3 w - - +0+0 0 1");
+        let p = p.make_move(Move::parse("a1a8"));
+        assert_eq!(p.remaining_checks(Color::White), 2);
+        assert_eq!(format!("{}", p), "R3k3/8/8/8/8/8/8/4K3 b - - +1+0");
+        let p = p.make_move(Move::parse("e8e7"));
+        assert_eq!(p.remaining_checks(Color::Black), 3);
+        assert_eq!(p.outcome(), None);
+    }
+
+    #[test]
+    fn third_check_wins() {
+        let p = Position::parse("4k3/8/8/8/8/8/8/R3K3 w - - +2+0 0 1");
+        let p = p.make_move(Move::parse("a1a8"));
+        assert_eq!(p.outcome(), Some(Outcome::Win(Color::White)));
+        assert!(p.legal_moves().is_empty());
+    }
+}